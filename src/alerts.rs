@@ -0,0 +1,657 @@
+//! Alert rules notifying webhooks when an indicator meets a condition, e.g. the
+//! hourly RSI of BTC crossing above 70.
+//!
+//! A background scheduler evaluates every rule against freshly fetched candles,
+//! a rule firing at most once per cooldown. Each firing is POSTed to the webhook of
+//! the rule, signed with its secret, retried with exponential backoff, and recorded
+//! in a delivery log along with every attempt.
+//!
+//! Rules belong to the API key that created them, the others not seeing them.
+//!
+//! Webhooks may not point to loopback, private or link-local addresses, checked
+//! when a rule is saved and again on every attempt, which connects to the
+//! addresses it checked. Redirects aren't followed.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::web::Data;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use reqwest::redirect::Policy;
+use reqwest::{Client, ClientBuilder, StatusCode, Url};
+use sha2::Sha256;
+
+use crate::batch::{run_jobs, JobOutput};
+use crate::configuration::AlertSettings;
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+use crate::interval::Interval;
+use crate::live::Subscription;
+
+pub const MAX_RULES: usize = 1_000;
+/// Deliveries kept in the log, the oldest ones being dropped.
+const MAX_DELIVERIES: usize = 1_000;
+const MAX_CONCURRENT_FETCHES: usize = 8;
+const DEFAULT_COOLDOWN_SECONDS: u64 = 3_600;
+const MIN_SECRET_LENGTH: usize = 16;
+const INTERNAL_WEBHOOK: &str = "must not point to a loopback, private or link-local address";
+
+pub const SIGNATURE_HEADER: &str = "X-Alert-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Alert-Timestamp";
+pub const DELIVERY_HEADER: &str = "X-Alert-Delivery";
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    Above,
+    Below,
+    /// From at or below the threshold at the previous evaluation to above it.
+    CrossesAbove,
+    CrossesBelow,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Condition {
+    pub operator: Operator,
+    pub threshold: f64,
+}
+
+impl Condition {
+    /// Whether `current` meets the condition, `previous` being the value at the
+    /// previous evaluation.
+    pub fn holds(&self, previous: Option<f64>, current: f64) -> bool {
+        let threshold = self.threshold;
+        match self.operator {
+            Operator::Above => current > threshold,
+            Operator::Below => current < threshold,
+            Operator::CrossesAbove => {
+                previous.is_some_and(|p| p <= threshold) && current > threshold
+            }
+            Operator::CrossesBelow => {
+                previous.is_some_and(|p| p >= threshold) && current < threshold
+            }
+        }
+    }
+}
+
+/// A rule as created or replaced by a client.
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleRequest {
+    coin: String,
+    indicator: Indicator,
+    period: Option<usize>,
+    interval: Option<Interval>,
+    condition: Condition,
+    cooldown_seconds: Option<u64>,
+    webhook_url: String,
+    /// Signs the deliveries, generated when absent.
+    secret: Option<String>,
+}
+
+impl RuleRequest {
+    fn webhook_url(&self) -> Result<Url, ApiError> {
+        Url::parse(&self.webhook_url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .ok_or_else(|| ApiError::bad_parameter("webhookUrl", "must be an http(s) URL"))
+    }
+
+    fn rule(
+        self,
+        id: u64,
+        owner: Option<String>,
+        created_at: i64,
+        secret: Option<String>,
+    ) -> Result<Rule, ApiError> {
+        let subscription =
+            Subscription::new(&self.coin, self.indicator, self.period, self.interval)?;
+        if !self.condition.threshold.is_finite() {
+            return Err(ApiError::bad_parameter(
+                "condition",
+                "threshold must be a number",
+            ));
+        }
+        let webhook_url = self.webhook_url()?;
+        let secret = match self.secret.or(secret) {
+            Some(secret) if secret.len() < MIN_SECRET_LENGTH => {
+                return Err(ApiError::bad_parameter(
+                    "secret",
+                    format!("must be at least {} characters long", MIN_SECRET_LENGTH),
+                ))
+            }
+            Some(secret) => secret,
+            None => hex::encode(rand::thread_rng().gen::<[u8; 32]>()),
+        };
+        Ok(Rule {
+            id,
+            subscription,
+            condition: self.condition,
+            cooldown_seconds: self.cooldown_seconds.unwrap_or(DEFAULT_COOLDOWN_SECONDS),
+            webhook_url: webhook_url.to_string(),
+            secret,
+            owner,
+            created_at,
+            state: RuleState::default(),
+        })
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: u64,
+    #[serde(flatten)]
+    pub subscription: Subscription,
+    pub condition: Condition,
+    pub cooldown_seconds: u64,
+    pub webhook_url: String,
+    /// Only returned when the rule is created.
+    #[serde(skip)]
+    pub secret: String,
+    /// The `id` of the API key that created the rule, none without authentication.
+    #[serde(skip)]
+    pub owner: Option<String>,
+    pub created_at: i64,
+    #[serde(flatten)]
+    pub state: RuleState,
+}
+
+/// What the scheduler last saw of a rule.
+#[derive(serde::Serialize, Clone, Debug, Default, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleState {
+    pub last_value: Option<f64>,
+    pub last_evaluated_at: Option<i64>,
+    pub last_triggered_at: Option<i64>,
+    /// Why the last evaluation failed, e.g. an unknown coin.
+    pub last_error: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(serde::Serialize, Clone, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Attempt {
+    pub at: i64,
+    /// Absent when no response came back.
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Delivery {
+    pub id: u64,
+    pub rule_id: u64,
+    pub webhook_url: String,
+    pub status: DeliveryStatus,
+    pub payload: Payload,
+    pub attempts: Vec<Attempt>,
+}
+
+/// The body POSTed to the webhook.
+#[derive(serde::Serialize, Clone, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+    pub delivery_id: u64,
+    pub rule_id: u64,
+    #[serde(flatten)]
+    pub subscription: Subscription,
+    pub condition: Condition,
+    pub value: f64,
+    pub previous_value: Option<f64>,
+    /// Close and start of the newest candle.
+    pub close: f64,
+    pub timestamp: i64,
+    pub triggered_at: i64,
+}
+
+/// Why a webhook can't be called.
+enum Unreachable {
+    /// Its host didn't resolve, maybe only for a while.
+    Unresolved(String),
+    /// It is or resolves to a loopback, private or link-local address.
+    Internal,
+}
+
+/// The address of `url` when its host is one rather than a name.
+fn literal_address(url: &Url) -> Option<IpAddr> {
+    // IPv6 addresses are bracketed.
+    url.host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// The addresses of the host of `url`, none of which may be internal.
+async fn resolve_webhook(url: &Url) -> Result<Vec<SocketAddr>, Unreachable> {
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addresses: Vec<SocketAddr> = match (literal_address(url), url.host_str()) {
+        (Some(ip), _) => vec![SocketAddr::new(ip, port)],
+        (None, Some(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|err| Unreachable::Unresolved(format!("{} didn't resolve: {}", domain, err)))?
+            .collect(),
+        (None, None) => return Err(Unreachable::Internal),
+    };
+    if addresses.is_empty() {
+        return Err(Unreachable::Unresolved("no address found".to_owned()));
+    }
+    if addresses.iter().any(|address| is_internal(address.ip())) {
+        return Err(Unreachable::Internal);
+    }
+    Ok(addresses)
+}
+
+/// Loopback, private, link-local (cloud metadata services among them), shared,
+/// unspecified, broadcast and multicast addresses.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || first == 0
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (first == 100 && (64..128).contains(&second))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = embedded_ipv4(ip) {
+                return is_internal(ip.into());
+            }
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7, and link-local, fe80::/10.
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+        }
+    }
+}
+
+/// The IPv4 address reached through `ip`: IPv4-mapped and IPv4-compatible addresses,
+/// NAT64, 64:ff9b::/96, and 6to4, 2002::/16, all embed one.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    match ip.segments() {
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] | [0x2002, high, low, ..] => {
+            Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)))
+        }
+        _ => ip.to_ipv4(),
+    }
+}
+
+/// The hex HMAC-SHA256 of `{timestamp}.{body}`, sent as `sha256=<signature>`.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[derive(Default)]
+struct Store {
+    rules: BTreeMap<u64, Rule>,
+    last_rule_id: u64,
+    /// Oldest first.
+    deliveries: VecDeque<Delivery>,
+    last_delivery_id: u64,
+}
+
+impl Store {
+    /// A rule of `owner`, the rules of other owners not being found.
+    fn rule(&self, id: u64, owner: Option<&str>) -> Result<&Rule, ApiError> {
+        self.rules
+            .get(&id)
+            .filter(|rule| rule.owner.as_deref() == owner)
+            .ok_or(ApiError::NotFound)
+    }
+}
+
+pub struct Alerts {
+    store: Mutex<Store>,
+    http_client: Client,
+    settings: AlertSettings,
+}
+
+impl Alerts {
+    pub fn new(settings: AlertSettings) -> Self {
+        let http_client = client_builder(&settings)
+            .build()
+            .expect("Failed to build the http client.");
+        Self {
+            store: Mutex::new(Store::default()),
+            http_client,
+            settings,
+        }
+    }
+
+    /// False once a thread panicked while holding the rules.
+    pub fn is_healthy(&self) -> bool {
+        !self.store.is_poisoned()
+    }
+
+    /// Refuses a webhook pointing to an internal address. One that doesn't resolve
+    /// yet is accepted, every delivery checking it again.
+    async fn check_webhook(&self, rule: &Rule) -> Result<(), ApiError> {
+        let url = Url::parse(&rule.webhook_url).expect("A rule keeps a valid webhook URL");
+        if self.settings.allow_private_webhooks {
+            return Ok(());
+        }
+        match resolve_webhook(&url).await {
+            Err(Unreachable::Internal) => {
+                Err(ApiError::bad_parameter("webhookUrl", INTERNAL_WEBHOOK))
+            }
+            Ok(_) | Err(Unreachable::Unresolved(_)) => Ok(()),
+        }
+    }
+
+    /// The client calling `webhook_url`, connecting only to the addresses checked
+    /// so that the host can't resolve to an internal one in between.
+    async fn webhook_client(&self, webhook_url: &str) -> Result<Client, Unreachable> {
+        if self.settings.allow_private_webhooks {
+            return Ok(self.http_client.clone());
+        }
+        let url = Url::parse(webhook_url).map_err(|_| Unreachable::Internal)?;
+        let addresses = resolve_webhook(&url).await?;
+        match (literal_address(&url), url.host_str()) {
+            (None, Some(domain)) => client_builder(&self.settings)
+                .resolve_to_addrs(domain, &addresses)
+                .build()
+                .map_err(|err| Unreachable::Unresolved(err.to_string())),
+            _ => Ok(self.http_client.clone()),
+        }
+    }
+
+    pub async fn create(
+        &self,
+        request: RuleRequest,
+        owner: Option<String>,
+    ) -> Result<Rule, ApiError> {
+        // The fields are checked before resolving the webhook, numbering the rule once stored.
+        let mut rule = request.rule(0, owner, now(), None)?;
+        self.check_webhook(&rule).await?;
+        let mut store = self.store.lock().unwrap();
+        if store.rules.len() >= MAX_RULES {
+            return Err(ApiError::bad_parameter(
+                "rule",
+                format!("at most {} rules can be kept", MAX_RULES),
+            ));
+        }
+        rule.id = store.last_rule_id + 1;
+        store.last_rule_id = rule.id;
+        store.rules.insert(rule.id, rule.clone());
+        Ok(rule)
+    }
+
+    /// Replaces a rule, keeping its secret unless a new one is given and starting
+    /// its evaluation over.
+    pub async fn replace(
+        &self,
+        id: u64,
+        request: RuleRequest,
+        owner: Option<&str>,
+    ) -> Result<Rule, ApiError> {
+        let existing = self.get(id, owner)?;
+        let rule = request.rule(
+            id,
+            existing.owner,
+            existing.created_at,
+            Some(existing.secret),
+        )?;
+        self.check_webhook(&rule).await?;
+        let mut store = self.store.lock().unwrap();
+        // Deleted while the webhook was resolved.
+        store.rule(id, owner)?;
+        store.rules.insert(id, rule.clone());
+        Ok(rule)
+    }
+
+    pub fn get(&self, id: u64, owner: Option<&str>) -> Result<Rule, ApiError> {
+        self.store.lock().unwrap().rule(id, owner).cloned()
+    }
+
+    pub fn list(&self, owner: Option<&str>) -> Vec<Rule> {
+        self.store
+            .lock()
+            .unwrap()
+            .rules
+            .values()
+            .filter(|rule| rule.owner.as_deref() == owner)
+            .cloned()
+            .collect()
+    }
+
+    pub fn delete(&self, id: u64, owner: Option<&str>) -> Result<(), ApiError> {
+        let mut store = self.store.lock().unwrap();
+        store.rule(id, owner)?;
+        store.rules.remove(&id);
+        Ok(())
+    }
+
+    /// The deliveries of a rule still in the log, newest first.
+    pub fn deliveries(&self, rule_id: u64, owner: Option<&str>) -> Result<Vec<Delivery>, ApiError> {
+        let store = self.store.lock().unwrap();
+        store.rule(rule_id, owner)?;
+        Ok(store
+            .deliveries
+            .iter()
+            .rev()
+            .filter(|delivery| delivery.rule_id == rule_id)
+            .cloned()
+            .collect())
+    }
+
+    /// Evaluates every rule every `evaluation_interval` until the process exits.
+    pub async fn run_scheduler(alerts: Data<Alerts>, crypto_client: Data<CryptoClient>) {
+        let mut interval = tokio::time::interval(alerts.settings.evaluation_interval());
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            Alerts::evaluate(&alerts, &crypto_client).await;
+        }
+    }
+
+    /// Evaluates every rule once, fetching the candles shared by several rules only
+    /// once, and starts delivering the ones firing.
+    pub async fn evaluate(alerts: &Data<Alerts>, crypto_client: &CryptoClient) {
+        let mut subscriptions: Vec<Subscription> = vec![];
+        for rule in alerts.store.lock().unwrap().rules.values() {
+            if !subscriptions.contains(&rule.subscription) {
+                subscriptions.push(rule.subscription.clone());
+            }
+        }
+        if subscriptions.is_empty() {
+            return;
+        }
+        let jobs: Vec<_> = subscriptions.iter().map(Subscription::job).collect();
+        let outputs = run_jobs(crypto_client, &jobs, MAX_CONCURRENT_FETCHES).await;
+
+        let now = now();
+        let mut deliveries = vec![];
+        {
+            let mut store = alerts.store.lock().unwrap();
+            let store = &mut *store;
+            for rule in store.rules.values_mut() {
+                let Some(index) = subscriptions.iter().position(|s| *s == rule.subscription) else {
+                    // Created or replaced while the candles were being fetched.
+                    continue;
+                };
+                rule.state.last_evaluated_at = Some(now);
+                let output = match &outputs[index] {
+                    Ok(output) => output,
+                    Err(err) => {
+                        rule.state.last_error = Some(err.to_string());
+                        continue;
+                    }
+                };
+                rule.state.last_error = None;
+                let previous = rule
+                    .state
+                    .last_value
+                    .or_else(|| previous_value(rule, output));
+                rule.state.last_value = Some(output.value);
+
+                let cooled_down = rule
+                    .state
+                    .last_triggered_at
+                    .is_none_or(|triggered_at| now - triggered_at >= rule.cooldown_seconds as i64);
+                if !(cooled_down && rule.condition.holds(previous, output.value)) {
+                    continue;
+                }
+                rule.state.last_triggered_at = Some(now);
+                let newest = &output.candles.ohlc[0];
+                store.last_delivery_id += 1;
+                let delivery = Delivery {
+                    id: store.last_delivery_id,
+                    rule_id: rule.id,
+                    webhook_url: rule.webhook_url.clone(),
+                    status: DeliveryStatus::Pending,
+                    payload: Payload {
+                        delivery_id: store.last_delivery_id,
+                        rule_id: rule.id,
+                        subscription: rule.subscription.clone(),
+                        condition: rule.condition,
+                        value: output.value,
+                        previous_value: previous,
+                        close: newest.close,
+                        timestamp: newest.starting_at,
+                        triggered_at: now,
+                    },
+                    attempts: vec![],
+                };
+                if store.deliveries.len() == MAX_DELIVERIES {
+                    store.deliveries.pop_front();
+                }
+                store.deliveries.push_back(delivery.clone());
+                deliveries.push((delivery, rule.secret.clone()));
+            }
+        }
+
+        for (delivery, secret) in deliveries {
+            tokio::spawn(Alerts::deliver(alerts.clone(), delivery, secret));
+        }
+    }
+
+    /// POSTs a delivery until the webhook accepts it, it rejects it with a 4xx other
+    /// than 429, or the attempts run out.
+    async fn deliver(alerts: Data<Alerts>, delivery: Delivery, secret: String) {
+        let body = serde_json::to_string(&delivery.payload).expect("Serializable payload");
+        let mut backoff = Duration::from_millis(alerts.settings.initial_backoff_milliseconds);
+        for attempt in 1..=alerts.settings.max_attempts.max(1) {
+            let timestamp = now();
+            // The error, and whether it is final.
+            let outcome = match alerts.webhook_client(&delivery.webhook_url).await {
+                Ok(client) => client
+                    .post(&delivery.webhook_url)
+                    .header("Content-Type", "application/json")
+                    .header(DELIVERY_HEADER, delivery.id)
+                    .header(TIMESTAMP_HEADER, timestamp)
+                    .header(
+                        SIGNATURE_HEADER,
+                        format!("sha256={}", sign(&secret, timestamp, &body)),
+                    )
+                    .body(body.clone())
+                    .send()
+                    .await
+                    .map_err(|err| (err.to_string(), false)),
+                Err(Unreachable::Unresolved(message)) => Err((message, false)),
+                Err(Unreachable::Internal) => {
+                    Err((format!("the webhook {}", INTERNAL_WEBHOOK), true))
+                }
+            };
+            let (record, status) = match outcome {
+                Ok(response) => {
+                    let code = response.status();
+                    let status = if code.is_success() {
+                        Some(DeliveryStatus::Delivered)
+                    } else if code.is_client_error() && code != StatusCode::TOO_MANY_REQUESTS {
+                        Some(DeliveryStatus::Failed)
+                    } else {
+                        None
+                    };
+                    let error =
+                        (!code.is_success()).then(|| format!("the webhook answered {}", code));
+                    (
+                        Attempt {
+                            at: timestamp,
+                            status_code: Some(code.as_u16()),
+                            error,
+                        },
+                        status,
+                    )
+                }
+                Err((error, blocked)) => (
+                    Attempt {
+                        at: timestamp,
+                        status_code: None,
+                        error: Some(error),
+                    },
+                    blocked.then_some(DeliveryStatus::Failed),
+                ),
+            };
+            let status = status.or_else(|| {
+                (attempt == alerts.settings.max_attempts.max(1)).then_some(DeliveryStatus::Failed)
+            });
+            alerts.record(delivery.id, record, status);
+            if status.is_some() {
+                return;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    fn record(&self, delivery_id: u64, attempt: Attempt, status: Option<DeliveryStatus>) {
+        let mut store = self.store.lock().unwrap();
+        if let Some(delivery) = store
+            .deliveries
+            .iter_mut()
+            .find(|delivery| delivery.id == delivery_id)
+        {
+            delivery.attempts.push(attempt);
+            if let Some(status) = status {
+                delivery.status = status;
+            }
+        }
+    }
+}
+
+/// Webhooks time out and don't follow redirects, which could lead anywhere.
+fn client_builder(settings: &AlertSettings) -> ClientBuilder {
+    Client::builder()
+        .timeout(Duration::from_millis(settings.timeout_milliseconds))
+        .redirect(Policy::none())
+}
+
+/// The value on the candle before the newest one, for a rule evaluated for the
+/// first time to detect a crossing.
+fn previous_value(rule: &Rule, output: &JobOutput) -> Option<f64> {
+    let older = output.candles.ohlc.get(1..)?;
+    rule.subscription
+        .indicator
+        .compute(older, rule.subscription.period)
+        .ok()
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
@@ -0,0 +1,291 @@
+//! API key authentication, wrapped around every route by `startup::run`.
+//!
+//! A key comes in the `X-API-Key` header or the `api_key` query parameter, the
+//! latter for WebSocket and SSE clients which can't set headers. Only the SHA-256
+//! of each key is configured. Every key has a token bucket limiting its rate and a
+//! quota of requests per UTC day, both reported in the headers of every response.
+//! The `/admin` routes also need the `admin` scope, and the key of a request is
+//! left in its extensions as a `Caller` for the routes scoping data to it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::web::{self, Data};
+use actix_web::{HttpMessage, ResponseError};
+use sha2::{Digest, Sha256};
+
+use crate::configuration::{ApiKeySettings, AuthSettings, Scope};
+use crate::error::ApiError;
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "X-RateLimit-Limit";
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "X-RateLimit-Remaining";
+pub const QUOTA_LIMIT_HEADER: &str = "X-Quota-Limit";
+pub const QUOTA_REMAINING_HEADER: &str = "X-Quota-Remaining";
+
+/// The hex SHA-256 of a key, as configured in `secret_hash`.
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[derive(serde::Deserialize)]
+struct KeysFile {
+    keys: Vec<ApiKeySettings>,
+}
+
+#[derive(serde::Deserialize)]
+struct KeyQuery {
+    api_key: Option<String>,
+}
+
+/// The key a request was made with.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    /// The `id` of the key.
+    pub key_id: String,
+    pub scopes: Vec<Scope>,
+}
+
+pub struct ApiKeys {
+    enabled: bool,
+    public_paths: Vec<String>,
+    /// By the hash of the key.
+    keys: HashMap<String, Mutex<KeyState>>,
+}
+
+struct KeyState {
+    caller: Caller,
+    bucket: TokenBucket,
+    quota: Option<Quota>,
+}
+
+impl KeyState {
+    fn usage(&self) -> Usage {
+        Usage {
+            burst: self.bucket.capacity as u32,
+            tokens: self.bucket.tokens as u32,
+            quota: self
+                .quota
+                .as_ref()
+                .map(|quota| (quota.limit, quota.limit.saturating_sub(quota.used))),
+        }
+    }
+}
+
+/// Holds up to `capacity` requests, refilled continuously.
+struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, per_second: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            per_second,
+            tokens: capacity as f64,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated_at = now;
+    }
+
+    /// Time until a token is available again.
+    fn retry_after(&self) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.per_second).max(0.0))
+    }
+}
+
+struct Quota {
+    limit: u64,
+    used: u64,
+    day: chrono::NaiveDate,
+}
+
+/// What is left to a key once its request is counted.
+pub struct Usage {
+    burst: u32,
+    tokens: u32,
+    quota: Option<(u64, u64)>,
+}
+
+impl Usage {
+    fn insert_headers(&self, headers: &mut HeaderMap) {
+        let mut insert = |name: &str, value: u64| {
+            headers.insert(
+                HeaderName::try_from(name).expect("A valid header name"),
+                HeaderValue::from(value),
+            );
+        };
+        insert(RATE_LIMIT_LIMIT_HEADER, self.burst as u64);
+        insert(RATE_LIMIT_REMAINING_HEADER, self.tokens as u64);
+        if let Some((limit, remaining)) = self.quota {
+            insert(QUOTA_LIMIT_HEADER, limit);
+            insert(QUOTA_REMAINING_HEADER, remaining);
+        }
+    }
+}
+
+impl ApiKeys {
+    /// Reads the keys of the configuration and of its `keys_file`.
+    pub fn new(settings: &AuthSettings) -> Result<Self, String> {
+        let mut configured = settings.keys.clone();
+        if let Some(path) = &settings.keys_file {
+            let file: KeysFile = config::Config::builder()
+                .add_source(config::File::with_name(path))
+                .build()
+                .and_then(|file| file.try_deserialize())
+                .map_err(|err| format!("Failed to read the API keys in `{}`: {}", path, err))?;
+            configured.extend(file.keys);
+        }
+        if settings.enabled && configured.is_empty() {
+            return Err("Authentication is enabled but no API key is configured.".to_owned());
+        }
+
+        let mut keys = HashMap::new();
+        let mut ids = vec![];
+        for key in configured {
+            // Rules and other data are scoped to the id of a key.
+            if ids.contains(&key.id) {
+                return Err(format!("The API key id `{}` is used twice.", key.id));
+            }
+            ids.push(key.id.clone());
+            let hash = key.secret_hash.trim().to_lowercase();
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "The `secret_hash` of the API key `{}` is not a hex SHA-256.",
+                    key.id
+                ));
+            }
+            let per_second = key
+                .requests_per_second
+                .unwrap_or(settings.requests_per_second);
+            if per_second <= 0.0 {
+                return Err(format!(
+                    "The API key `{}` must allow some requests per second.",
+                    key.id
+                ));
+            }
+            let state = KeyState {
+                caller: Caller {
+                    key_id: key.id.clone(),
+                    scopes: key.scopes.clone(),
+                },
+                bucket: TokenBucket::new(key.burst.unwrap_or(settings.burst).max(1), per_second),
+                quota: key.daily_quota.or(settings.daily_quota).map(|limit| Quota {
+                    limit,
+                    used: 0,
+                    day: chrono::Utc::now().date_naive(),
+                }),
+            };
+            if keys.insert(hash, Mutex::new(state)).is_some() {
+                return Err(format!("The API key `{}` is configured twice.", key.id));
+            }
+        }
+        Ok(Self {
+            enabled: settings.enabled,
+            public_paths: settings.public_paths.clone(),
+            keys,
+        })
+    }
+
+    /// Counts a request of `key` against its rate limit and quota.
+    pub fn check(&self, key: Option<&str>) -> Result<(Caller, Usage), ApiError> {
+        let state = key
+            .and_then(|key| self.keys.get(&hash_key(key)))
+            .ok_or(ApiError::Unauthorized)?;
+        let mut state = state.lock().unwrap();
+        let now = chrono::Utc::now();
+        if let Some(quota) = &mut state.quota {
+            if quota.day != now.date_naive() {
+                quota.day = now.date_naive();
+                quota.used = 0;
+            }
+            if quota.used >= quota.limit {
+                let until_midnight = 86_400 - now.timestamp().rem_euclid(86_400);
+                return Err(ApiError::QuotaExceeded {
+                    retry_after: Duration::from_secs(until_midnight as u64),
+                });
+            }
+        }
+        state.bucket.refill();
+        if state.bucket.tokens < 1.0 {
+            return Err(ApiError::RateLimited {
+                retry_after: state.bucket.retry_after(),
+            });
+        }
+        state.bucket.tokens -= 1.0;
+        if let Some(quota) = &mut state.quota {
+            quota.used += 1;
+        }
+        Ok((state.caller.clone(), state.usage()))
+    }
+
+    /// What is left to a key without counting a request, for the headers of a
+    /// rejected one.
+    fn usage(&self, key: &str) -> Option<Usage> {
+        let mut state = self.keys.get(&hash_key(key))?.lock().unwrap();
+        state.bucket.refill();
+        Some(state.usage())
+    }
+}
+
+/// Rejects the requests to non-public paths without a valid key, beyond its rate
+/// limit or quota, or to the `/admin` routes without the `admin` scope.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let api_keys = req
+        .app_data::<Data<ApiKeys>>()
+        .expect("ApiKeys are registered in `startup::run`")
+        .clone();
+    if !api_keys.enabled || api_keys.public_paths.iter().any(|path| path == req.path()) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+        .or_else(|| {
+            web::Query::<KeyQuery>::from_query(req.query_string())
+                .ok()
+                .and_then(|query| query.into_inner().api_key)
+        });
+    let checked = api_keys.check(key.as_deref()).and_then(|(caller, usage)| {
+        let admin = req.path() == "/admin" || req.path().starts_with("/admin/");
+        if admin && !caller.scopes.contains(&Scope::Admin) {
+            return Err(ApiError::Forbidden);
+        }
+        Ok((caller, usage))
+    });
+    match checked {
+        Ok((caller, usage)) => {
+            req.extensions_mut().insert(caller);
+            let mut response = next.call(req).await?;
+            usage.insert_headers(response.headers_mut());
+            Ok(response.map_into_left_body())
+        }
+        Err(err) => {
+            let mut response = err.error_response();
+            if let Some(usage) = key.as_deref().and_then(|key| api_keys.usage(key)) {
+                usage.insert_headers(response.headers_mut());
+            }
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Replaying candles through a long-only strategy whose entry and exit rules are
+//! conditions of the expression language, e.g. `crosses_above(ema(close, 12),
+//! ema(close, 26))`.
+//!
+//! A rule holding on the close of a candle is filled at the open of the next one,
+//! so that no fill uses a price the rule couldn't have seen. Buys fill `slippage`
+//! above the open and sells below it, and each fill pays `fee` on its notional.
+//! A position still open after the last candle is sold at its close.
+
+use crate::crypto_client::{OhlcParams, ParsedOhlc};
+use crate::error::ApiError;
+use crate::expression::{CandleContext, Expr, ExpressionError, Series};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 86400.0;
+
+/// Fractions of the traded notional, e.g. `0.001` for 0.1%.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(default)]
+pub struct Costs {
+    pub fee: f64,
+    pub slippage: f64,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Self {
+            fee: 0.001,
+            slippage: 0.0005,
+        }
+    }
+}
+
+pub struct Strategy {
+    entry: Expr,
+    exit: Expr,
+    costs: Costs,
+}
+
+impl Strategy {
+    pub fn new(entry: &str, exit: &str, costs: Costs) -> Result<Self, ApiError> {
+        if !(0.0..0.5).contains(&costs.fee) {
+            return Err(ApiError::bad_parameter("fee", "must be between 0 and 0.5"));
+        }
+        if !(0.0..0.5).contains(&costs.slippage) {
+            return Err(ApiError::bad_parameter(
+                "slippage",
+                "must be between 0 and 0.5",
+            ));
+        }
+        Ok(Self {
+            entry: rule("entry", entry)?,
+            exit: rule("exit", exit)?,
+            costs,
+        })
+    }
+
+    /// The candles needed before both rules are defined, capped at the largest
+    /// limit since no more candles can be replayed anyway.
+    pub fn lookback(&self) -> usize {
+        self.entry
+            .lookback()
+            .max(self.exit.lookback())
+            .min(OhlcParams::MAX_LIMIT as usize)
+    }
+
+    /// Replays `candles`, oldest first, starting with `initial_capital` in cash.
+    pub fn run(&self, candles: &[ParsedOhlc], initial_capital: f64) -> Result<Report, ApiError> {
+        let context = CandleContext::new(candles);
+        let entries = conditions("entry", self.entry.evaluate(&context))?;
+        let exits = conditions("exit", self.exit.evaluate(&context))?;
+
+        let mut cash = initial_capital;
+        let mut position: Option<Position> = None;
+        let mut trades = vec![];
+        let mut equity = Vec::with_capacity(candles.len());
+        for (i, candle) in candles.iter().enumerate() {
+            let fillable = i > 0 && candle.open.is_finite();
+            match position.take() {
+                Some(open) if fillable && exits[i - 1] == Some(true) => {
+                    let (trade, proceeds) =
+                        open.close(candle.starting_at, candle.open, &self.costs, "signal");
+                    cash += proceeds;
+                    trades.push(trade);
+                }
+                None if fillable && entries[i - 1] == Some(true) => {
+                    let price = candle.open * (1.0 + self.costs.slippage);
+                    let quantity = cash / (price * (1.0 + self.costs.fee));
+                    position = Some(Position {
+                        entry_time: candle.starting_at,
+                        entry_price: price,
+                        quantity,
+                        cost: cash,
+                    });
+                    cash = 0.0;
+                }
+                kept => position = kept,
+            }
+            let holdings = position
+                .as_ref()
+                .map_or(0.0, |open| open.quantity * candle.close);
+            equity.push(EquityPoint {
+                timestamp: candle.starting_at,
+                equity: cash + holdings,
+            });
+        }
+        if let (Some(open), Some(last)) = (position, candles.last()) {
+            // Sold at the last close, the curve ending on the cash it returns.
+            let (trade, proceeds) =
+                open.close(last.starting_at, last.close, &self.costs, "end_of_data");
+            cash += proceeds;
+            trades.push(trade);
+            if let Some(point) = equity.last_mut() {
+                point.equity = cash;
+            }
+        }
+
+        let metrics = Metrics::new(candles, initial_capital, &trades, &equity);
+        Ok(Report {
+            trades,
+            equity,
+            metrics,
+        })
+    }
+}
+
+fn rule(parameter: &str, source: &str) -> Result<Expr, ApiError> {
+    let expr =
+        Expr::parse(source).map_err(|err| ApiError::bad_parameter(parameter, err.to_string()))?;
+    CandleContext::check(&expr).map_err(|message| ApiError::bad_parameter(parameter, message))?;
+    if !expr.is_condition() {
+        return Err(ApiError::bad_parameter(
+            parameter,
+            "must be a condition, e.g. `rsi(14) < 30`",
+        ));
+    }
+    Ok(expr)
+}
+
+fn conditions(
+    parameter: &str,
+    series: Result<Series, ExpressionError>,
+) -> Result<Vec<Option<bool>>, ApiError> {
+    match series {
+        Ok(Series::Conditions(conditions)) => Ok(conditions),
+        Ok(Series::Numbers(_)) => Err(ApiError::bad_parameter(parameter, "must be a condition")),
+        Err(err) => Err(ApiError::bad_parameter(parameter, err.to_string())),
+    }
+}
+
+struct Position {
+    entry_time: i64,
+    entry_price: f64,
+    quantity: f64,
+    /// Cash spent, fee included.
+    cost: f64,
+}
+
+impl Position {
+    /// The trade and the cash it returns.
+    fn close(self, time: i64, price: f64, costs: &Costs, reason: &'static str) -> (Trade, f64) {
+        let exit_price = price * (1.0 - costs.slippage);
+        let notional = self.quantity * exit_price;
+        let fees = self.cost - self.quantity * self.entry_price + notional * costs.fee;
+        let proceeds = notional * (1.0 - costs.fee);
+        let trade = Trade {
+            entry_time: self.entry_time,
+            entry_price: self.entry_price,
+            exit_time: time,
+            exit_price,
+            quantity: self.quantity,
+            fees,
+            pnl: proceeds - self.cost,
+            return_: proceeds / self.cost - 1.0,
+            exit_reason: reason,
+        };
+        (trade, proceeds)
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+    pub entry_time: i64,
+    /// Slippage included, as for `exit_price`.
+    pub entry_price: f64,
+    pub exit_time: i64,
+    pub exit_price: f64,
+    pub quantity: f64,
+    pub fees: f64,
+    /// Net of fees.
+    pub pnl: f64,
+    #[serde(rename = "return")]
+    pub return_: f64,
+    /// `signal`, or `end_of_data` for a position closed after the last candle.
+    pub exit_reason: &'static str,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+pub struct EquityPoint {
+    pub timestamp: i64,
+    pub equity: f64,
+}
+
+/// Ratios are fractions, `None` where they are undefined, e.g. the win rate
+/// without trades or the Sharpe ratio of a flat curve.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+    pub initial_capital: f64,
+    pub final_equity: f64,
+    pub total_return: f64,
+    /// The largest fall from a peak of the equity curve, relative to that peak.
+    pub max_drawdown: f64,
+    pub trades: usize,
+    pub win_rate: Option<f64>,
+    /// Annualised from the returns between candles.
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+}
+
+impl Metrics {
+    fn new(
+        candles: &[ParsedOhlc],
+        initial_capital: f64,
+        trades: &[Trade],
+        equity: &[EquityPoint],
+    ) -> Self {
+        let final_equity = equity.last().map_or(initial_capital, |point| point.equity);
+
+        let mut peak = initial_capital;
+        let mut max_drawdown: f64 = 0.0;
+        for point in equity {
+            peak = peak.max(point.equity);
+            max_drawdown = max_drawdown.max((peak - point.equity) / peak);
+        }
+
+        let wins = trades.iter().filter(|trade| trade.pnl > 0.0).count();
+        let win_rate = (!trades.is_empty()).then(|| wins as f64 / trades.len() as f64);
+
+        let returns: Vec<f64> = std::iter::once(initial_capital)
+            .chain(equity.iter().map(|point| point.equity))
+            .collect::<Vec<f64>>()
+            .windows(2)
+            .map(|pair| pair[1] / pair[0] - 1.0)
+            .collect();
+        let periods_per_year = match (candles.first(), candles.last()) {
+            (Some(first), Some(last))
+                if candles.len() > 1 && last.starting_at > first.starting_at =>
+            {
+                let spacing =
+                    (last.starting_at - first.starting_at) as f64 / (candles.len() - 1) as f64;
+                SECONDS_PER_YEAR / spacing
+            }
+            _ => 0.0,
+        };
+        let (sharpe, sortino) = if returns.len() < 2 || periods_per_year == 0.0 {
+            (None, None)
+        } else {
+            let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+            let deviation = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
+                / (returns.len() - 1) as f64)
+                .sqrt();
+            let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>()
+                / returns.len() as f64)
+                .sqrt();
+            let annualise = |risk: f64| (risk > 0.0).then(|| mean / risk * periods_per_year.sqrt());
+            (annualise(deviation), annualise(downside))
+        };
+
+        Self {
+            initial_capital,
+            final_equity,
+            total_return: final_equity / initial_capital - 1.0,
+            max_drawdown,
+            trades: trades.len(),
+            win_rate,
+            sharpe,
+            sortino,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = BacktestReport)]
+pub struct Report {
+    pub trades: Vec<Trade>,
+    /// Marked at every close.
+    pub equity: Vec<EquityPoint>,
+    pub metrics: Metrics,
+}
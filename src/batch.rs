@@ -0,0 +1,79 @@
+//! Computing many indicators for many coins at once, each coin being resolved and
+//! each distinct set of candles being fetched only once.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::stream::{self, StreamExt};
+
+use crate::candles::{fetch_candles, CandleRequest, Candles};
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub coin: String,
+    pub indicator: Indicator,
+    pub period: usize,
+    pub candles: CandleRequest,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobOutput {
+    pub value: f64,
+    /// The candles the value was computed on, shared by the jobs asking for the same ones.
+    pub candles: Arc<Candles>,
+}
+
+/// Runs every job, with at most `concurrency` upstream calls in flight. Results come
+/// back in the order of the jobs, a failing job leaving the others unaffected.
+pub async fn run_jobs(
+    crypto_client: &CryptoClient,
+    jobs: &[Job],
+    concurrency: usize,
+) -> Vec<Result<JobOutput, ApiError>> {
+    let mut coins: Vec<String> = jobs.iter().map(|job| job.coin.to_uppercase()).collect();
+    coins.sort();
+    coins.dedup();
+    let uuids: HashMap<String, Result<String, ApiError>> = stream::iter(coins)
+        .map(|coin| async move {
+            let uuid = crypto_client.get_coin_uuid(&coin).await;
+            (coin, uuid)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut fetches: Vec<(String, CandleRequest)> = vec![];
+    for job in jobs {
+        if let Some(Ok(uuid)) = uuids.get(&job.coin.to_uppercase()) {
+            let fetch = (uuid.clone(), job.candles.clone());
+            if !fetches.contains(&fetch) {
+                fetches.push(fetch);
+            }
+        }
+    }
+    let candles: HashMap<(String, CandleRequest), Result<Arc<Candles>, ApiError>> =
+        stream::iter(fetches)
+            .map(|(uuid, request)| async move {
+                let candles = fetch_candles(crypto_client, &uuid, &request)
+                    .await
+                    .map(Arc::new);
+                ((uuid, request), candles)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+
+    jobs.iter()
+        .map(|job| {
+            let uuid = uuids[&job.coin.to_uppercase()].clone()?;
+            let candles = candles[&(uuid, job.candles.clone())].clone()?;
+            Ok(JobOutput {
+                value: job.indicator.compute(&candles.ohlc, job.period)?,
+                candles,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,141 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A small thread-safe cache where every entry carries its own time-to-live.
+///
+/// When the cache is full, expired entries are dropped first and then the
+/// entry closest to its expiry is evicted to make room for the new one.
+pub struct TtlCache<K, V> {
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    max_entries: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            max_entries,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let mut entries = self.entries.lock().unwrap();
+        let value = match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        match value {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        value
+    }
+
+    /// Stores `value` for `ttl`. A zero ttl or a zero-sized cache stores nothing.
+    pub fn insert(&self, key: K, value: V, ttl: Duration) {
+        if ttl.is_zero() || self.max_entries == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let before = entries.len();
+            entries.retain(|_, entry| entry.expires_at > now);
+            let mut evicted = (before - entries.len()) as u64;
+
+            if entries.len() >= self.max_entries {
+                let soonest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(soonest) = soonest {
+                    entries.remove(&soonest);
+                    evicted += 1;
+                }
+            }
+            self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        }
+
+        entries.insert(
+            key,
+            Entry {
+                value,
+                expires_at: now + ttl,
+            },
+        );
+    }
+
+    /// Removes every entry and returns how many were dropped.
+    pub fn purge(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let purged = entries.len();
+        entries.clear();
+        purged
+    }
+
+    /// False once a thread panicked while holding the entries, after which every
+    /// access panics too.
+    pub fn is_healthy(&self) -> bool {
+        !self.entries.is_poisoned()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap().len();
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+
+        CacheStats {
+            entries,
+            max_entries: self.max_entries,
+            hits,
+            misses,
+            evictions: self.evictions.load(Ordering::Relaxed),
+            hit_ratio: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: usize,
+    pub max_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub hit_ratio: f64,
+}
@@ -0,0 +1,102 @@
+use crate::crypto_client::{CryptoClient, OhlcParams, ParsedOhlc};
+use crate::error::ApiError;
+use crate::interval::Interval;
+use crate::resample::{self, Gap, GapPolicy};
+
+/// Candles an indicator is computed on, in any interval: the ones Coinranking
+/// doesn't provide are resampled from a finer native one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CandleRequest {
+    pub interval: Interval,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// Number of `interval` candles wanted.
+    pub limit: u32,
+    pub gaps: GapPolicy,
+    /// Skips the cached candles, for callers watching the forming one.
+    pub latest: bool,
+}
+
+impl Default for CandleRequest {
+    fn default() -> Self {
+        Self {
+            interval: Interval::default(),
+            from: None,
+            to: None,
+            limit: OhlcParams::DEFAULT_LIMIT,
+            gaps: GapPolicy::default(),
+            latest: false,
+        }
+    }
+}
+
+impl CandleRequest {
+    /// The most candles of `interval` a request can ask for, each resampled one
+    /// taking several native ones from the upstream.
+    pub fn max_limit(interval: Interval) -> u32 {
+        let ratio = ratio(interval);
+        (OhlcParams::MAX_LIMIT - (ratio - 1)) / ratio
+    }
+}
+
+/// The native candles making up one candle of `interval`.
+fn ratio(interval: Interval) -> u32 {
+    (interval.seconds() / interval.native_source().seconds()) as u32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candles {
+    pub interval: Interval,
+    /// The native interval fetched from the upstream.
+    pub source_interval: Interval,
+    /// Newest first.
+    pub ohlc: Vec<ParsedOhlc>,
+    /// Missing source candles, only reported by the `mark` and `forward_fill` policies.
+    pub gaps: Vec<Gap>,
+}
+
+pub async fn fetch_candles(
+    crypto_client: &CryptoClient,
+    coin_uuid: &str,
+    request: &CandleRequest,
+) -> Result<Candles, ApiError> {
+    let max_limit = CandleRequest::max_limit(request.interval);
+    if request.limit > max_limit {
+        return Err(ApiError::bad_parameter(
+            "limit",
+            format!(
+                "must be at most {} for `{}` candles",
+                max_limit, request.interval
+            ),
+        ));
+    }
+    let source_interval = request.interval.native_source();
+    let ratio = ratio(request.interval);
+    // One bucket more than needed, the oldest one is likely to be incomplete.
+    let params = OhlcParams {
+        interval: source_interval,
+        from: request.from,
+        to: request.to,
+        limit: request.limit * ratio + ratio - 1,
+    };
+
+    let data = if request.latest {
+        crypto_client.refresh_coin_ohlc(coin_uuid, &params).await?
+    } else {
+        crypto_client.get_coin_ohlc(coin_uuid, &params).await?
+    };
+    let (source, gaps) = resample::fill_gaps(data.ohlc, source_interval, request.gaps);
+    let mut ohlc = if source_interval == request.interval {
+        source
+    } else {
+        resample::resample(&source, request.interval)
+    };
+    ohlc.truncate(request.limit as usize);
+
+    Ok(Candles {
+        interval: request.interval,
+        source_interval,
+        ohlc,
+        gaps,
+    })
+}
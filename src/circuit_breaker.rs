@@ -0,0 +1,98 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Stops calling an unhealthy dependency for a while after too many consecutive failures.
+pub struct CircuitBreaker {
+    state: Mutex<State>,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+enum State {
+    Closed {
+        consecutive_failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// The open period elapsed and a single trial call is in flight. If it never
+    /// reports back (e.g. it was cancelled) another trial is allowed after `until`.
+    HalfOpen {
+        until: Instant,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            state: Mutex::new(State::Closed {
+                consecutive_failures: 0,
+            }),
+            failure_threshold,
+            open_duration,
+        }
+    }
+
+    /// Whether a call may go through. Once the open period is over the first caller
+    /// is let through as a trial, the others keep failing fast until it reports back.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } | State::HalfOpen { until } if now >= until => {
+                *state = State::HalfOpen {
+                    until: now + self.open_duration,
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = State::Closed {
+            consecutive_failures: 0,
+        };
+    }
+
+    pub fn record_failure(&self) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let consecutive_failures = match *state {
+            State::Closed {
+                consecutive_failures,
+            } => consecutive_failures + 1,
+            State::Open { .. } | State::HalfOpen { .. } => self.failure_threshold,
+        };
+
+        *state = if consecutive_failures >= self.failure_threshold {
+            State::Open {
+                until: Instant::now() + self.open_duration,
+            }
+        } else {
+            State::Closed {
+                consecutive_failures,
+            }
+        };
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
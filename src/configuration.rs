@@ -1,3 +1,6 @@
+use crate::indicators::Indicator;
+use crate::interval::Interval;
+use crate::missing_data::MissingDataPolicy;
 use secrecy::Secret;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use std::convert::{TryFrom, TryInto};
@@ -6,6 +9,20 @@ use std::convert::{TryFrom, TryInto};
 pub struct Settings {
     pub application: ApplicationSettings,
     pub crypto_client: CryptoClientSettings,
+    #[serde(default)]
+    pub optimize: OptimizeSettings,
+    #[serde(default)]
+    pub live: LiveSettings,
+    #[serde(default)]
+    pub alerts: AlertSettings,
+    #[serde(default)]
+    pub precompute: PrecomputeSettings,
+    #[serde(default)]
+    pub auth: AuthSettings,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    #[serde(default)]
+    pub readiness: ReadinessSettings,
 }
 
 #[derive(serde::Deserialize)]
@@ -13,6 +30,318 @@ pub struct CryptoClientSettings {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub base_url: String,
     pub auth_token: Secret<String>,
+    #[serde(default = "default_timeout_milliseconds")]
+    pub timeout_milliseconds: u64,
+    /// How `null` or non-numeric upstream values are handled.
+    #[serde(default)]
+    pub missing_data: MissingDataPolicy,
+    #[serde(default)]
+    pub retry: RetrySettings,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerSettings,
+    #[serde(default)]
+    pub cache: CacheSettings,
+}
+
+impl CryptoClientSettings {
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+fn default_timeout_milliseconds() -> u64 {
+    10_000
+}
+
+/// Retries of failed upstream calls: timeouts, connection errors, 5xx and 429.
+/// The delay doubles at every attempt, with random jitter, unless the upstream
+/// asks for a specific one through `Retry-After`.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetrySettings {
+    pub max_retries: u32,
+    pub initial_backoff_milliseconds: u64,
+    /// Also the longest `Retry-After` we are willing to wait for, longer ones are
+    /// passed on to our own client as a 429.
+    pub max_backoff_milliseconds: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff_milliseconds: 200,
+            max_backoff_milliseconds: 5_000,
+        }
+    }
+}
+
+/// After `failure_threshold` consecutive failed upstream calls every request fails
+/// fast for `open_seconds`, then a single trial call decides whether to close it again.
+/// A threshold of 0 disables the breaker.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CircuitBreakerSettings {
+    pub failure_threshold: u32,
+    pub open_seconds: u64,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_seconds: 30,
+        }
+    }
+}
+
+/// Time-to-live and size limits of the in-process cache kept by `CryptoClient`.
+/// Every field has a default, so the whole section can be omitted.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Maximum number of entries kept for each kind of cached data.
+    pub max_entries: usize,
+    /// Symbol -> UUID mappings practically never change.
+    pub uuid_ttl_seconds: u64,
+    /// OHLC data is cached until the current candle closes, but never longer than this.
+    pub ohlc_max_ttl_seconds: u64,
+    /// Used when the newest candle has already closed, so its end can't be used as expiry.
+    pub ohlc_fallback_ttl_seconds: u64,
+    pub history_ttl_seconds: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_entries: 1_000,
+            uuid_ttl_seconds: 7 * 24 * 60 * 60,
+            ohlc_max_ttl_seconds: 60 * 60,
+            ohlc_fallback_ttl_seconds: 60,
+            history_ttl_seconds: 60,
+        }
+    }
+}
+
+/// The background poller pushing indicator updates to WebSocket subscribers.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LiveSettings {
+    /// How often the candles of every subscription are fetched again.
+    pub poll_interval_milliseconds: u64,
+    /// Distinct subscriptions polled at once, across every client.
+    pub max_subscriptions: usize,
+}
+
+impl LiveSettings {
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_milliseconds)
+    }
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval_milliseconds: 5_000,
+            max_subscriptions: 1_000,
+        }
+    }
+}
+
+/// The scheduler evaluating alert rules and the delivery of their webhooks.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AlertSettings {
+    /// How often every rule is evaluated against freshly fetched candles.
+    pub evaluation_interval_milliseconds: u64,
+    /// Attempts of a webhook delivery, the delay doubling after each failed one.
+    pub max_attempts: u32,
+    pub initial_backoff_milliseconds: u64,
+    pub timeout_milliseconds: u64,
+    /// Lets webhooks point to loopback, private and link-local addresses, which
+    /// are refused by default so that rules can't reach internal services.
+    pub allow_private_webhooks: bool,
+}
+
+impl AlertSettings {
+    pub fn evaluation_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.evaluation_interval_milliseconds)
+    }
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            evaluation_interval_milliseconds: 60_000,
+            max_attempts: 3,
+            initial_backoff_milliseconds: 1_000,
+            timeout_milliseconds: 5_000,
+            allow_private_webhooks: false,
+        }
+    }
+}
+
+/// The indicators refreshed in the background on every candle close, served by
+/// their dedicated routes without any upstream call.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PrecomputeSettings {
+    pub watchlist: Vec<WatchedCoin>,
+    /// Waited after a candle closes, so that the upstream has the new one.
+    pub close_delay_milliseconds: u64,
+    /// Waited before fetching again after a failure.
+    pub retry_delay_milliseconds: u64,
+}
+
+impl Default for PrecomputeSettings {
+    fn default() -> Self {
+        Self {
+            watchlist: vec![],
+            close_delay_milliseconds: 2_000,
+            retry_delay_milliseconds: 30_000,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct WatchedCoin {
+    pub coin: String,
+    /// Every indicator with a dedicated route by default.
+    #[serde(default = "all_indicators")]
+    pub indicators: Vec<Indicator>,
+    #[serde(default)]
+    pub interval: Interval,
+}
+
+fn all_indicators() -> Vec<Indicator> {
+    Indicator::ALL.to_vec()
+}
+
+/// API keys required on every route but the public ones, each with its own rate
+/// limit and daily quota. Enabled by default, the service refusing to start until
+/// a key is configured or `enabled` is turned off.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AuthSettings {
+    pub enabled: bool,
+    pub keys: Vec<ApiKeySettings>,
+    /// A YAML file listing more `keys`, kept out of the configuration.
+    pub keys_file: Option<String>,
+    /// Paths reachable without a key, `/metrics` included by default so a scrape needs none.
+    pub public_paths: Vec<String>,
+    /// Token bucket of the keys not setting their own: refilled at `requests_per_second`,
+    /// holding up to `burst` requests.
+    pub requests_per_second: f64,
+    pub burst: u32,
+    /// Requests per UTC day, unlimited when absent.
+    pub daily_quota: Option<u64>,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keys: vec![],
+            keys_file: None,
+            public_paths: vec![
+                "/health_check".to_owned(),
+                "/readiness".to_owned(),
+                "/docs".to_owned(),
+                "/openapi.json".to_owned(),
+                "/metrics".to_owned(),
+            ],
+            requests_per_second: 5.0,
+            burst: 20,
+            daily_quota: Some(10_000),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ApiKeySettings {
+    /// Names the key in logs, the key itself never being stored.
+    pub id: String,
+    /// Hex SHA-256 of the key.
+    pub secret_hash: String,
+    pub requests_per_second: Option<f64>,
+    pub burst: Option<u32>,
+    pub daily_quota: Option<u64>,
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+}
+
+/// What a key may do beyond the indicator, analysis and alert routes.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// The `/admin` routes, e.g. purging the caches.
+    Admin,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LoggingSettings {
+    pub format: LogFormat,
+    /// A `tracing` filter such as `info` or `taindicators=debug,warn`, `RUST_LOG`
+    /// taking precedence.
+    pub level: String,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: "info".to_owned(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+/// The threads shared by every `/optimize` search running at once, half the
+/// cores by default so that the other routes keep some.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct OptimizeSettings {
+    pub threads: usize,
+}
+
+impl Default for OptimizeSettings {
+    fn default() -> Self {
+        Self {
+            threads: std::thread::available_parallelism()
+                .map_or(1, |threads| (threads.get() / 2).max(1)),
+        }
+    }
+}
+
+/// The `/readiness` report, whose upstream probe is reused for `probe_ttl_seconds`
+/// so that frequent checks don't eat the upstream quota.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ReadinessSettings {
+    pub probe_ttl_seconds: u64,
+}
+
+impl ReadinessSettings {
+    pub fn probe_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.probe_ttl_seconds)
+    }
+}
+
+impl Default for ReadinessSettings {
+    fn default() -> Self {
+        Self {
+            probe_ttl_seconds: 30,
+        }
+    }
 }
 
 #[derive(serde::Deserialize)]
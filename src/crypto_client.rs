@@ -1,151 +1,602 @@
-use reqwest::Client;
+use crate::cache::{CacheStats, TtlCache};
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+use crate::configuration::{CacheSettings, CryptoClientSettings, RetrySettings};
+use crate::error::ApiError;
+use crate::interval::Interval;
+use crate::metrics::Metrics;
+use crate::missing_data::{self, MissingDataPolicy, Row};
+use crate::single_flight::SingleFlight;
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
 use secrecy::{ExposeSecret, Secret};
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::Instrument;
 
 pub struct CryptoClient {
     http_client: Client,
     base_url: String,
     authorization_token: Secret<String>,
+    missing_data: MissingDataPolicy,
+    retry_settings: RetrySettings,
+    circuit_breaker: CircuitBreaker,
+    cache_settings: CacheSettings,
+    uuid_cache: TtlCache<String, String>,
+    ohlc_cache: TtlCache<String, ParsedOhlcData>,
+    history_cache: TtlCache<String, ParsedDataHistory>,
+    uuid_flights: SingleFlight<String, Result<String, ApiError>>,
+    ohlc_flights: SingleFlight<String, Result<ParsedOhlcData, ApiError>>,
+    history_flights: SingleFlight<String, Result<ParsedDataHistory, ApiError>>,
+    metrics: Arc<Metrics>,
 }
 
 impl CryptoClient {
-    pub fn new(base_url: String, authorization_token: Secret<String>) -> Self {
-        let http_client = Client::new();
+    pub fn new(settings: CryptoClientSettings, metrics: Arc<Metrics>) -> Self {
+        let http_client = Client::builder()
+            .timeout(settings.timeout())
+            .build()
+            .expect("Failed to build the http client.");
+        let max_entries = settings.cache.max_entries;
 
         Self {
             http_client,
-            base_url,
-            authorization_token,
+            base_url: settings.base_url,
+            authorization_token: settings.auth_token,
+            missing_data: settings.missing_data,
+            retry_settings: settings.retry,
+            circuit_breaker: CircuitBreaker::new(
+                settings.circuit_breaker.failure_threshold,
+                Duration::from_secs(settings.circuit_breaker.open_seconds),
+            ),
+            cache_settings: settings.cache,
+            uuid_cache: TtlCache::new(max_entries),
+            ohlc_cache: TtlCache::new(max_entries),
+            history_cache: TtlCache::new(max_entries),
+            uuid_flights: SingleFlight::new(),
+            ohlc_flights: SingleFlight::new(),
+            history_flights: SingleFlight::new(),
+            metrics,
+        }
+    }
+
+    pub fn circuit_state(&self) -> CircuitState {
+        self.circuit_breaker.state()
+    }
+
+    pub fn caches_healthy(&self) -> bool {
+        self.uuid_cache.is_healthy()
+            && self.ohlc_cache.is_healthy()
+            && self.history_cache.is_healthy()
+    }
+
+    pub fn cache_stats(&self) -> CryptoClientCacheStats {
+        CryptoClientCacheStats {
+            uuid: self.uuid_cache.stats(),
+            ohlc: self.ohlc_cache.stats(),
+            history: self.history_cache.stats(),
+        }
+    }
+
+    /// Drops the cached entries of the given kind, or of every kind when `None`.
+    /// Returns the number of entries removed.
+    pub fn purge_cache(&self, kind: Option<CacheKind>) -> usize {
+        match kind {
+            Some(CacheKind::Uuid) => self.uuid_cache.purge(),
+            Some(CacheKind::Ohlc) => self.ohlc_cache.purge(),
+            Some(CacheKind::History) => self.history_cache.purge(),
+            None => self.uuid_cache.purge() + self.ohlc_cache.purge() + self.history_cache.purge(),
         }
     }
 
     pub async fn get_history_prices(
         &self,
         coin_uuid: &str,
-        time: &str,
-    ) -> Result<ParsedDataHistory, reqwest::Error> {
+        time: TimePeriod,
+    ) -> Result<ParsedDataHistory, ApiError> {
+        let cache_key = format!("{}:{}", coin_uuid, time.as_str());
+        if let Some(cached) = self.history_cache.get(&cache_key) {
+            return Ok(cached);
+        }
+
+        self.history_flights
+            .run(cache_key.clone(), || {
+                self.observed(
+                    "get_history_prices",
+                    self.fetch_history_prices(coin_uuid, time, cache_key),
+                )
+            })
+            .await
+    }
+
+    async fn fetch_history_prices(
+        &self,
+        coin_uuid: &str,
+        time: TimePeriod,
+        cache_key: String,
+    ) -> Result<ParsedDataHistory, ApiError> {
         let url = format!(
             "{}/coin/{}/history?timePeriod={}",
-            self.base_url, coin_uuid, time
+            self.base_url,
+            coin_uuid,
+            time.as_str()
         );
 
-        let response = self
-            .http_client
-            .get(&url)
-            .header("X-RapidAPI-Key", self.authorization_token.expose_secret())
-            .send()
-            .await?
-            .error_for_status()?;
+        let response = self.send(&url).await?;
 
         let parsed_response = response.json::<HistoryResponseData>().await?;
 
-        let parsed_history: Vec<ParsedHistory> = parsed_response
-            .data
-            .history
+        let history = parsed_response.data.history;
+        let rows = history
             .iter()
-            .map(|history| ParsedHistory {
-                price: history.price.parse::<f64>().unwrap(),
+            .map(|history| Row {
                 timestamp: history.timestamp,
+                values: vec![missing_data::parse_value(history.price.as_deref())],
+            })
+            .collect();
+
+        let parsed_history: Vec<ParsedHistory> = missing_data::apply(self.missing_data, rows)?
+            .into_iter()
+            .map(|(index, values)| ParsedHistory {
+                price: values[0],
+                timestamp: history[index].timestamp,
             })
             .collect();
 
         let parsed_data = ParsedDataHistory {
             history: parsed_history,
         };
+        self.history_cache.insert(
+            cache_key,
+            parsed_data.clone(),
+            Duration::from_secs(self.cache_settings.history_ttl_seconds),
+        );
         Ok(parsed_data)
     }
 
-    pub async fn get_coin_uuid(&self, coin_symbol: &str) -> Result<String, CoinUuidErr> {
-        let url = format!("{}/search-suggestions?query={}", self.base_url, coin_symbol);
+    pub async fn get_coin_uuid(&self, coin_symbol: &str) -> Result<String, ApiError> {
+        let symbol = coin_symbol.to_ascii_uppercase();
+        if let Some(uuid) = self.uuid_cache.get(&symbol) {
+            return Ok(uuid);
+        }
+
+        self.uuid_flights
+            .run(symbol.clone(), || {
+                self.observed("get_coin_uuid", self.fetch_coin_uuid(symbol))
+            })
+            .await
+    }
+
+    async fn fetch_coin_uuid(&self, symbol: String) -> Result<String, ApiError> {
+        let url = format!("{}/search-suggestions?query={}", self.base_url, symbol);
 
-        let response = self
-            .http_client
-            .get(&url)
-            .header("X-RapidAPI-Key", self.authorization_token.expose_secret())
-            .send()
-            .await?
-            .error_for_status()?;
+        let response = self.send(&url).await?;
 
         let body = response.json::<Body>().await?;
 
         for entry in body.data.coins {
-            if entry.symbol.as_str().eq(&coin_symbol.to_ascii_uppercase()) {
+            if entry.symbol == symbol {
+                self.uuid_cache.insert(
+                    symbol,
+                    entry.uuid.clone(),
+                    Duration::from_secs(self.cache_settings.uuid_ttl_seconds),
+                );
                 return Ok(entry.uuid);
             }
         }
 
-        Err(CoinUuidErr::CoinNotFound)
+        Err(ApiError::UnknownCoin(symbol))
     }
 
-    pub async fn get_coin_ohlc(&self, coin_symbol: &str) -> Result<ParsedOhlcData, CoinUuidErr> /* reqwest::Error */
-    {
-        let url = format!("{}/coin/{}/ohlc", self.base_url, coin_symbol);
+    /// The `limit` coins with the largest market capitalisation, largest first.
+    /// Not cached: the ranking moves and only the screener asks for it.
+    pub async fn get_top_coins(&self, limit: u32) -> Result<Vec<CoinSummary>, ApiError> {
+        let url = format!(
+            "{}/coins?orderBy=marketCap&orderDirection=desc&limit={}",
+            self.base_url, limit
+        );
+
+        self.observed("get_top_coins", async {
+            let response = self.send(&url).await?;
+
+            let body = response.json::<CoinsResponseData>().await?;
+            Ok(body.data.coins)
+        })
+        .await
+    }
+
+    /// A single cheap call checking that the upstream answers and accepts the token,
+    /// without retries nor the circuit breaker, which would hide the actual state.
+    pub async fn probe(&self) -> Result<(), ApiError> {
+        let url = format!("{}/coins?limit=1", self.base_url);
+        self.observed("probe", async {
+            let response = self
+                .http_client
+                .get(&url)
+                .header("X-RapidAPI-Key", self.authorization_token.expose_secret())
+                .send()
+                .await?;
+            match response.status() {
+                status if status.is_success() => Ok(()),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    Err(ApiError::UpstreamFailure(format!(
+                        "the upstream rejected the API token ({})",
+                        response.status()
+                    )))
+                }
+                StatusCode::TOO_MANY_REQUESTS => Err(ApiError::UpstreamRateLimited {
+                    retry_after: retry_after(&response),
+                }),
+                status => Err(ApiError::UpstreamFailure(format!(
+                    "the upstream answered {}",
+                    status
+                ))),
+            }
+        })
+        .await
+    }
 
-        let response = self
-            .http_client
-            .get(&url)
-            .header("X-RapidAPI-Key", self.authorization_token.expose_secret())
-            .send()
-            .await?
-            .error_for_status()?;
+    /// Candles newest first, as Coinranking returns them.
+    pub async fn get_coin_ohlc(
+        &self,
+        coin_uuid: &str,
+        params: &OhlcParams,
+    ) -> Result<ParsedOhlcData, ApiError> {
+        let cache_key = params.cache_key(coin_uuid);
+        if let Some(cached) = self.ohlc_cache.get(&cache_key) {
+            return Ok(cached);
+        }
+
+        self.ohlc_flights
+            .run(cache_key.clone(), || {
+                self.observed(
+                    "get_coin_ohlc",
+                    self.fetch_coin_ohlc(coin_uuid, params, cache_key),
+                )
+            })
+            .await
+    }
+
+    /// Same as `get_coin_ohlc`, skipping the cache to see the forming candle. The
+    /// cache is still refreshed, and concurrent identical calls still share a fetch.
+    pub async fn refresh_coin_ohlc(
+        &self,
+        coin_uuid: &str,
+        params: &OhlcParams,
+    ) -> Result<ParsedOhlcData, ApiError> {
+        let cache_key = params.cache_key(coin_uuid);
+        self.ohlc_flights
+            .run(cache_key.clone(), || {
+                self.observed(
+                    "refresh_coin_ohlc",
+                    self.fetch_coin_ohlc(coin_uuid, params, cache_key),
+                )
+            })
+            .await
+    }
+
+    async fn fetch_coin_ohlc(
+        &self,
+        coin_uuid: &str,
+        params: &OhlcParams,
+        cache_key: String,
+    ) -> Result<ParsedOhlcData, ApiError> {
+        let mut url = format!(
+            "{}/coin/{}/ohlc?interval={}&limit={}",
+            self.base_url, coin_uuid, params.interval, params.limit
+        );
+        if let Some(from) = params.from {
+            url.push_str(&format!("&from={}", from));
+        }
+        if let Some(to) = params.to {
+            url.push_str(&format!("&to={}", to));
+        }
+
+        let response = self.send(&url).await?;
 
         let response_json = response.json::<OhlcResponseData>().await?;
 
-        let parsed_ohlc: Result<Vec<ParsedOhlc>, CoinUuidErr> = response_json
+        // The window is also enforced here in case the upstream ignores it.
+        let ohlc: Vec<Ohlc> = response_json
             .data
             .ohlc
+            .into_iter()
+            .filter(|ohlc| params.from.is_none_or(|from| ohlc.starting_at >= from))
+            .filter(|ohlc| params.to.is_none_or(|to| ohlc.ending_at <= to))
+            .take(params.limit as usize)
+            .collect();
+        let rows = ohlc
             .iter()
-            .take(30)
-            .map(|ohlc| parse_ohlc(&ohlc))
+            .map(|ohlc| Row {
+                timestamp: ohlc.starting_at,
+                values: [&ohlc.open, &ohlc.high, &ohlc.low, &ohlc.close, &ohlc.avg]
+                    .into_iter()
+                    .map(|value| missing_data::parse_value(value.as_deref()))
+                    .collect(),
+            })
             .collect();
 
-        let parsed_data = ParsedOhlcData { ohlc: parsed_ohlc? };
+        let parsed_ohlc = missing_data::apply(self.missing_data, rows)?
+            .into_iter()
+            .map(|(index, values)| ParsedOhlc {
+                starting_at: ohlc[index].starting_at,
+                ending_at: ohlc[index].ending_at,
+                open: values[0],
+                high: values[1],
+                low: values[2],
+                close: values[3],
+                avg: values[4],
+                volume: missing_data::parse_value(ohlc[index].volume.as_deref()),
+                filled: false,
+            })
+            .collect();
+
+        let parsed_data = ParsedOhlcData { ohlc: parsed_ohlc };
+        self.ohlc_cache.insert(
+            cache_key,
+            parsed_data.clone(),
+            self.ohlc_ttl(&parsed_data, params),
+        );
 
         Ok(parsed_data)
     }
+
+    /// Runs an upstream call of `method`, recording it in the metrics.
+    async fn observed<T>(
+        &self,
+        method: &str,
+        call: impl Future<Output = Result<T, ApiError>>,
+    ) -> Result<T, ApiError> {
+        let span = tracing::info_span!("upstream_call", method, duration_ms = Empty);
+        let started = Instant::now();
+        let result = call.instrument(span.clone()).await;
+        let elapsed = started.elapsed();
+        self.metrics
+            .observe_upstream(method, elapsed, result.as_ref().err());
+        span.record("duration_ms", elapsed.as_millis() as u64);
+        span.in_scope(|| match &result {
+            Ok(_) => tracing::info!("upstream call succeeded"),
+            Err(err) => tracing::warn!(code = err.code(), error = %err, "upstream call failed"),
+        });
+        result
+    }
+
+    /// Performs a GET against the upstream, retrying timeouts, connection errors,
+    /// 5xx and 429 responses with exponential backoff, and failing fast while the
+    /// circuit breaker is open.
+    async fn send(&self, url: &str) -> Result<Response, ApiError> {
+        if !self.circuit_breaker.allow() {
+            return Err(ApiError::UpstreamUnavailable);
+        }
+
+        let logged_url = redact(url);
+        let mut attempt = 0;
+        loop {
+            let last_attempt = attempt >= self.retry_settings.max_retries;
+            let started = Instant::now();
+            let outcome = self
+                .http_client
+                .get(url)
+                .header("X-RapidAPI-Key", self.authorization_token.expose_secret())
+                .send()
+                .await;
+            let duration_ms = started.elapsed().as_millis() as u64;
+            match &outcome {
+                Ok(response) => tracing::info!(
+                    url = %logged_url,
+                    status = response.status().as_u16(),
+                    duration_ms,
+                    attempt,
+                    "upstream request"
+                ),
+                Err(err) => tracing::warn!(
+                    url = %logged_url,
+                    timeout = err.is_timeout(),
+                    connect = err.is_connect(),
+                    duration_ms,
+                    attempt,
+                    "upstream request failed"
+                ),
+            }
+
+            let delay = match outcome {
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    // Being throttled says nothing about the upstream's health.
+                    let retry_after = retry_after(&response);
+                    let max_backoff =
+                        Duration::from_millis(self.retry_settings.max_backoff_milliseconds);
+                    match retry_after {
+                        Some(retry_after) if retry_after > max_backoff => {
+                            return Err(ApiError::UpstreamRateLimited {
+                                retry_after: Some(retry_after),
+                            })
+                        }
+                        _ if last_attempt => {
+                            return Err(ApiError::UpstreamRateLimited { retry_after })
+                        }
+                        Some(retry_after) => retry_after,
+                        None => self.backoff(attempt),
+                    }
+                }
+                Ok(response) if response.status().is_server_error() => {
+                    if last_attempt {
+                        self.circuit_breaker.record_failure();
+                        return Err(ApiError::UpstreamFailure(format!(
+                            "the upstream answered {}",
+                            response.status()
+                        )));
+                    }
+                    self.backoff(attempt)
+                }
+                Ok(response) => {
+                    self.circuit_breaker.record_success();
+                    return Ok(response.error_for_status()?);
+                }
+                Err(err) if err.is_timeout() || err.is_connect() => {
+                    if last_attempt {
+                        self.circuit_breaker.record_failure();
+                        return Err(err.into());
+                    }
+                    self.backoff(attempt)
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Exponential backoff with "equal jitter": half of the delay is fixed, the other half random.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let initial = self.retry_settings.initial_backoff_milliseconds;
+        let max = self.retry_settings.max_backoff_milliseconds;
+        let delay = initial
+            .saturating_mul(2u64.saturating_pow(attempt))
+            .min(max);
+        let jitter = rand::thread_rng().gen_range(0..=delay / 2);
+        Duration::from_millis(delay - delay / 2 + jitter)
+    }
+
+    /// OHLC data stays valid until the current candle closes, capped by the configured
+    /// maximum. A window entirely in the past never changes.
+    fn ohlc_ttl(&self, data: &ParsedOhlcData, params: &OhlcParams) -> Duration {
+        let now = chrono::Utc::now().timestamp();
+        let max_ttl = self.cache_settings.ohlc_max_ttl_seconds;
+        if params.to.is_some_and(|to| to <= now) {
+            return Duration::from_secs(max_ttl);
+        }
+        match data.ohlc.iter().map(|ohlc| ohlc.ending_at).max() {
+            Some(ending_at) if ending_at > now => {
+                Duration::from_secs(((ending_at - now) as u64).min(max_ttl))
+            }
+            _ => Duration::from_secs(self.cache_settings.ohlc_fallback_ttl_seconds.min(max_ttl)),
+        }
+    }
 }
 
-fn parse_ohlc(ohlc: &Ohlc) -> Result<ParsedOhlc, CoinUuidErr> {
-    Ok(ParsedOhlc {
-        starting_at: ohlc.starting_at,
-        ending_at: ohlc.ending_at,
-        open: parse_float(&ohlc.open)?,
-        high: parse_float(&ohlc.high)?,
-        low: parse_float(&ohlc.low)?,
-        close: parse_float(&ohlc.close)?,
-        avg: parse_float(&ohlc.avg)?,
-    })
+/// The URL without credentials, should the base URL hold any, for the logs.
+fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut url) => {
+            if url.password().is_some() {
+                let _ = url.set_password(Some("redacted"));
+            }
+            url.to_string()
+        }
+        Err(_) => "<invalid url>".to_owned(),
+    }
 }
 
-fn parse_float(s: &String) -> Result<f64, reqwest::StatusCode> {
-    match s.parse::<f64>() {
-        Ok(value) => Ok(value),
-        Err(_) => Err(reqwest::StatusCode::INTERNAL_SERVER_ERROR),
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
     }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(seconds as u64))
+}
+
+/// Time spans accepted by Coinranking's `timePeriod` for the price history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub enum TimePeriod {
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "3h")]
+    ThreeHours,
+    #[serde(rename = "12h")]
+    TwelveHours,
+    #[serde(rename = "24h")]
+    OneDay,
+    #[serde(rename = "7d")]
+    SevenDays,
+    #[serde(rename = "30d")]
+    ThirtyDays,
+    #[serde(rename = "3m")]
+    ThreeMonths,
+    #[serde(rename = "1y")]
+    OneYear,
+    #[serde(rename = "3y")]
+    ThreeYears,
+    #[serde(rename = "5y")]
+    FiveYears,
 }
 
-/*** Err enums ****/
+impl TimePeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimePeriod::OneHour => "1h",
+            TimePeriod::ThreeHours => "3h",
+            TimePeriod::TwelveHours => "12h",
+            TimePeriod::OneDay => "24h",
+            TimePeriod::SevenDays => "7d",
+            TimePeriod::ThirtyDays => "30d",
+            TimePeriod::ThreeMonths => "3m",
+            TimePeriod::OneYear => "1y",
+            TimePeriod::ThreeYears => "3y",
+            TimePeriod::FiveYears => "5y",
+        }
+    }
+}
 
-#[derive(Debug)]
-pub enum CoinUuidErr {
-    CoinNotFound,
-    RequestError(reqwest::Error),
-    StatusError(reqwest::StatusCode),
+/// Which candles `get_coin_ohlc` asks for. `from` and `to` are unix timestamps in seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OhlcParams {
+    pub interval: Interval,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: u32,
 }
 
-impl From<reqwest::Error> for CoinUuidErr {
-    fn from(err: reqwest::Error) -> Self {
-        CoinUuidErr::RequestError(err)
+impl OhlcParams {
+    /// The most candles Coinranking returns in one call.
+    pub const MAX_LIMIT: u32 = 5000;
+    pub const DEFAULT_LIMIT: u32 = 30;
+
+    fn cache_key(&self, coin_uuid: &str) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            coin_uuid,
+            self.interval,
+            self.from.map(|from| from.to_string()).unwrap_or_default(),
+            self.to.map(|to| to.to_string()).unwrap_or_default(),
+            self.limit
+        )
     }
 }
 
-impl From<reqwest::StatusCode> for CoinUuidErr {
-    fn from(err: reqwest::StatusCode) -> Self {
-        CoinUuidErr::StatusError(err)
+impl Default for OhlcParams {
+    fn default() -> Self {
+        Self {
+            interval: Interval::default(),
+            from: None,
+            to: None,
+            limit: Self::DEFAULT_LIMIT,
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    Uuid,
+    Ohlc,
+    History,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct CryptoClientCacheStats {
+    pub uuid: CacheStats,
+    pub ohlc: CacheStats,
+    pub history: CacheStats,
+}
+
 ///this struct is necessary for serde because the values in the
 ///request's fields are embedded in strings rather than being bare
 ///floats, so it is done in a manual step immediately after parsing the request
@@ -174,6 +625,28 @@ pub struct Coin {
     pub price: String,
 }
 
+/*************** coinsResponseData ****************/
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinsResponseData {
+    pub status: String,
+    pub data: DataCoins,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataCoins {
+    pub coins: Vec<CoinSummary>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinSummary {
+    pub uuid: String,
+    pub symbol: String,
+    pub name: String,
+}
+
 /*************************** historyResponseData ******************************/
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -193,7 +666,7 @@ pub struct DataHistory {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct History {
-    pub price: String,
+    pub price: Option<String>,
     pub timestamp: i64,
 }
 /********* PARSED DATA ******/
@@ -230,11 +703,14 @@ pub struct Data {
 pub struct Ohlc {
     pub starting_at: i64,
     pub ending_at: i64,
-    pub open: String,
-    pub high: String,
-    pub low: String,
-    pub close: String,
-    pub avg: String,
+    pub open: Option<String>,
+    pub high: Option<String>,
+    pub low: Option<String>,
+    pub close: Option<String>,
+    pub avg: Option<String>,
+    /// Not returned by Coinranking today, but by other providers.
+    #[serde(default)]
+    pub volume: Option<String>,
 }
 /********* PARSED DATA ******/
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -243,7 +719,7 @@ pub struct ParsedOhlcData {
     pub ohlc: Vec<ParsedOhlc>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedOhlc {
     pub starting_at: i64,
@@ -253,6 +729,11 @@ pub struct ParsedOhlc {
     pub low: f64,
     pub close: f64,
     pub avg: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    /// Synthetic candle inserted to fill a gap in the upstream data.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub filled: bool,
 }
 
 //you never know
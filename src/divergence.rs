@@ -0,0 +1,219 @@
+//! Divergences between price and an oscillator, found by comparing consecutive
+//! swing points of both series.
+//!
+//! Swing lows are compared on the lows of the candles, swing highs on their highs:
+//!
+//! - regular bullish: price makes a lower low, the oscillator a higher low
+//! - hidden bullish: price makes a higher low, the oscillator a lower low
+//! - regular bearish: price makes a higher high, the oscillator a lower high
+//! - hidden bearish: price makes a lower high, the oscillator a higher high
+
+use std::str::FromStr;
+
+use crate::crypto_client::{OhlcParams, ParsedOhlc};
+use crate::error::ApiError;
+use crate::indicators::{self, Bias};
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Oscillator {
+    Rsi,
+    /// The MACD line, 12/26 periods.
+    Macd,
+    Stochastic,
+}
+
+impl FromStr for Oscillator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rsi" => Ok(Oscillator::Rsi),
+            "macd" => Ok(Oscillator::Macd),
+            "stochastic" => Ok(Oscillator::Stochastic),
+            _ => Err(format!(
+                "unknown oscillator `{}`, expected one of rsi, macd, stochastic",
+                s
+            )),
+        }
+    }
+}
+
+impl Oscillator {
+    /// Checks a requested period, `None` meaning the default one. The MACD has
+    /// fixed periods.
+    pub fn period(&self, period: Option<usize>) -> Result<usize, ApiError> {
+        match (self, period) {
+            (Oscillator::Macd, None) => Ok(26),
+            (Oscillator::Macd, Some(_)) => Err(ApiError::bad_parameter(
+                "period",
+                "the MACD uses fixed periods of 12 and 26",
+            )),
+            (_, None) => Ok(14),
+            (_, Some(period)) if (2..=OhlcParams::MAX_LIMIT as usize).contains(&period) => {
+                Ok(period)
+            }
+            (_, Some(_)) => Err(ApiError::bad_parameter(
+                "period",
+                format!("must be between 2 and {}", OhlcParams::MAX_LIMIT),
+            )),
+        }
+    }
+
+    /// One value per candle, `candles` being oldest first.
+    pub fn series(&self, candles: &[ParsedOhlc], period: usize) -> Vec<f64> {
+        let column =
+            |field: fn(&ParsedOhlc) -> f64| -> Vec<f64> { candles.iter().map(field).collect() };
+        let closes = column(|ohlc| ohlc.close);
+        match self {
+            Oscillator::Rsi => indicators::rsi(&closes, period),
+            Oscillator::Macd => indicators::macd(&closes, 12, 26, 9).macd,
+            Oscillator::Stochastic => indicators::stochastic_oscillator(
+                &column(|ohlc| ohlc.high),
+                &column(|ohlc| ohlc.low),
+                &closes,
+                period,
+            ),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DivergenceKind {
+    RegularBullish,
+    HiddenBullish,
+    RegularBearish,
+    HiddenBearish,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Point {
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Divergence {
+    pub kind: DivergenceKind,
+    pub bias: Bias,
+    /// The two price swings, oldest first.
+    pub price: [Point; 2],
+    /// The oscillator swings matching the price ones.
+    pub oscillator: [Point; 2],
+    /// The relative price change plus the oscillator change over its range on
+    /// the whole series: the further both move apart, the stronger.
+    pub strength: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwingParams {
+    /// Candles on each side a swing point must exceed, also how far apart the
+    /// price and oscillator swings may be.
+    pub swing: usize,
+    /// The most candles between the two swings of a divergence.
+    pub max_distance: usize,
+}
+
+impl Default for SwingParams {
+    fn default() -> Self {
+        Self {
+            swing: 3,
+            max_distance: 60,
+        }
+    }
+}
+
+/// Indices of the values above (`high`) or below the `swing` values on their left
+/// and right. Plateaus count once, at their first point.
+fn swings(values: &[f64], swing: usize, high: bool) -> Vec<usize> {
+    let beats = |a: f64, b: f64| if high { a > b } else { a < b };
+    (swing..values.len().saturating_sub(swing))
+        .filter(|&i| {
+            let value = values[i];
+            !value.is_nan()
+                && values[i - swing..i]
+                    .iter()
+                    .all(|&other| !other.is_nan() && beats(value, other))
+                && values[i + 1..=i + swing]
+                    .iter()
+                    .all(|&other| !other.is_nan() && !beats(other, value))
+        })
+        .collect()
+}
+
+/// The size of `change` relative to `scale`, 0 without a scale to compare with,
+/// e.g. over a flat oscillator, rather than an infinite or NaN strength.
+fn relative(change: f64, scale: f64) -> f64 {
+    if scale == 0.0 || !scale.is_finite() {
+        return 0.0;
+    }
+    (change / scale).abs()
+}
+
+/// Every divergence between the candles, oldest first, and the oscillator values
+/// computed on them, ordered by the second price swing.
+pub fn detect(candles: &[ParsedOhlc], oscillator: &[f64], params: &SwingParams) -> Vec<Divergence> {
+    let lows: Vec<f64> = candles.iter().map(|ohlc| ohlc.low).collect();
+    let highs: Vec<f64> = candles.iter().map(|ohlc| ohlc.high).collect();
+    let defined = oscillator.iter().filter(|value| !value.is_nan());
+    let range = defined.clone().copied().fold(f64::MIN, f64::max)
+        - defined.copied().fold(f64::MAX, f64::min);
+
+    let mut divergences = vec![];
+    for high in [false, true] {
+        let price = if high { &highs } else { &lows };
+        let price_swings = swings(price, params.swing, high);
+        let oscillator_swings = swings(oscillator, params.swing, high);
+        // The oscillator swing closest to a price swing, within `swing` candles.
+        let matching = |i: usize| {
+            oscillator_swings
+                .iter()
+                .copied()
+                .filter(|&j| i.abs_diff(j) <= params.swing)
+                .min_by_key(|&j| i.abs_diff(j))
+        };
+
+        for pair in price_swings.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            if second - first > params.max_distance {
+                continue;
+            }
+            let (Some(o1), Some(o2)) = (matching(first), matching(second)) else {
+                continue;
+            };
+            if o1 >= o2 {
+                continue;
+            }
+            let price_change = price[second] - price[first];
+            let oscillator_change = oscillator[o2] - oscillator[o1];
+            let kind = match (high, price_change, oscillator_change) {
+                (false, p, o) if p < 0.0 && o > 0.0 => DivergenceKind::RegularBullish,
+                (false, p, o) if p > 0.0 && o < 0.0 => DivergenceKind::HiddenBullish,
+                (true, p, o) if p > 0.0 && o < 0.0 => DivergenceKind::RegularBearish,
+                (true, p, o) if p < 0.0 && o > 0.0 => DivergenceKind::HiddenBearish,
+                _ => continue,
+            };
+            let point = |values: &[f64], i: usize| Point {
+                timestamp: candles[i].starting_at,
+                value: values[i],
+            };
+            divergences.push((
+                second,
+                Divergence {
+                    kind,
+                    bias: if high { Bias::Bearish } else { Bias::Bullish },
+                    price: [point(price, first), point(price, second)],
+                    oscillator: [point(oscillator, o1), point(oscillator, o2)],
+                    strength: relative(price_change, price[first])
+                        + relative(oscillator_change, range),
+                },
+            ));
+        }
+    }
+    divergences.sort_by_key(|(second, _)| *second);
+    divergences
+        .into_iter()
+        .map(|(_, divergence)| divergence)
+        .collect()
+}
@@ -0,0 +1,190 @@
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::telemetry::current_request_id;
+
+/// Every error the service can return, from the upstream client up to the routes.
+///
+/// Rendered as a JSON body `{ status, code, message, details, requestId }` with the
+/// matching HTTP status. `Clone` so that the result of a coalesced upstream call can
+/// be handed to every request waiting for it.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ApiError {
+    #[error("Coin `{0}` was not found.")]
+    UnknownCoin(String),
+    #[error("Invalid `{parameter}`: {message}")]
+    BadParameter { parameter: String, message: String },
+    #[error("Not enough data: {required} values are needed, {available} are available.")]
+    InsufficientData { required: usize, available: usize },
+    #[error("The requested resource does not exist.")]
+    NotFound,
+    #[error("The upstream request failed: {0}")]
+    UpstreamFailure(String),
+    #[error("The upstream did not answer in time.")]
+    UpstreamTimeout,
+    #[error("The upstream rate limit was exceeded.")]
+    UpstreamRateLimited { retry_after: Option<Duration> },
+    #[error("The upstream is temporarily unavailable.")]
+    UpstreamUnavailable,
+    #[error("Internal error: {0}")]
+    Internal(String),
+    #[error("A valid API key is required.")]
+    Unauthorized,
+    #[error("This API key is not allowed to do that.")]
+    Forbidden,
+    #[error("Too many requests for this API key.")]
+    RateLimited { retry_after: Duration },
+    #[error("The daily quota of this API key is used up.")]
+    QuotaExceeded { retry_after: Duration },
+}
+
+/// The body of every error response.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorBody {
+    /// Always `error`.
+    pub status: &'static str,
+    /// Stable across releases, e.g. `unknown_coin` or `rate_limited`.
+    pub code: &'static str,
+    pub message: String,
+    /// Depends on the `code`, e.g. `coin` or `retryAfterSeconds`.
+    #[schema(value_type = Object)]
+    pub details: serde_json::Value,
+    /// Absent from the errors reported inline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    pub fn bad_parameter(parameter: &str, message: impl Into<String>) -> Self {
+        ApiError::BadParameter {
+            parameter: parameter.to_owned(),
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::UnknownCoin(_) => "unknown_coin",
+            ApiError::BadParameter { .. } => "bad_parameter",
+            ApiError::InsufficientData { .. } => "insufficient_data",
+            ApiError::NotFound => "not_found",
+            ApiError::UpstreamFailure(_) => "upstream_failure",
+            ApiError::UpstreamTimeout => "upstream_timeout",
+            ApiError::UpstreamRateLimited { .. } => "upstream_rate_limited",
+            ApiError::UpstreamUnavailable => "upstream_unavailable",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Forbidden => "forbidden",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::QuotaExceeded { .. } => "quota_exceeded",
+        }
+    }
+
+    /// The JSON body of the error response, also used to report errors inline,
+    /// e.g. for a single job of a batch.
+    pub fn body(&self) -> serde_json::Value {
+        serde_json::to_value(self.error_body()).expect("Serializable error body")
+    }
+
+    pub fn error_body(&self) -> ErrorBody {
+        ErrorBody {
+            status: "error",
+            code: self.code(),
+            message: self.to_string(),
+            details: self.details(),
+            request_id: None,
+        }
+    }
+
+    fn details(&self) -> serde_json::Value {
+        match self {
+            ApiError::UnknownCoin(coin) => json!({ "coin": coin }),
+            ApiError::BadParameter { parameter, .. } => json!({ "parameter": parameter }),
+            ApiError::InsufficientData {
+                required,
+                available,
+            } => json!({ "required": required, "available": available }),
+            ApiError::UpstreamRateLimited {
+                retry_after: Some(retry_after),
+            }
+            | ApiError::RateLimited { retry_after }
+            | ApiError::QuotaExceeded { retry_after } => {
+                json!({ "retryAfterSeconds": retry_after.as_secs().max(1) })
+            }
+            _ => json!({}),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::UnknownCoin(_) | ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::BadParameter { .. } => StatusCode::BAD_REQUEST,
+            ApiError::InsufficientData { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::UpstreamFailure(_) => StatusCode::BAD_GATEWAY,
+            ApiError::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::UpstreamRateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::UpstreamUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::RateLimited { .. } | ApiError::QuotaExceeded { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::UpstreamRateLimited {
+            retry_after: Some(retry_after),
+        }
+        | ApiError::RateLimited { retry_after }
+        | ApiError::QuotaExceeded { retry_after } = self
+        {
+            response.insert_header((RETRY_AFTER, retry_after.as_secs().max(1).to_string()));
+        }
+
+        response.json(ErrorBody {
+            request_id: current_request_id(),
+            ..self.error_body()
+        })
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            ApiError::UpstreamTimeout
+        } else {
+            ApiError::UpstreamFailure(err.to_string())
+        }
+    }
+}
+
+/// Fails with `InsufficientData` unless `available` values cover the `required` ones.
+pub fn require_data(required: usize, available: usize) -> Result<(), ApiError> {
+    if available < required {
+        return Err(ApiError::InsufficientData {
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Turns actix's extractor failures (bad path segments, query strings or bodies)
+/// into our JSON errors. Used by the `PathConfig`, `QueryConfig` and `JsonConfig`
+/// registered in `startup::run`.
+pub fn extractor_error(parameter: &str, err: impl std::fmt::Display) -> actix_web::Error {
+    ApiError::bad_parameter(parameter, err.to_string()).into()
+}
+
+pub async fn not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound)
+}
@@ -0,0 +1,878 @@
+//! A small expression language over candle series, e.g.
+//! `ema(close, 12) - ema(close, 26)` or `rsi(14) < 30 and close > sma(close, 50)`.
+//!
+//! Arithmetic (`+ - * /`), comparisons (`< <= > >= == !=`), boolean logic (`and`,
+//! `or`, `not`, also written `&& || !`), parentheses, function calls and lookback
+//! offsets (`close[1]` is the previous close). An expression evaluates to a series
+//! with one point per candle, oldest first: numbers, or conditions for comparisons
+//! and crossovers, undefined where there isn't enough history. Identifiers are
+//! resolved by a `Context`.
+
+use std::fmt;
+
+use crate::crypto_client::{OhlcParams, ParsedOhlc};
+use crate::indicators;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{message} at position {position}")]
+pub struct ExpressionError {
+    /// Byte offset in the source, 0 for errors found while evaluating.
+    pub position: usize,
+    pub message: String,
+}
+
+impl ExpressionError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    /// `sma(period)` over the closes, or `sma(series, period)`
+    Sma,
+    /// `ema(period)` over the closes, or `ema(series, period)`
+    Ema,
+    /// `stddev(series, period)`
+    StdDev,
+    /// `highest(series, period)`
+    Highest,
+    /// `lowest(series, period)`
+    Lowest,
+    /// `rsi(period)` over the closes, or `rsi(series, period)`
+    Rsi,
+    /// `stochastic(period)`
+    Stochastic,
+    /// `aroon(period)`, the Aroon oscillator
+    Aroon,
+    /// `crosses_above(a, b)`: `a` goes from at most `b` to above it
+    CrossesAbove,
+    /// `crosses_below(a, b)`: `a` goes from at least `b` to below it
+    CrossesBelow,
+    /// `abs(series)`
+    Abs,
+    /// `min(a, b)`
+    Min,
+    /// `max(a, b)`
+    Max,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "sma" => Function::Sma,
+            "ema" => Function::Ema,
+            "stddev" => Function::StdDev,
+            "highest" => Function::Highest,
+            "lowest" => Function::Lowest,
+            "rsi" => Function::Rsi,
+            "stochastic" => Function::Stochastic,
+            "aroon" => Function::Aroon,
+            "crosses_above" => Function::CrossesAbove,
+            "crosses_below" => Function::CrossesBelow,
+            "abs" => Function::Abs,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        })
+    }
+
+    /// The accepted numbers of series arguments, the period coming after them.
+    fn series_arguments(&self) -> &'static [usize] {
+        match self {
+            Function::Sma | Function::Ema | Function::Rsi => &[0, 1],
+            Function::Stochastic | Function::Aroon => &[0],
+            Function::StdDev | Function::Highest | Function::Lowest | Function::Abs => &[1],
+            Function::CrossesAbove | Function::CrossesBelow | Function::Min | Function::Max => &[2],
+        }
+    }
+
+    /// The smallest period accepted, `None` for the functions without one.
+    fn minimum_period(&self) -> Option<usize> {
+        match self {
+            Function::Rsi => Some(2),
+            Function::Sma
+            | Function::Ema
+            | Function::StdDev
+            | Function::Highest
+            | Function::Lowest
+            | Function::Stochastic
+            | Function::Aroon => Some(1),
+            Function::CrossesAbove
+            | Function::CrossesBelow
+            | Function::Abs
+            | Function::Min
+            | Function::Max => None,
+        }
+    }
+
+    fn returns(&self) -> Kind {
+        match self {
+            Function::CrossesAbove | Function::CrossesBelow => Kind::Condition,
+            _ => Kind::Number,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A function with its series arguments, and its period if it takes one.
+    Call(Function, Vec<Expr>, Option<usize>),
+    /// `expr[n]`: the value `n` candles earlier.
+    Offset(Box<Expr>, usize),
+}
+
+/// What an expression evaluates to, checked when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Number,
+    Condition,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Number => f.write_str("a number"),
+            Kind::Condition => f.write_str("a condition"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Series {
+    /// NaN where undefined.
+    Numbers(Vec<f64>),
+    /// `None` where undefined.
+    Conditions(Vec<Option<bool>>),
+}
+
+/// Resolves the identifiers of an expression to series of `points()` values.
+pub trait Context {
+    fn points(&self) -> usize;
+    fn variable(&self, name: &str) -> Option<Vec<f64>>;
+}
+
+/// The fields of candles ordered oldest first: `open`, `high`, `low`, `close`
+/// (also `price`), `avg` and `volume`.
+pub struct CandleContext<'a> {
+    candles: &'a [ParsedOhlc],
+}
+
+impl<'a> CandleContext<'a> {
+    pub const FIELDS: [&'static str; 7] =
+        ["open", "high", "low", "close", "price", "avg", "volume"];
+
+    pub fn new(candles: &'a [ParsedOhlc]) -> Self {
+        Self { candles }
+    }
+
+    /// Checks that an expression only uses the fields of the candles.
+    pub fn check(expr: &Expr) -> Result<(), String> {
+        match expr
+            .variables()
+            .into_iter()
+            .find(|name| !Self::FIELDS.contains(name))
+        {
+            Some(name) => Err(format!(
+                "unknown value `{}`, expected one of {}",
+                name,
+                Self::FIELDS.join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Context for CandleContext<'_> {
+    fn points(&self) -> usize {
+        self.candles.len()
+    }
+
+    fn variable(&self, name: &str) -> Option<Vec<f64>> {
+        let field: fn(&ParsedOhlc) -> f64 = match name {
+            "open" => |ohlc| ohlc.open,
+            "high" => |ohlc| ohlc.high,
+            "low" => |ohlc| ohlc.low,
+            "close" | "price" => |ohlc| ohlc.close,
+            "avg" => |ohlc| ohlc.avg,
+            "volume" => |ohlc| ohlc.volume.unwrap_or(f64::NAN),
+            _ => return None,
+        };
+        Some(self.candles.iter().map(field).collect())
+    }
+}
+
+impl Expr {
+    /// The longest source accepted, in bytes.
+    pub const MAX_LENGTH: usize = 1000;
+    /// The deepest nesting of parentheses, calls and unary operators accepted.
+    pub const MAX_DEPTH: usize = 64;
+
+    pub fn parse(source: &str) -> Result<Expr, ExpressionError> {
+        if source.len() > Self::MAX_LENGTH {
+            return Err(ExpressionError::new(
+                Self::MAX_LENGTH,
+                format!("expression longer than {} bytes", Self::MAX_LENGTH),
+            ));
+        }
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+            end: source.len(),
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some((position, token)) => Err(ExpressionError::new(
+                position,
+                format!("unexpected `{}`", token),
+            )),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) | Expr::Unary(UnaryOp::Negate, _) => Kind::Number,
+            Expr::Unary(UnaryOp::Not, _) => Kind::Condition,
+            Expr::Binary(op, _, _) => match op {
+                BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide => {
+                    Kind::Number
+                }
+                _ => Kind::Condition,
+            },
+            Expr::Call(function, _, _) => function.returns(),
+            Expr::Offset(expr, _) => expr.kind(),
+        }
+    }
+
+    /// Whether the expression is a condition, e.g. `rsi(14) < 30`.
+    pub fn is_condition(&self) -> bool {
+        self.kind() == Kind::Condition
+    }
+
+    /// Every identifier used, in order of first appearance.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = vec![];
+        self.collect_variables(&mut variables);
+        variables
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Variable(name) => {
+                if !variables.contains(&name.as_str()) {
+                    variables.push(name)
+                }
+            }
+            Expr::Unary(_, operand) | Expr::Offset(operand, _) => {
+                operand.collect_variables(variables)
+            }
+            Expr::Binary(_, left, right) => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+            Expr::Call(_, arguments, _) => {
+                for argument in arguments {
+                    argument.collect_variables(variables);
+                }
+            }
+        }
+    }
+
+    /// The number of earlier candles needed before the first defined point.
+    pub fn lookback(&self) -> usize {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) => 0,
+            Expr::Unary(_, operand) => operand.lookback(),
+            Expr::Binary(_, left, right) => left.lookback().max(right.lookback()),
+            Expr::Call(function, arguments, period) => {
+                let crossing = matches!(function, Function::CrossesAbove | Function::CrossesBelow);
+                arguments
+                    .iter()
+                    .map(Expr::lookback)
+                    .max()
+                    .unwrap_or(0)
+                    .saturating_add(period.map_or(0, |period| period - 1))
+                    .saturating_add(usize::from(crossing))
+            }
+            Expr::Offset(expr, offset) => expr.lookback().saturating_add(*offset),
+        }
+    }
+
+    pub fn evaluate(&self, context: &impl Context) -> Result<Series, ExpressionError> {
+        let points = context.points();
+        let column = |name: &str| {
+            context
+                .variable(name)
+                .ok_or_else(|| ExpressionError::new(0, format!("unknown value `{}`", name)))
+        };
+        Ok(match self {
+            Expr::Number(number) => Series::Numbers(vec![*number; points]),
+            Expr::Variable(name) => Series::Numbers(column(name)?),
+            Expr::Unary(UnaryOp::Negate, operand) => Series::Numbers(
+                numbers(operand.evaluate(context)?)
+                    .iter()
+                    .map(|x| -x)
+                    .collect(),
+            ),
+            Expr::Unary(UnaryOp::Not, operand) => Series::Conditions(
+                conditions(operand.evaluate(context)?)
+                    .iter()
+                    .map(|x| x.map(|x| !x))
+                    .collect(),
+            ),
+            Expr::Binary(op, left, right) => {
+                apply(*op, left.evaluate(context)?, right.evaluate(context)?)
+            }
+            Expr::Offset(expr, offset) => match expr.evaluate(context)? {
+                Series::Numbers(values) => Series::Numbers(shift(&values, *offset, f64::NAN)),
+                Series::Conditions(values) => Series::Conditions(shift(&values, *offset, None)),
+            },
+            Expr::Call(function, arguments, period) => {
+                let period = period.unwrap_or(1);
+                let mut series = vec![];
+                for argument in arguments {
+                    series.push(numbers(argument.evaluate(context)?));
+                }
+                // `sma`, `ema` and `rsi` default to the closes.
+                let mut closes = || match series.pop() {
+                    Some(series) => Ok(series),
+                    None => column("close"),
+                };
+                match function {
+                    Function::Sma => Series::Numbers(indicators::sma(&closes()?, period)),
+                    Function::Ema => Series::Numbers(indicators::ema(&closes()?, period)),
+                    Function::StdDev => Series::Numbers(indicators::stddev(&series[0], period)),
+                    Function::Highest => Series::Numbers(indicators::highest(&series[0], period)),
+                    Function::Lowest => Series::Numbers(indicators::lowest(&series[0], period)),
+                    Function::Rsi => Series::Numbers(indicators::rsi(&closes()?, period)),
+                    Function::Stochastic => Series::Numbers(indicators::stochastic_oscillator(
+                        &column("high")?,
+                        &column("low")?,
+                        &column("close")?,
+                        period,
+                    )),
+                    Function::Aroon => Series::Numbers(indicators::aroon_oscillator(
+                        &column("high")?,
+                        &column("low")?,
+                        period,
+                    )),
+                    Function::CrossesAbove => Series::Conditions(crosses(&series[0], &series[1])),
+                    Function::CrossesBelow => Series::Conditions(crosses(&series[1], &series[0])),
+                    Function::Abs => Series::Numbers(series[0].iter().map(|x| x.abs()).collect()),
+                    Function::Min => Series::Numbers(zip(&series[0], &series[1], f64::min)),
+                    Function::Max => Series::Numbers(zip(&series[0], &series[1], f64::max)),
+                }
+            }
+        })
+    }
+}
+
+// The kinds of the operands were checked when parsing, a mismatch can't happen.
+fn numbers(series: Series) -> Vec<f64> {
+    match series {
+        Series::Numbers(values) => values,
+        Series::Conditions(values) => vec![f64::NAN; values.len()],
+    }
+}
+
+fn conditions(series: Series) -> Vec<Option<bool>> {
+    match series {
+        Series::Conditions(values) => values,
+        Series::Numbers(values) => vec![None; values.len()],
+    }
+}
+
+fn shift<T: Copy>(values: &[T], offset: usize, undefined: T) -> Vec<T> {
+    (0..values.len())
+        .map(|i| i.checked_sub(offset).map_or(undefined, |j| values[j]))
+        .collect()
+}
+
+/// NaN where either side is, unlike `f64::min` and `f64::max` on their own.
+fn zip(left: &[f64], right: &[f64], f: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    left.iter()
+        .zip(right)
+        .map(|(x, y)| {
+            if x.is_nan() || y.is_nan() {
+                f64::NAN
+            } else {
+                f(*x, *y)
+            }
+        })
+        .collect()
+}
+
+/// Where `a` goes from at most `b` to above it.
+fn crosses(a: &[f64], b: &[f64]) -> Vec<Option<bool>> {
+    (0..a.len())
+        .map(|i| {
+            if i == 0 || [a[i - 1], a[i], b[i - 1], b[i]].iter().any(|x| x.is_nan()) {
+                return None;
+            }
+            Some(a[i - 1] <= b[i - 1] && a[i] > b[i])
+        })
+        .collect()
+}
+
+fn compare(left: Series, right: Series, f: impl Fn(f64, f64) -> bool) -> Vec<Option<bool>> {
+    numbers(left)
+        .iter()
+        .zip(numbers(right))
+        .map(|(x, y)| (!x.is_nan() && !y.is_nan()).then(|| f(*x, y)))
+        .collect()
+}
+
+fn arithmetic(left: Series, right: Series, f: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+    numbers(left)
+        .iter()
+        .zip(numbers(right))
+        .map(|(x, y)| f(*x, y))
+        .collect()
+}
+
+fn apply(op: BinaryOp, left: Series, right: Series) -> Series {
+    match op {
+        BinaryOp::Add => Series::Numbers(arithmetic(left, right, |x, y| x + y)),
+        BinaryOp::Subtract => Series::Numbers(arithmetic(left, right, |x, y| x - y)),
+        BinaryOp::Multiply => Series::Numbers(arithmetic(left, right, |x, y| x * y)),
+        BinaryOp::Divide => Series::Numbers(arithmetic(left, right, |x, y| x / y)),
+        BinaryOp::Less => Series::Conditions(compare(left, right, |x, y| x < y)),
+        BinaryOp::LessOrEqual => Series::Conditions(compare(left, right, |x, y| x <= y)),
+        BinaryOp::Greater => Series::Conditions(compare(left, right, |x, y| x > y)),
+        BinaryOp::GreaterOrEqual => Series::Conditions(compare(left, right, |x, y| x >= y)),
+        BinaryOp::Equal | BinaryOp::NotEqual => {
+            let equal: Vec<Option<bool>> = match (left, right) {
+                (Series::Conditions(x), Series::Conditions(y)) => {
+                    x.iter().zip(y).map(|(x, y)| Some((*x)? == y?)).collect()
+                }
+                (left, right) => compare(left, right, |x, y| x == y),
+            };
+            if op == BinaryOp::Equal {
+                Series::Conditions(equal)
+            } else {
+                Series::Conditions(equal.iter().map(|x| x.map(|x| !x)).collect())
+            }
+        }
+        // Undefined points are unknown rather than false: `false and unknown` is
+        // false, `true and unknown` unknown.
+        BinaryOp::And | BinaryOp::Or => Series::Conditions(
+            conditions(left)
+                .into_iter()
+                .zip(conditions(right))
+                .map(|(x, y)| {
+                    let absorbing = op == BinaryOp::Or;
+                    if x == Some(absorbing) || y == Some(absorbing) {
+                        Some(absorbing)
+                    } else if x.is_some() && y.is_some() {
+                        Some(!absorbing)
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+        ),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(&'static str),
+    /// One of `( ) [ ] ,`
+    Punctuation(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Identifier(name) => f.write_str(name),
+            Token::Operator(op) => f.write_str(op),
+            Token::Punctuation(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+/// Longest first, so that `<=` isn't read as `<` followed by `=`.
+const OPERATORS: [&str; 13] = [
+    "<=", ">=", "==", "!=", "&&", "||", "<", ">", "+", "-", "*", "/", "!",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = source[start..end].parse().map_err(|_| {
+                ExpressionError::new(start, format!("invalid number `{}`", &source[start..end]))
+            })?;
+            tokens.push((start, Token::Number(number)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let token = match &source[start..end] {
+                "and" => Token::Operator("&&"),
+                "or" => Token::Operator("||"),
+                "not" => Token::Operator("!"),
+                name => Token::Identifier(name.to_owned()),
+            };
+            tokens.push((start, token));
+        } else if "()[],".contains(c) {
+            chars.next();
+            tokens.push((start, Token::Punctuation(c)));
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| source[start..].starts_with(*op))
+                .ok_or_else(|| ExpressionError::new(start, format!("unexpected `{}`", c)))?;
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push((start, Token::Operator(op)));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent, one method per precedence level from the loosest, checking
+/// the kind of every operand on the way.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// How deeply nested the current operand is, bounded so that the recursion
+    /// can't overflow the stack.
+    depth: usize,
+    /// Length of the source, the position reported for a premature end.
+    end: usize,
+}
+
+type Level = fn(&mut Parser) -> Result<Expr, ExpressionError>;
+
+impl Parser {
+    fn peek(&self) -> Option<(usize, &Token)> {
+        self.tokens
+            .get(self.position)
+            .map(|(position, token)| (*position, token))
+    }
+
+    /// Where the next token starts.
+    fn offset(&self) -> usize {
+        self.peek().map_or(self.end, |(position, _)| position)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), ExpressionError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| ExpressionError::new(self.end, "unexpected end of expression"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is one of `operators`.
+    fn operator(&mut self, operators: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some((_, Token::Operator(op))) if operators.contains(op) => {
+                let op = *op;
+                self.position += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes the next token if it is `c`.
+    fn punctuation(&mut self, c: char) -> bool {
+        let found = matches!(self.peek(), Some((_, Token::Punctuation(p))) if *p == c);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_punctuation(&mut self, c: char) -> Result<(), ExpressionError> {
+        if self.punctuation(c) {
+            return Ok(());
+        }
+        let message = match self.peek() {
+            Some((_, token)) => format!("expected `{}`, got `{}`", c, token),
+            None => format!("expected `{}`", c),
+        };
+        Err(ExpressionError::new(self.offset(), message))
+    }
+
+    /// Parses with `level` one nesting deeper.
+    fn nested(&mut self, level: Level) -> Result<Expr, ExpressionError> {
+        if self.depth == Expr::MAX_DEPTH {
+            return Err(ExpressionError::new(
+                self.offset(),
+                format!("expression nested deeper than {} levels", Expr::MAX_DEPTH),
+            ));
+        }
+        self.depth += 1;
+        let expr = level(self);
+        self.depth -= 1;
+        expr
+    }
+
+    /// Parses with `level`, checking that the result is of the `expected` kind.
+    fn operand(&mut self, expected: Kind, level: Level) -> Result<Expr, ExpressionError> {
+        let position = self.offset();
+        let expr = level(self)?;
+        check(&expr, expected, position)?;
+        Ok(expr)
+    }
+
+    /// A left associative chain of `level` operands joined by `operators`.
+    fn chain(
+        &mut self,
+        level: Level,
+        operators: &[(&str, BinaryOp)],
+        expected: Kind,
+    ) -> Result<Expr, ExpressionError> {
+        let position = self.offset();
+        let mut expr = level(self)?;
+        let names: Vec<&str> = operators.iter().map(|(name, _)| *name).collect();
+        while let Some(name) = self.operator(&names) {
+            check(&expr, expected, position)?;
+            let op = operators.iter().find(|(n, _)| *n == name).unwrap().1;
+            let right = self.operand(expected, level)?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain(Self::and, &[("||", BinaryOp::Or)], Kind::Condition)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain(Self::not, &[("&&", BinaryOp::And)], Kind::Condition)
+    }
+
+    fn not(&mut self) -> Result<Expr, ExpressionError> {
+        if self.operator(&["!"]).is_some() {
+            let operand = self.operand(Kind::Condition, |parser| parser.nested(Self::not))?;
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(operand)));
+        }
+        self.comparison()
+    }
+
+    /// Comparisons don't chain: `a < b < c` is an error.
+    fn comparison(&mut self) -> Result<Expr, ExpressionError> {
+        let position = self.offset();
+        let left = self.sum()?;
+        let op = match self.operator(&["<", "<=", ">", ">=", "==", "!="]) {
+            Some("<") => BinaryOp::Less,
+            Some("<=") => BinaryOp::LessOrEqual,
+            Some(">") => BinaryOp::Greater,
+            Some(">=") => BinaryOp::GreaterOrEqual,
+            Some("==") => BinaryOp::Equal,
+            Some(_) => BinaryOp::NotEqual,
+            None => return Ok(left),
+        };
+        let right_position = self.offset();
+        let right = self.sum()?;
+        if matches!(op, BinaryOp::Equal | BinaryOp::NotEqual) {
+            check(&right, left.kind(), right_position)?;
+        } else {
+            check(&left, Kind::Number, position)?;
+            check(&right, Kind::Number, right_position)?;
+        }
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain(
+            Self::product,
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Subtract)],
+            Kind::Number,
+        )
+    }
+
+    fn product(&mut self) -> Result<Expr, ExpressionError> {
+        self.chain(
+            Self::unary,
+            &[("*", BinaryOp::Multiply), ("/", BinaryOp::Divide)],
+            Kind::Number,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExpressionError> {
+        if self.operator(&["-"]).is_some() {
+            let operand = self.operand(Kind::Number, |parser| parser.nested(Self::unary))?;
+            return Ok(Expr::Unary(UnaryOp::Negate, Box::new(operand)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ExpressionError> {
+        let mut expr = self.atom()?;
+        while self.punctuation('[') {
+            let offset = match self.next()? {
+                (_, Token::Number(number))
+                    if number >= 0.0
+                        && number.fract() == 0.0
+                        && number <= OhlcParams::MAX_LIMIT as f64 =>
+                {
+                    number as usize
+                }
+                (position, _) => {
+                    return Err(ExpressionError::new(
+                        position,
+                        format!(
+                            "expected a whole number of candles up to {}",
+                            OhlcParams::MAX_LIMIT
+                        ),
+                    ))
+                }
+            };
+            self.expect_punctuation(']')?;
+            expr = Expr::Offset(Box::new(expr), offset);
+        }
+        Ok(expr)
+    }
+
+    fn atom(&mut self) -> Result<Expr, ExpressionError> {
+        match self.next()? {
+            (_, Token::Number(number)) => Ok(Expr::Number(number)),
+            (position, Token::Identifier(name)) => {
+                if self.punctuation('(') {
+                    return self.call(position, &name);
+                }
+                Ok(Expr::Variable(name))
+            }
+            (_, Token::Punctuation('(')) => {
+                let expr = self.nested(Self::or)?;
+                self.expect_punctuation(')')?;
+                Ok(expr)
+            }
+            (position, token) => Err(ExpressionError::new(
+                position,
+                format!("unexpected `{}`", token),
+            )),
+        }
+    }
+
+    /// The arguments of `name(`, up to the closing parenthesis.
+    fn call(&mut self, position: usize, name: &str) -> Result<Expr, ExpressionError> {
+        let function = Function::parse(name).ok_or_else(|| {
+            ExpressionError::new(position, format!("unknown function `{}`", name))
+        })?;
+
+        let mut arguments = vec![];
+        if !self.punctuation(')') {
+            loop {
+                arguments.push((self.offset(), self.nested(Self::or)?));
+                if self.punctuation(')') {
+                    break;
+                }
+                self.expect_punctuation(',')?;
+            }
+        }
+
+        let period = match function.minimum_period() {
+            None => None,
+            Some(minimum) => match arguments.pop() {
+                Some((_, Expr::Number(period)))
+                    if period.fract() == 0.0
+                        && period >= minimum as f64
+                        && period <= OhlcParams::MAX_LIMIT as f64 =>
+                {
+                    Some(period as usize)
+                }
+                Some((period_position, _)) => {
+                    return Err(ExpressionError::new(
+                        period_position,
+                        format!(
+                            "the period of `{}` must be a whole number between {} and {}",
+                            name,
+                            minimum,
+                            OhlcParams::MAX_LIMIT
+                        ),
+                    ))
+                }
+                None => {
+                    return Err(ExpressionError::new(
+                        position,
+                        format!("`{}` needs a period", name),
+                    ))
+                }
+            },
+        };
+
+        if !function.series_arguments().contains(&arguments.len()) {
+            return Err(ExpressionError::new(
+                position,
+                format!("wrong number of arguments to `{}`", name),
+            ));
+        }
+        let mut series = vec![];
+        for (argument_position, argument) in arguments {
+            check(&argument, Kind::Number, argument_position)?;
+            series.push(argument);
+        }
+        Ok(Expr::Call(function, series, period))
+    }
+}
+
+fn check(expr: &Expr, expected: Kind, position: usize) -> Result<(), ExpressionError> {
+    if expr.kind() != expected {
+        return Err(ExpressionError::new(
+            position,
+            format!("expected {}, got {}", expected, expr.kind()),
+        ));
+    }
+    Ok(())
+}
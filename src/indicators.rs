@@ -0,0 +1,357 @@
+//! Indicator math over plain value series, ordered oldest first.
+//!
+//! Except for `simple_moving_average`, every function returns one value per input
+//! value, NaN where there isn't enough data yet.
+
+use crate::crypto_client::{OhlcParams, ParsedOhlc};
+use crate::error::{require_data, ApiError};
+use std::str::FromStr;
+
+/// Average of every `window` consecutive values: one value per complete window,
+/// so `values.len() - window + 1` of them, and none if the window doesn't fit.
+pub fn simple_moving_average(values: &[f64], window: usize) -> Vec<f64> {
+    if window == 0 || window > values.len() {
+        return vec![];
+    }
+
+    let mut averages = Vec::with_capacity(values.len() - window + 1);
+    let mut sum: f64 = values[..window].iter().sum();
+    averages.push(sum / window as f64);
+    for i in window..values.len() {
+        sum += values[i] - values[i - window];
+        averages.push(sum / window as f64);
+    }
+    averages
+}
+
+/// Applies `f` to every window of `period` values, the result being aligned with
+/// `values`: NaN until the first window is complete, and for windows holding a NaN.
+fn rolling(values: &[f64], period: usize, f: impl Fn(&[f64]) -> f64) -> Vec<f64> {
+    let period = period.max(1);
+    (0..values.len())
+        .map(|i| {
+            if i + 1 < period {
+                return f64::NAN;
+            }
+            let window = &values[i + 1 - period..=i];
+            if window.iter().any(|value| value.is_nan()) {
+                f64::NAN
+            } else {
+                f(window)
+            }
+        })
+        .collect()
+}
+
+pub fn sma(values: &[f64], period: usize) -> Vec<f64> {
+    rolling(values, period, |window| {
+        window.iter().sum::<f64>() / window.len() as f64
+    })
+}
+
+/// Population standard deviation over each window.
+pub fn stddev(values: &[f64], period: usize) -> Vec<f64> {
+    rolling(values, period, |window| {
+        let mean = window.iter().sum::<f64>() / window.len() as f64;
+        let variance = window
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / window.len() as f64;
+        variance.sqrt()
+    })
+}
+
+pub fn highest(values: &[f64], period: usize) -> Vec<f64> {
+    rolling(values, period, |window| {
+        window.iter().copied().fold(f64::MIN, f64::max)
+    })
+}
+
+pub fn lowest(values: &[f64], period: usize) -> Vec<f64> {
+    rolling(values, period, |window| {
+        window.iter().copied().fold(f64::MAX, f64::min)
+    })
+}
+
+/// EMA seeded with the average of the first `period` values. A NaN value yields
+/// NaN and leaves the average unchanged.
+pub fn ema(values: &[f64], period: usize) -> Vec<f64> {
+    let period = period.max(1);
+    let smoothing = 2. / (1. + period as f64);
+    let seeds = sma(values, period);
+    let mut average: Option<f64> = None;
+    values
+        .iter()
+        .zip(seeds)
+        .map(|(value, seed)| match average {
+            None if !seed.is_nan() => {
+                average = Some(seed);
+                seed
+            }
+            Some(previous) if !value.is_nan() => {
+                let next = value * smoothing + previous * (1. - smoothing);
+                average = Some(next);
+                next
+            }
+            _ => f64::NAN,
+        })
+        .collect()
+}
+
+/// RSI over the `period - 1` changes between the `period` most recent closes.
+pub fn rsi(closes: &[f64], period: usize) -> Vec<f64> {
+    rolling(closes, period, |window| {
+        let mut sum_of_gains = 0.0;
+        let mut sum_of_losses = 0.0;
+        for pair in window.windows(2) {
+            let x = pair[1] - pair[0];
+            match x {
+                res if res > 0.0 => sum_of_gains += x,
+                res if res < 0.0 => sum_of_losses += x.abs(),
+                _ => {}
+            };
+        }
+
+        let average_gain = sum_of_gains / period as f64;
+        let average_losses = sum_of_losses / period as f64;
+
+        let rs = average_gain / average_losses;
+
+        let second_part = 100.0 / (1.0 + rs);
+        100.0 - second_part
+    })
+}
+
+/// Where the close sits between the lowest low and the highest high of the window, in percent.
+pub fn stochastic_oscillator(
+    highs: &[f64],
+    lows: &[f64],
+    closes: &[f64],
+    period: usize,
+) -> Vec<f64> {
+    let highest_high = highest(highs, period);
+    let lowest_low = lowest(lows, period);
+    (0..closes.len())
+        .map(|i| {
+            let numerator = closes[i] - lowest_low[i];
+
+            let denominator = highest_high[i] - lowest_low[i];
+
+            (numerator / denominator) * 100.
+        })
+        .collect()
+}
+
+/// Aroon up minus Aroon down, the candles since the highest high and the lowest low
+/// being counted within a window of `period` candles.
+pub fn aroon_oscillator(highs: &[f64], lows: &[f64], period: usize) -> Vec<f64> {
+    // Position of the most recent extreme, counted from the end of the window.
+    fn since(window: &[f64], better: impl Fn(f64, f64) -> bool) -> f64 {
+        let mut best = window.len() - 1;
+        for i in (0..window.len()).rev() {
+            if better(window[i], window[best]) {
+                best = i;
+            }
+        }
+        (window.len() - 1 - best) as f64
+    }
+
+    let period_f = period.max(1) as f64;
+    let aroon_up = rolling(highs, period, |window| {
+        100.0 * ((period_f - since(window, |a, b| a > b)) / period_f)
+    });
+    let aroon_down = rolling(lows, period, |window| {
+        100.0 * ((period_f - since(window, |a, b| a < b)) / period_f)
+    });
+
+    aroon_up
+        .iter()
+        .zip(aroon_down)
+        .map(|(up, down)| up - down)
+        .collect()
+}
+
+/// The MACD line (fast EMA minus slow EMA), its signal line (an EMA of the MACD
+/// line) and the histogram (MACD minus signal).
+pub struct Macd {
+    pub macd: Vec<f64>,
+    pub signal: Vec<f64>,
+    pub histogram: Vec<f64>,
+}
+
+pub fn macd(closes: &[f64], fast: usize, slow: usize, signal: usize) -> Macd {
+    let macd: Vec<f64> = ema(closes, fast)
+        .iter()
+        .zip(ema(closes, slow))
+        .map(|(fast, slow)| fast - slow)
+        .collect();
+    let signal = ema(&macd, signal);
+    let histogram = macd.iter().zip(&signal).map(|(m, s)| m - s).collect();
+    Macd {
+        macd,
+        signal,
+        histogram,
+    }
+}
+
+/// The SMA of the closes with bands `width` standard deviations above and below it.
+pub struct BollingerBands {
+    pub middle: Vec<f64>,
+    pub upper: Vec<f64>,
+    pub lower: Vec<f64>,
+}
+
+pub fn bollinger_bands(closes: &[f64], period: usize, width: f64) -> BollingerBands {
+    let middle = sma(closes, period);
+    let deviation = stddev(closes, period);
+    let band = |sign: f64| -> Vec<f64> {
+        middle
+            .iter()
+            .zip(&deviation)
+            .map(|(m, d)| m + sign * width * d)
+            .collect()
+    };
+    BollingerBands {
+        upper: band(1.0),
+        lower: band(-1.0),
+        middle,
+    }
+}
+
+/// The single valued OHLC indicators, by the name of their route.
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Indicator {
+    Rsi,
+    AroonOscillator,
+    StochasticOscillator,
+    #[serde(alias = "ema")]
+    ExponentialMovingAverage,
+}
+
+impl FromStr for Indicator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rsi" => Ok(Indicator::Rsi),
+            "aroon_oscillator" => Ok(Indicator::AroonOscillator),
+            "stochastic_oscillator" => Ok(Indicator::StochasticOscillator),
+            "exponential_moving_average" | "ema" => Ok(Indicator::ExponentialMovingAverage),
+            _ => Err(format!("unknown indicator `{}`", s)),
+        }
+    }
+}
+
+impl Indicator {
+    pub const ALL: [Indicator; 4] = [
+        Indicator::Rsi,
+        Indicator::AroonOscillator,
+        Indicator::StochasticOscillator,
+        Indicator::ExponentialMovingAverage,
+    ];
+
+    /// The period of the dedicated route, which isn't configurable there.
+    pub fn default_period(&self) -> usize {
+        match self {
+            Indicator::Rsi | Indicator::StochasticOscillator => 14,
+            Indicator::AroonOscillator => 25,
+            Indicator::ExponentialMovingAverage => 20,
+        }
+    }
+
+    /// Checks a requested period, `None` meaning the default one.
+    pub fn period(&self, period: Option<usize>) -> Result<usize, ApiError> {
+        let max = OhlcParams::MAX_LIMIT as usize / self.candles_required(1);
+        match period {
+            None => Ok(self.default_period()),
+            Some(period) if (2..=max).contains(&period) => Ok(period),
+            Some(_) => Err(ApiError::bad_parameter(
+                "period",
+                format!("must be between 2 and {}", max),
+            )),
+        }
+    }
+
+    /// The candles a value depends on. The EMA is warmed up over as many candles
+    /// again as its period, a fixed window so that fetching more doesn't move it.
+    pub fn candles_required(&self, period: usize) -> usize {
+        match self {
+            Indicator::ExponentialMovingAverage => period.saturating_mul(2),
+            _ => period,
+        }
+    }
+
+    /// The candles fetched when no `limit` is given.
+    pub fn default_limit(&self, period: usize) -> u32 {
+        (self.candles_required(period) as u32).max(OhlcParams::DEFAULT_LIMIT)
+    }
+
+    /// The value for every candle, oldest first like `candles`.
+    pub fn series(&self, candles: &[ParsedOhlc], period: usize) -> Vec<f64> {
+        let column =
+            |field: fn(&ParsedOhlc) -> f64| -> Vec<f64> { candles.iter().map(field).collect() };
+        let closes = column(|ohlc| ohlc.close);
+        match self {
+            Indicator::Rsi => rsi(&closes, period),
+            Indicator::AroonOscillator => {
+                aroon_oscillator(&column(|ohlc| ohlc.high), &column(|ohlc| ohlc.low), period)
+            }
+            Indicator::StochasticOscillator => stochastic_oscillator(
+                &column(|ohlc| ohlc.high),
+                &column(|ohlc| ohlc.low),
+                &closes,
+                period,
+            ),
+            Indicator::ExponentialMovingAverage => {
+                rolling(&closes, self.candles_required(period), |window| {
+                    ema(window, period)[window.len() - 1]
+                })
+            }
+        }
+    }
+
+    /// The latest value, `candles` being newest first as `CryptoClient` returns them.
+    pub fn compute(&self, candles: &[ParsedOhlc], period: usize) -> Result<f64, ApiError> {
+        let required = self.candles_required(period);
+        require_data(required, candles.len())?;
+        let chronological: Vec<ParsedOhlc> = candles[..required].iter().rev().cloned().collect();
+        Ok(self.series(&chronological, period)[required - 1])
+    }
+
+    /// Reads a value as a trend: RSI and stochastic above or below 50, Aroon
+    /// oscillator above or below 0, last close above or below the EMA.
+    pub fn bias(&self, value: f64, last_close: f64) -> Bias {
+        let midpoint = match self {
+            Indicator::Rsi | Indicator::StochasticOscillator => 50.0,
+            Indicator::AroonOscillator => 0.0,
+            Indicator::ExponentialMovingAverage => {
+                return Bias::from_difference(last_close - value)
+            }
+        };
+        Bias::from_difference(value - midpoint)
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Bias {
+    Bullish,
+    Bearish,
+    Neutral,
+}
+
+impl Bias {
+    fn from_difference(difference: f64) -> Self {
+        if difference > 0.0 {
+            Bias::Bullish
+        } else if difference < 0.0 {
+            Bias::Bearish
+        } else {
+            Bias::Neutral
+        }
+    }
+}
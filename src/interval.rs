@@ -0,0 +1,150 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Candle intervals. The ones Coinranking's OHLC endpoint doesn't support are
+/// built by resampling the largest native interval that divides them. Ordered by length.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+pub enum Interval {
+    #[serde(rename = "minute")]
+    Minute,
+    #[serde(rename = "5minutes")]
+    FiveMinutes,
+    #[serde(rename = "15minutes")]
+    FifteenMinutes,
+    #[serde(rename = "30minutes")]
+    ThirtyMinutes,
+    #[serde(rename = "hour")]
+    Hour,
+    #[serde(rename = "4hours")]
+    FourHours,
+    #[serde(rename = "8hours")]
+    EightHours,
+    #[serde(rename = "12hours")]
+    TwelveHours,
+    #[default]
+    #[serde(rename = "day")]
+    Day,
+    #[serde(rename = "3days")]
+    ThreeDays,
+    #[serde(rename = "week")]
+    Week,
+    #[serde(rename = "month")]
+    Month,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 12] = [
+        Interval::Minute,
+        Interval::FiveMinutes,
+        Interval::FifteenMinutes,
+        Interval::ThirtyMinutes,
+        Interval::Hour,
+        Interval::FourHours,
+        Interval::EightHours,
+        Interval::TwelveHours,
+        Interval::Day,
+        Interval::ThreeDays,
+        Interval::Week,
+        Interval::Month,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::Minute => "minute",
+            Interval::FiveMinutes => "5minutes",
+            Interval::FifteenMinutes => "15minutes",
+            Interval::ThirtyMinutes => "30minutes",
+            Interval::Hour => "hour",
+            Interval::FourHours => "4hours",
+            Interval::EightHours => "8hours",
+            Interval::TwelveHours => "12hours",
+            Interval::Day => "day",
+            Interval::ThreeDays => "3days",
+            Interval::Week => "week",
+            Interval::Month => "month",
+        }
+    }
+
+    /// Nominal length of a candle, months are counted as 30 days.
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Interval::Minute => 60,
+            Interval::FiveMinutes => 5 * 60,
+            Interval::FifteenMinutes => 15 * 60,
+            Interval::ThirtyMinutes => 30 * 60,
+            Interval::Hour => 60 * 60,
+            Interval::FourHours => 4 * 60 * 60,
+            Interval::EightHours => 8 * 60 * 60,
+            Interval::TwelveHours => 12 * 60 * 60,
+            Interval::Day => 24 * 60 * 60,
+            Interval::ThreeDays => 3 * 24 * 60 * 60,
+            Interval::Week => 7 * 24 * 60 * 60,
+            Interval::Month => 30 * 24 * 60 * 60,
+        }
+    }
+
+    /// Whether Coinranking returns candles of this interval directly.
+    pub fn is_native(&self) -> bool {
+        matches!(
+            self,
+            Interval::Minute
+                | Interval::Hour
+                | Interval::EightHours
+                | Interval::Day
+                | Interval::Week
+                | Interval::Month
+        )
+    }
+
+    /// The native interval to fetch in order to build this one: itself when native,
+    /// otherwise the largest native interval that divides it.
+    pub fn native_source(&self) -> Interval {
+        if self.is_native() {
+            return *self;
+        }
+        Interval::ALL
+            .into_iter()
+            .filter(|interval| interval.is_native() && self.seconds() % interval.seconds() == 0)
+            .max_by_key(|interval| interval.seconds())
+            .unwrap_or(Interval::Minute)
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Interval::ALL.iter().map(|i| i.as_str()).collect();
+                format!(
+                    "unknown interval `{}`, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
@@ -1,4 +1,28 @@
+pub mod alerts;
+pub mod auth;
+pub mod backtest;
+pub mod batch;
+pub mod cache;
+pub mod candles;
+pub mod circuit_breaker;
 pub mod configuration;
 pub mod crypto_client;
+pub mod divergence;
+pub mod error;
+pub mod expression;
+pub mod indicators;
+pub mod interval;
+pub mod live;
+pub mod metrics;
+pub mod missing_data;
+pub mod openapi;
+pub mod optimize;
+pub mod precompute;
+pub mod readiness;
+pub mod resample;
 pub mod routes;
+pub mod screener;
+pub mod signals;
+pub mod single_flight;
 pub mod startup;
+pub mod telemetry;
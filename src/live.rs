@@ -0,0 +1,333 @@
+//! Pushing indicator values to WebSocket and SSE subscribers as candles form and close.
+//!
+//! A single background poller fetches the latest candles of every subscribed coin
+//! and interval, once for all the subscribers asking for them, and broadcasts an
+//! event whenever the value of a subscription changes. Events are numbered, the
+//! most recent ones being kept for SSE clients resuming after a disconnection.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::web::Data;
+use tokio::sync::{broadcast, Notify};
+
+use crate::batch::{run_jobs, Job};
+use crate::candles::CandleRequest;
+use crate::crypto_client::CryptoClient;
+use crate::error::{ApiError, ErrorBody};
+use crate::indicators::Indicator;
+use crate::interval::Interval;
+
+const MAX_CONCURRENT_FETCHES: usize = 8;
+/// Events kept for subscribers reading slowly, older ones being dropped.
+const EVENT_BUFFER: usize = 256;
+/// Events kept for clients resuming from an earlier one.
+const HISTORY: usize = 1_000;
+
+/// What a client subscribes to, e.g.
+/// `{"coin": "btc", "indicator": "rsi", "params": {"period": 14, "interval": "hour"}}`.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SubscriptionRequest {
+    coin: String,
+    indicator: Indicator,
+    #[serde(default)]
+    params: SubscriptionParams,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+struct SubscriptionParams {
+    period: Option<usize>,
+    interval: Option<Interval>,
+}
+
+impl SubscriptionRequest {
+    pub fn subscription(&self) -> Result<Subscription, ApiError> {
+        Subscription::new(
+            &self.coin,
+            self.indicator,
+            self.params.period,
+            self.params.interval,
+        )
+    }
+}
+
+/// A validated subscription, shared by every client asking for the same values.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq, Hash, utoipa::ToSchema)]
+pub struct Subscription {
+    pub coin: String,
+    pub indicator: Indicator,
+    pub period: usize,
+    pub interval: Interval,
+}
+
+impl Subscription {
+    /// Checks the period, `None` meaning the one of the dedicated route, and
+    /// defaults to daily candles.
+    pub fn new(
+        coin: &str,
+        indicator: Indicator,
+        period: Option<usize>,
+        interval: Option<Interval>,
+    ) -> Result<Self, ApiError> {
+        if coin.trim().is_empty() {
+            return Err(ApiError::bad_parameter("coin", "must not be empty"));
+        }
+        Ok(Self {
+            coin: coin.trim().to_uppercase(),
+            indicator,
+            period: indicator.period(period)?,
+            interval: interval.unwrap_or_default(),
+        })
+    }
+
+    /// The job fetching the latest candles of the subscription, bypassing the cache.
+    pub fn job(&self) -> Job {
+        Job {
+            coin: self.coin.clone(),
+            indicator: self.indicator,
+            period: self.period,
+            candles: CandleRequest {
+                interval: self.interval,
+                // One more candle for alerts, which also look at the previous value.
+                limit: self
+                    .indicator
+                    .default_limit(self.period)
+                    .max(self.indicator.candles_required(self.period) as u32 + 1)
+                    .min(CandleRequest::max_limit(self.interval)),
+                latest: true,
+                ..CandleRequest::default()
+            },
+        }
+    }
+}
+
+/// The messages sent to WebSocket clients.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Subscribed {
+        subscription: Subscription,
+    },
+    Unsubscribed {
+        subscription: Subscription,
+    },
+    /// The value on the newest candle, sent again whenever it or the candle changes.
+    Update {
+        subscription: Subscription,
+        /// Start of the newest candle.
+        timestamp: i64,
+        close: f64,
+        value: f64,
+    },
+    /// A failed poll of a subscription, or a message the server couldn't act on.
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        subscription: Option<Subscription>,
+        /// The body of the equivalent REST error.
+        #[schema(value_type = ErrorBody)]
+        error: serde_json::Value,
+    },
+}
+
+impl Event {
+    /// The `type` of the event, also its SSE event name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Subscribed { .. } => "subscribed",
+            Event::Unsubscribed { .. } => "unsubscribed",
+            Event::Update { .. } => "update",
+            Event::Error { .. } => "error",
+        }
+    }
+
+    pub fn subscription(&self) -> Option<&Subscription> {
+        match self {
+            Event::Subscribed { subscription }
+            | Event::Unsubscribed { subscription }
+            | Event::Update { subscription, .. } => Some(subscription),
+            Event::Error { subscription, .. } => subscription.as_ref(),
+        }
+    }
+}
+
+/// An event broadcast by the poller, numbered from 1 in the order of publication.
+#[derive(Debug, PartialEq)]
+pub struct Published {
+    pub id: u64,
+    pub event: Event,
+}
+
+#[derive(Default)]
+struct Subscriptions {
+    /// Clients subscribed to each subscription.
+    clients: HashMap<Subscription, usize>,
+    /// The last event published for each subscription.
+    latest: HashMap<Subscription, Arc<Published>>,
+    /// The last `HISTORY` events published, oldest first.
+    history: VecDeque<Arc<Published>>,
+    last_id: u64,
+}
+
+pub struct LiveHub {
+    subscriptions: Mutex<Subscriptions>,
+    events: broadcast::Sender<Arc<Published>>,
+    /// Wakes the poller up early, for new subscriptions to get a first value.
+    wake: Notify,
+    poll_interval: Duration,
+    /// Distinct subscriptions polled at once, each one costing upstream requests.
+    max_subscriptions: usize,
+}
+
+impl LiveHub {
+    pub fn new(poll_interval: Duration, max_subscriptions: usize) -> Self {
+        Self {
+            subscriptions: Mutex::new(Subscriptions::default()),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            wake: Notify::new(),
+            poll_interval,
+            max_subscriptions,
+        }
+    }
+
+    /// False once a thread panicked while holding the subscriptions.
+    pub fn is_healthy(&self) -> bool {
+        !self.subscriptions.is_poisoned()
+    }
+
+    /// The events of every subscription, for the caller to filter.
+    pub fn events(&self) -> broadcast::Receiver<Arc<Published>> {
+        self.events.subscribe()
+    }
+
+    /// The events published after `last_id`, or `None` when some of them are no
+    /// longer kept or `last_id` was never published, e.g. before a restart.
+    pub fn replay(&self, last_id: u64) -> Option<Vec<Arc<Published>>> {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        let first_kept = subscriptions
+            .history
+            .front()
+            .map_or(subscriptions.last_id + 1, |published| published.id);
+        if last_id > subscriptions.last_id || last_id + 1 < first_kept {
+            return None;
+        }
+        Some(
+            subscriptions
+                .history
+                .iter()
+                .filter(|published| published.id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Registers one more client, returning the last event of the subscription if
+    /// one was already polled. Fails when the subscription would be polled on top of
+    /// `max_subscriptions` others.
+    pub fn subscribe(
+        &self,
+        subscription: &Subscription,
+    ) -> Result<Option<Arc<Published>>, ApiError> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if !subscriptions.clients.contains_key(subscription)
+            && subscriptions.clients.len() >= self.max_subscriptions
+        {
+            return Err(ApiError::bad_parameter(
+                "subscription",
+                format!("at most {} are polled at once", self.max_subscriptions),
+            ));
+        }
+        let clients = subscriptions
+            .clients
+            .entry(subscription.clone())
+            .or_insert(0);
+        *clients += 1;
+        if *clients == 1 {
+            self.wake.notify_one();
+        }
+        Ok(subscriptions.latest.get(subscription).cloned())
+    }
+
+    pub fn unsubscribe(&self, subscription: &Subscription) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if let Some(clients) = subscriptions.clients.get_mut(subscription) {
+            *clients -= 1;
+            if *clients == 0 {
+                subscriptions.clients.remove(subscription);
+                subscriptions.latest.remove(subscription);
+            }
+        }
+    }
+
+    /// Polls every subscription until the process exits, immediately after a new
+    /// one and then every `poll_interval`.
+    pub async fn run_poller(hub: Data<LiveHub>, crypto_client: Data<CryptoClient>) {
+        loop {
+            let idle = hub.subscriptions.lock().unwrap().clients.is_empty();
+            if idle {
+                hub.wake.notified().await;
+            } else {
+                tokio::select! {
+                    _ = hub.wake.notified() => {}
+                    _ = tokio::time::sleep(hub.poll_interval) => {}
+                }
+            }
+            hub.poll(&crypto_client).await;
+        }
+    }
+
+    async fn poll(&self, crypto_client: &CryptoClient) {
+        let subscriptions: Vec<Subscription> = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .clients
+            .keys()
+            .cloned()
+            .collect();
+        let jobs: Vec<Job> = subscriptions.iter().map(Subscription::job).collect();
+        let outputs = run_jobs(crypto_client, &jobs, MAX_CONCURRENT_FETCHES).await;
+
+        let mut state = self.subscriptions.lock().unwrap();
+        for (subscription, output) in subscriptions.into_iter().zip(outputs) {
+            // Dropped while its candles were being fetched.
+            if !state.clients.contains_key(&subscription) {
+                continue;
+            }
+            let event = match output {
+                Ok(output) => {
+                    let newest = output
+                        .candles
+                        .ohlc
+                        .first()
+                        .expect("An indicator value comes from at least one candle");
+                    Event::Update {
+                        subscription: subscription.clone(),
+                        timestamp: newest.starting_at,
+                        close: newest.close,
+                        value: output.value,
+                    }
+                }
+                Err(err) => Event::Error {
+                    subscription: Some(subscription.clone()),
+                    error: err.body(),
+                },
+            };
+            if state.latest.get(&subscription).map(|latest| &latest.event) == Some(&event) {
+                continue;
+            }
+            state.last_id += 1;
+            let published = Arc::new(Published {
+                id: state.last_id,
+                event,
+            });
+            state.latest.insert(subscription, published.clone());
+            if state.history.len() == HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(published.clone());
+            // Failing only when nobody listens.
+            let _ = self.events.send(published);
+        }
+    }
+}
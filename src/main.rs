@@ -1,15 +1,17 @@
 use std::net::TcpListener;
 use taindicators::configuration::get_configuration;
 use taindicators::startup::run;
+use taindicators::telemetry;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     let configuration = get_configuration().expect("Failed to read configuration.");
+    telemetry::init(&configuration.logging).expect("Failed to set up logging.");
     let address = format!(
         "{}:{}",
         configuration.application.host, configuration.application.port
     );
     let listener = TcpListener::bind(address)?;
-    run(listener)?.await?;
+    run(listener, configuration)?.await?;
     Ok(())
 }
@@ -0,0 +1,250 @@
+//! Prometheus metrics, served in the text format by `/metrics`.
+//!
+//! Every application has its own registry, holding the requests per route and
+//! status with their latency, the requests in flight, the upstream calls per
+//! `CryptoClient` method with their latency and errors, and the cache statistics,
+//! the latter being read from the client at every scrape.
+
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+
+/// The route label of requests matching no route, so that unknown paths don't
+/// each get their own series.
+const UNMATCHED: &str = "unmatched";
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    http_requests_in_flight: IntGaugeVec,
+    upstream_requests: IntCounterVec,
+    upstream_request_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    cache_hits: IntCounterVec,
+    cache_misses: IntCounterVec,
+    cache_hit_ratio: GaugeVec,
+    cache_entries: IntGaugeVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        fn register<M: prometheus::core::Collector + Clone + 'static>(
+            registry: &Registry,
+            metric: prometheus::Result<M>,
+        ) -> M {
+            let metric = metric.expect("Valid metric");
+            registry
+                .register(Box::new(metric.clone()))
+                .expect("Metric registered once");
+            metric
+        }
+
+        Self {
+            http_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("http_requests_total", "HTTP requests handled."),
+                    &["method", "route", "status"],
+                ),
+            ),
+            http_request_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "http_request_duration_seconds",
+                        "Time to answer HTTP requests.",
+                    ),
+                    &["method", "route", "status"],
+                ),
+            ),
+            http_requests_in_flight: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new("http_requests_in_flight", "HTTP requests being handled."),
+                    &["method", "route"],
+                ),
+            ),
+            upstream_requests: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("upstream_requests_total", "Calls to the upstream API."),
+                    &["method"],
+                ),
+            ),
+            upstream_request_duration: register(
+                &registry,
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "upstream_request_duration_seconds",
+                        "Time taken by calls to the upstream API, retries included.",
+                    ),
+                    &["method"],
+                ),
+            ),
+            upstream_errors: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("upstream_errors_total", "Failed calls to the upstream API."),
+                    &["method", "code"],
+                ),
+            ),
+            cache_hits: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("cache_hits_total", "Lookups answered by the cache."),
+                    &["cache"],
+                ),
+            ),
+            cache_misses: register(
+                &registry,
+                IntCounterVec::new(
+                    Opts::new("cache_misses_total", "Lookups missing the cache."),
+                    &["cache"],
+                ),
+            ),
+            cache_hit_ratio: register(
+                &registry,
+                GaugeVec::new(
+                    Opts::new("cache_hit_ratio", "Hits over lookups since the start."),
+                    &["cache"],
+                ),
+            ),
+            cache_entries: register(
+                &registry,
+                IntGaugeVec::new(
+                    Opts::new("cache_entries", "Entries held by the cache."),
+                    &["cache"],
+                ),
+            ),
+            registry,
+        }
+    }
+
+    /// Records a call of a `CryptoClient` method that reached the upstream.
+    pub fn observe_upstream(&self, method: &str, duration: Duration, error: Option<&ApiError>) {
+        self.upstream_requests.with_label_values(&[method]).inc();
+        self.upstream_request_duration
+            .with_label_values(&[method])
+            .observe(duration.as_secs_f64());
+        if let Some(error) = error {
+            self.upstream_errors
+                .with_label_values(&[method, error.code()])
+                .inc();
+        }
+    }
+
+    /// Every metric in the Prometheus text format.
+    pub fn render(&self, crypto_client: &CryptoClient) -> String {
+        let stats = crypto_client.cache_stats();
+        for (cache, stats) in [
+            ("uuid", stats.uuid),
+            ("ohlc", stats.ohlc),
+            ("history", stats.history),
+        ] {
+            // The client counts on its own, the counters only catch up.
+            let hits = self.cache_hits.with_label_values(&[cache]);
+            hits.inc_by(stats.hits.saturating_sub(hits.get()));
+            let misses = self.cache_misses.with_label_values(&[cache]);
+            misses.inc_by(stats.misses.saturating_sub(misses.get()));
+            self.cache_hit_ratio
+                .with_label_values(&[cache])
+                .set(stats.hit_ratio);
+            self.cache_entries
+                .with_label_values(&[cache])
+                .set(stats.entries as i64);
+        }
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Metrics are encodable");
+        String::from_utf8(buffer).expect("The text format is UTF-8")
+    }
+}
+
+/// Counts and times every request by route pattern, e.g. `/rsi/{coin}`, and status.
+pub async fn track(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let metrics = req
+        .app_data::<Data<Metrics>>()
+        .expect("Metrics are registered in `startup::run`")
+        .clone();
+    let method = method_label(req.method());
+    let route = req.match_pattern().unwrap_or_else(|| UNMATCHED.to_owned());
+    let in_flight = InFlight::start(
+        metrics
+            .http_requests_in_flight
+            .with_label_values(&[method, &route]),
+    );
+    let started = Instant::now();
+
+    let response = next.call(req).await;
+
+    drop(in_flight);
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    let labels = [method, route.as_str(), status.as_str()];
+    metrics.http_requests.with_label_values(&labels).inc();
+    metrics
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
+
+/// The standard methods, labelled as themselves; any other method is labelled `other` so
+/// clients cannot grow the label set.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::PATCH => "PATCH",
+        Method::TRACE => "TRACE",
+        _ => "other",
+    }
+}
+
+/// Counts a request as in flight until dropped, including when the client goes away and
+/// the request's future is dropped before it completes.
+struct InFlight(IntGauge);
+
+impl InFlight {
+    fn start(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::ApiError;
+
+/// What to do with `null` or non-numeric values returned by the upstream, which
+/// Coinranking does for some periods of some coins.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingDataPolicy {
+    /// Drop every entry with a missing value.
+    #[default]
+    Skip,
+    /// Linearly interpolate missing values from the closest valid neighbours, by
+    /// timestamp. Entries at the edges, with no neighbour on one side, are dropped.
+    Interpolate,
+    /// Fail the whole request.
+    Error,
+}
+
+/// One upstream entry: the timestamp used to interpolate and its raw values.
+pub struct Row {
+    pub timestamp: i64,
+    pub values: Vec<Option<f64>>,
+}
+
+/// `None` for null, non-numeric and non-finite values.
+pub fn parse_value(raw: Option<&str>) -> Option<f64> {
+    raw?.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
+
+/// Applies `policy` to `rows`, returning the complete ones together with their
+/// index in `rows` so that callers can pick up the fields that aren't values.
+pub fn apply(
+    policy: MissingDataPolicy,
+    rows: Vec<Row>,
+) -> Result<Vec<(usize, Vec<f64>)>, ApiError> {
+    let rows = match policy {
+        MissingDataPolicy::Skip => rows,
+        MissingDataPolicy::Interpolate => interpolate(rows),
+        MissingDataPolicy::Error => {
+            if let Some(row) = rows
+                .iter()
+                .find(|row| row.values.iter().any(Option::is_none))
+            {
+                return Err(ApiError::UpstreamFailure(format!(
+                    "missing or non-numeric value at timestamp {}",
+                    row.timestamp
+                )));
+            }
+            rows
+        }
+    };
+
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, row)| {
+            let values: Option<Vec<f64>> = row.values.into_iter().collect();
+            values.map(|values| (index, values))
+        })
+        .collect())
+}
+
+fn interpolate(mut rows: Vec<Row>) -> Vec<Row> {
+    let columns = rows.iter().map(|row| row.values.len()).max().unwrap_or(0);
+
+    for column in 0..columns {
+        let known: Vec<(usize, i64, f64)> = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(index, row)| {
+                row.values
+                    .get(column)
+                    .copied()
+                    .flatten()
+                    .map(|value| (index, row.timestamp, value))
+            })
+            .collect();
+
+        for pair in known.windows(2) {
+            let (start, start_timestamp, start_value) = pair[0];
+            let (end, end_timestamp, end_value) = pair[1];
+            for row in rows.iter_mut().take(end).skip(start + 1) {
+                let span = (end_timestamp - start_timestamp) as f64;
+                let weight = if span == 0.0 {
+                    0.5
+                } else {
+                    (row.timestamp - start_timestamp) as f64 / span
+                };
+                if let Some(value) = row.values.get_mut(column) {
+                    *value = Some(start_value + (end_value - start_value) * weight);
+                }
+            }
+        }
+    }
+
+    rows
+}
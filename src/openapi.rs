@@ -0,0 +1,135 @@
+//! The OpenAPI document of the service, generated from the routes and the types
+//! they take and return so that it can't drift from them.
+//!
+//! Served as JSON by `/openapi.json` and rendered by `/docs`.
+
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, Ref, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+use crate::auth::API_KEY_HEADER;
+use crate::crypto_client::CacheKind;
+use crate::error::ErrorBody;
+use crate::routes;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "taindicators",
+        description = "Technical analysis indicators computed over Coinranking candles."
+    ),
+    paths(
+        routes::health_check::health_check,
+        routes::readiness::readiness,
+        routes::metrics::metrics,
+        routes::cache::cache_stats,
+        routes::cache::purge_cache,
+        routes::cache::purge_cache_kind,
+        routes::simple_moving_average::simple_moving_average,
+        routes::fibonacci_retracement::fibonacci_retracement,
+        routes::fibonacci_retracement::fibonacci_extension,
+        routes::rsi::rsi,
+        routes::aroon_oscillator::aroon_oscillator,
+        routes::stochastic_oscillator::stochastic_oscillator,
+        routes::ema::exponential_moving_average,
+        routes::multi_timeframe::multi_timeframe,
+        routes::batch::batch,
+        routes::screener::screener,
+        routes::evaluate::evaluate,
+        routes::signals::signals,
+        routes::divergences::divergences,
+        routes::backtest::backtest,
+        routes::optimize::optimize,
+        routes::live::live,
+        routes::events::events,
+        routes::alerts::create_alert,
+        routes::alerts::list_alerts,
+        routes::alerts::get_alert,
+        routes::alerts::replace_alert,
+        routes::alerts::delete_alert,
+        routes::alerts::alert_deliveries,
+    ),
+    // Path parameters aren't collected with the schemas of the operations.
+    components(schemas(ErrorBody, CacheKind)),
+    modifiers(&Unlicensed, &Errors, &ApiKeys),
+    tags(
+        (name = "indicators", description = "Single indicators of a coin."),
+        (name = "analysis", description = "Expressions, signals and screens over indicators."),
+        (name = "strategies", description = "Backtests and parameter searches of trading rules."),
+        (name = "live", description = "Indicator updates pushed as they change."),
+        (name = "alerts", description = "Rules calling a webhook when an indicator crosses a threshold."),
+        (name = "admin", description = "The upstream caches."),
+        (name = "operations", description = "Health, readiness and metrics."),
+    )
+)]
+pub struct ApiDoc;
+
+/// utoipa takes the license from Cargo, which sets an empty one for unlicensed packages.
+struct Unlicensed;
+
+impl Modify for Unlicensed {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if openapi
+            .info
+            .license
+            .as_ref()
+            .is_some_and(|license| license.name.is_empty())
+        {
+            openapi.info.license = None;
+        }
+    }
+}
+
+/// Every error has the same body, documented once as the `default` response of
+/// every operation rather than status by status.
+struct Errors;
+
+impl Modify for Errors {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let error = ResponseBuilder::new()
+            .description("An error, the `code` telling which.")
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("ErrorBody")))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            for operation in [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                operation
+                    .responses
+                    .responses
+                    .insert("default".to_owned(), error.clone().into());
+            }
+        }
+    }
+}
+
+/// The key required when authentication is enabled, on every path but the
+/// configured public ones.
+struct ApiKeys;
+
+impl Modify for ApiKeys {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+            );
+        openapi.security = Some(vec![SecurityRequirement::new(
+            "api_key",
+            Vec::<String>::new(),
+        )]);
+    }
+}
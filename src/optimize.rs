@@ -0,0 +1,554 @@
+//! Searching the parameters of a backtested strategy with walk-forward analysis.
+//!
+//! The entry and exit rules are templates whose `{name}` placeholders are replaced
+//! by the values of each candidate, e.g. `rsi({period}) < {oversold}`. The candles
+//! are cut into consecutive folds, each split into an in-sample part where the
+//! candidates are ranked and an out-of-sample part where they are checked.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use utoipa::ToSchema;
+
+use crate::backtest::{Costs, Metrics, Strategy};
+use crate::crypto_client::ParsedOhlc;
+use crate::error::ApiError;
+
+pub const MAX_CANDIDATES: usize = 1000;
+const MAX_FOLDS: usize = 10;
+
+/// The threads shared by every optimisation running at once, so that concurrent
+/// searches queue for them rather than each taking every core.
+pub struct Workers {
+    threads: Arc<Semaphore>,
+}
+
+impl Workers {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: Arc::new(Semaphore::new(threads.max(1))),
+        }
+    }
+
+    /// Waits for a free thread, then takes the others free too, up to `wanted`.
+    /// They are given back when the returned permit is dropped, its
+    /// `num_permits()` being the number of threads to run on.
+    pub async fn reserve(&self, wanted: usize) -> OwnedSemaphorePermit {
+        let mut reserved = self
+            .threads
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("The semaphore is never closed");
+        while reserved.num_permits() < wanted {
+            match self.threads.clone().try_acquire_owned() {
+                Ok(permit) => reserved.merge(permit),
+                Err(_) => break,
+            }
+        }
+        reserved
+    }
+}
+
+/// The values `min`, `min + step`, ... up to `max` of a placeholder.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Parameter {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+}
+
+impl Parameter {
+    fn values(&self) -> Result<Vec<f64>, ApiError> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(ApiError::bad_parameter(
+                "parameters",
+                format!("`{}` isn't a valid name", self.name),
+            ));
+        }
+        let finite = self.min.is_finite() && self.max.is_finite() && self.step.is_finite();
+        if !(finite && self.max >= self.min && self.step > 0.0) {
+            return Err(ApiError::bad_parameter(
+                "parameters",
+                format!("`{}` needs min <= max and a positive step", self.name),
+            ));
+        }
+        // Counted as a float first, as a tiny step would overflow a `usize`.
+        let count = ((self.max - self.min) / self.step + 1e-9).floor() + 1.0;
+        if count > MAX_CANDIDATES as f64 {
+            return Err(ApiError::bad_parameter(
+                "parameters",
+                format!("`{}` has more than {} values", self.name, MAX_CANDIDATES),
+            ));
+        }
+        let count = count as usize;
+        // Rounded so that steps like 0.1 don't print as 0.30000000000000004.
+        Ok((0..count)
+            .map(|i| ((self.min + i as f64 * self.step) * 1e9).round() / 1e9)
+            .collect())
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Search {
+    /// Every combination of values.
+    #[default]
+    Grid,
+    /// `samples` distinct combinations drawn at random.
+    Random,
+}
+
+/// What candidates are ranked by, higher being better.
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    #[default]
+    Sharpe,
+    Sortino,
+    TotalReturn,
+}
+
+impl Objective {
+    fn of(&self, metrics: &Metrics) -> Option<f64> {
+        match self {
+            Objective::Sharpe => metrics.sharpe,
+            Objective::Sortino => metrics.sortino,
+            Objective::TotalReturn => Some(metrics.total_return),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WalkForward {
+    pub folds: usize,
+    /// Fraction of each fold ranking the candidates, the rest checking them.
+    pub in_sample: f64,
+}
+
+impl Default for WalkForward {
+    fn default() -> Self {
+        Self {
+            folds: 3,
+            in_sample: 0.7,
+        }
+    }
+}
+
+impl WalkForward {
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if !(1..=MAX_FOLDS).contains(&self.folds) {
+            return Err(ApiError::bad_parameter(
+                "folds",
+                format!("must be between 1 and {}", MAX_FOLDS),
+            ));
+        }
+        if !(0.1..=0.9).contains(&self.in_sample) {
+            return Err(ApiError::bad_parameter(
+                "inSample",
+                "must be between 0.1 and 0.9",
+            ));
+        }
+        Ok(())
+    }
+
+    fn splits(&self, candles: usize, lookback: usize) -> Result<Vec<Split>, ApiError> {
+        let WalkForward { folds, in_sample } = *self;
+        let fold_length = candles / folds;
+        let in_sample_length = (fold_length as f64 * in_sample).round() as usize;
+        // Both parts need a fill after the rules are first defined.
+        let warmed_up = lookback.saturating_add(2);
+        if in_sample_length < warmed_up || fold_length - in_sample_length < 2 {
+            let required = (warmed_up as f64 / in_sample.min(1.0 - in_sample)).ceil() as usize;
+            return Err(ApiError::InsufficientData {
+                required: required * folds,
+                available: candles,
+            });
+        }
+        Ok((0..folds)
+            .map(|fold| {
+                let start = fold * fold_length;
+                let end = if fold + 1 == folds {
+                    candles
+                } else {
+                    start + fold_length
+                };
+                let split = start + in_sample_length;
+                let warm_up = lookback.min(split - start);
+                Split {
+                    in_sample: start..split,
+                    out_of_sample: split - warm_up..end,
+                    warm_up,
+                }
+            })
+            .collect())
+    }
+}
+
+pub type Candidate = BTreeMap<String, f64>;
+
+pub struct Optimization {
+    pub entry: String,
+    pub exit: String,
+    pub costs: Costs,
+    pub initial_capital: f64,
+    pub objective: Objective,
+    pub walk_forward: WalkForward,
+}
+
+impl Optimization {
+    /// The candidates to evaluate, every one of them giving valid rules.
+    pub fn candidates(
+        &self,
+        parameters: &[Parameter],
+        search: Search,
+        samples: usize,
+        seed: u64,
+    ) -> Result<Vec<Candidate>, ApiError> {
+        if parameters.is_empty() {
+            return Err(ApiError::bad_parameter("parameters", "give at least one"));
+        }
+        for parameter in parameters {
+            let placeholder = format!("{{{}}}", parameter.name);
+            if !self.entry.contains(&placeholder) && !self.exit.contains(&placeholder) {
+                return Err(ApiError::bad_parameter(
+                    "parameters",
+                    format!("`{}` is used by neither rule", parameter.name),
+                ));
+            }
+        }
+        let values = parameters
+            .iter()
+            .map(Parameter::values)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let total = values
+            .iter()
+            .try_fold(1usize, |total, values| total.checked_mul(values.len()));
+        let indices: Vec<usize> = match search {
+            Search::Grid => match total {
+                Some(total) if total <= MAX_CANDIDATES => (0..total).collect(),
+                _ => {
+                    return Err(ApiError::bad_parameter(
+                        "parameters",
+                        format!(
+                            "the grid has more than {} combinations, use a random search",
+                            MAX_CANDIDATES
+                        ),
+                    ))
+                }
+            },
+            Search::Random => {
+                if !(1..=MAX_CANDIDATES).contains(&samples) {
+                    return Err(ApiError::bad_parameter(
+                        "samples",
+                        format!("must be between 1 and {}", MAX_CANDIDATES),
+                    ));
+                }
+                let mut rng = StdRng::seed_from_u64(seed);
+                match total {
+                    Some(total) if total <= samples => (0..total).collect(),
+                    Some(total) if total <= 100 * MAX_CANDIDATES => {
+                        let mut all: Vec<usize> = (0..total).collect();
+                        all.shuffle(&mut rng);
+                        all.truncate(samples);
+                        all
+                    }
+                    // Too many combinations to shuffle: each value is drawn apart
+                    // and the repeated combinations are dropped.
+                    _ => {
+                        let mut candidates: Vec<Candidate> = vec![];
+                        for _ in 0..samples {
+                            let candidate = parameters
+                                .iter()
+                                .zip(&values)
+                                .map(|(parameter, values)| {
+                                    (parameter.name.clone(), *values.choose(&mut rng).unwrap())
+                                })
+                                .collect();
+                            if !candidates.contains(&candidate) {
+                                candidates.push(candidate);
+                            }
+                        }
+                        return self.check(candidates);
+                    }
+                }
+            }
+        };
+
+        let candidates = indices
+            .into_iter()
+            .map(|mut index| {
+                let mut candidate = Candidate::new();
+                for (parameter, values) in parameters.iter().zip(&values) {
+                    candidate.insert(parameter.name.clone(), values[index % values.len()]);
+                    index /= values.len();
+                }
+                candidate
+            })
+            .collect();
+        self.check(candidates)
+    }
+
+    /// Fails on the first candidate giving invalid rules, e.g. a period of 0.
+    fn check(&self, candidates: Vec<Candidate>) -> Result<Vec<Candidate>, ApiError> {
+        for candidate in &candidates {
+            self.strategy(candidate)?;
+        }
+        Ok(candidates)
+    }
+
+    pub fn strategy(&self, candidate: &Candidate) -> Result<Strategy, ApiError> {
+        let fill = |template: &str| {
+            candidate
+                .iter()
+                .fold(template.to_owned(), |rule, (name, value)| {
+                    rule.replace(&format!("{{{}}}", name), &value.to_string())
+                })
+        };
+        Strategy::new(&fill(&self.entry), &fill(&self.exit), self.costs)
+    }
+
+    /// The candles needed before the rules of every candidate are defined.
+    pub fn lookback(&self, candidates: &[Candidate]) -> Result<usize, ApiError> {
+        candidates.iter().try_fold(0, |lookback, candidate| {
+            Ok(lookback.max(self.strategy(candidate)?.lookback()))
+        })
+    }
+
+    /// Evaluates every candidate on every fold, on up to `threads` threads.
+    pub fn run(
+        &self,
+        candles: &[ParsedOhlc],
+        candidates: Vec<Candidate>,
+        threads: usize,
+    ) -> Result<Report, ApiError> {
+        self.walk_forward.validate()?;
+        let lookback = self.lookback(&candidates)?;
+        let splits = self.walk_forward.splits(candles.len(), lookback)?;
+
+        let chunk = candidates.len().div_ceil(threads.max(1)).max(1);
+        let evaluated: Vec<Evaluation> = std::thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(chunk)
+                .map(|chunk| {
+                    let splits = &splits;
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|candidate| self.evaluate(candles, candidate, splits))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .map_err(|_| ApiError::Internal("an optimisation thread panicked".into()))?
+                })
+                .collect::<Result<Vec<Vec<_>>, _>>()
+                .map(|chunks| chunks.into_iter().flatten().collect())
+        })?;
+
+        Ok(self.report(candles, &splits, evaluated))
+    }
+
+    fn evaluate(
+        &self,
+        candles: &[ParsedOhlc],
+        candidate: &Candidate,
+        splits: &[Split],
+    ) -> Result<Evaluation, ApiError> {
+        let strategy = self.strategy(candidate)?;
+        let folds = splits
+            .iter()
+            .map(|split| {
+                let in_sample =
+                    strategy.run(&candles[split.in_sample.clone()], self.initial_capital)?;
+                let out_of_sample =
+                    strategy.run(&candles[split.out_of_sample.clone()], self.initial_capital)?;
+                Ok((in_sample.metrics, out_of_sample.metrics))
+            })
+            .collect::<Result<Vec<_>, ApiError>>()?;
+        Ok(Evaluation {
+            candidate: candidate.clone(),
+            folds,
+        })
+    }
+
+    fn report(
+        &self,
+        candles: &[ParsedOhlc],
+        splits: &[Split],
+        evaluated: Vec<Evaluation>,
+    ) -> Report {
+        let objective = self.objective;
+        let score = |metrics: &Metrics| objective.of(metrics).unwrap_or(f64::NEG_INFINITY);
+
+        let folds = splits
+            .iter()
+            .enumerate()
+            .filter_map(|(fold, split)| {
+                let best = evaluated
+                    .iter()
+                    .max_by(|a, b| score(&a.folds[fold].0).total_cmp(&score(&b.folds[fold].0)))?;
+                let (in_sample, out_of_sample) = best.folds[fold].clone();
+                Some(FoldReport {
+                    in_sample: Range::new(candles, split.in_sample.clone()),
+                    out_of_sample: Range::new(
+                        candles,
+                        split.out_of_sample.start + split.warm_up..split.out_of_sample.end,
+                    ),
+                    parameters: best.candidate.clone(),
+                    in_sample_metrics: in_sample,
+                    out_of_sample_metrics: out_of_sample,
+                })
+            })
+            .collect();
+
+        let mut ranked: Vec<CandidateReport> = evaluated
+            .into_iter()
+            .map(|evaluation| {
+                let in_sample: Vec<f64> = evaluation
+                    .folds
+                    .iter()
+                    .filter_map(|(metrics, _)| objective.of(metrics))
+                    .collect();
+                let out_of_sample: Vec<f64> = evaluation
+                    .folds
+                    .iter()
+                    .filter_map(|(_, metrics)| objective.of(metrics))
+                    .collect();
+                let in_sample_mean = mean(&in_sample);
+                let out_of_sample_mean = mean(&out_of_sample);
+                CandidateReport {
+                    in_sample: in_sample_mean,
+                    out_of_sample: out_of_sample_mean,
+                    out_of_sample_std_dev: std_dev(&out_of_sample),
+                    efficiency: match (in_sample_mean, out_of_sample_mean) {
+                        (Some(is), Some(oos)) if is > 0.0 => Some(oos / is),
+                        _ => None,
+                    },
+                    profitable_folds: evaluation
+                        .folds
+                        .iter()
+                        .filter(|(_, metrics)| metrics.total_return > 0.0)
+                        .count(),
+                    parameters: evaluation.candidate,
+                }
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.in_sample
+                .unwrap_or(f64::NEG_INFINITY)
+                .total_cmp(&a.in_sample.unwrap_or(f64::NEG_INFINITY))
+        });
+
+        Report {
+            best: ranked,
+            folds,
+        }
+    }
+}
+
+/// Index ranges of the candles, the out-of-sample one starting early enough for
+/// the rules to be defined on its first candle.
+struct Split {
+    in_sample: std::ops::Range<usize>,
+    out_of_sample: std::ops::Range<usize>,
+    warm_up: usize,
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+fn std_dev(values: &[f64]) -> Option<f64> {
+    let mean = mean(values)?;
+    Some(
+        (values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / values.len() as f64)
+            .sqrt(),
+    )
+}
+
+struct Evaluation {
+    candidate: Candidate,
+    /// In-sample and out-of-sample metrics of each fold.
+    folds: Vec<(Metrics, Metrics)>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Range {
+    pub from: i64,
+    pub to: i64,
+    pub candles: usize,
+}
+
+impl Range {
+    fn new(candles: &[ParsedOhlc], range: std::ops::Range<usize>) -> Self {
+        Self {
+            from: candles[range.start].starting_at,
+            to: candles[range.end - 1].ending_at,
+            candles: range.len(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FoldReport {
+    pub in_sample: Range,
+    pub out_of_sample: Range,
+    /// The best candidate in sample.
+    #[schema(value_type = HashMap<String, f64>)]
+    pub parameters: Candidate,
+    pub in_sample_metrics: Metrics,
+    pub out_of_sample_metrics: Metrics,
+}
+
+/// The objective of a candidate averaged over the folds, `None` where it is never
+/// defined.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CandidateReport {
+    #[schema(value_type = HashMap<String, f64>)]
+    pub parameters: Candidate,
+    pub in_sample: Option<f64>,
+    pub out_of_sample: Option<f64>,
+    /// How much the out-of-sample objective varies from fold to fold.
+    pub out_of_sample_std_dev: Option<f64>,
+    /// Out-of-sample over in-sample objective, when the latter is positive.
+    pub efficiency: Option<f64>,
+    /// Folds where the candidate made money out of sample.
+    pub profitable_folds: usize,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = OptimizeReport)]
+pub struct Report {
+    /// Every candidate, best in sample first.
+    pub best: Vec<CandidateReport>,
+    pub folds: Vec<FoldReport>,
+}
@@ -0,0 +1,142 @@
+//! Indicators of a configured watchlist, computed in the background so that their
+//! dedicated routes answer without waiting for the upstream.
+//!
+//! Every value is refreshed once the candle it was computed on closes, fetching the
+//! candles of a coin and interval once for all of its indicators. A value that
+//! fails to refresh is kept, its `computedAt` telling how fresh it is.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::web::Data;
+
+use crate::batch::{run_jobs, Job};
+use crate::candles::CandleRequest;
+use crate::configuration::PrecomputeSettings;
+use crate::crypto_client::CryptoClient;
+use crate::indicators::Indicator;
+use crate::interval::Interval;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// A coin, uppercased, with an indicator on its default period and an interval.
+type Key = (String, Indicator, Interval);
+
+#[derive(Clone, Debug)]
+pub struct Precomputed {
+    pub value: f64,
+    pub meta: OhlcMeta,
+}
+
+pub struct Precomputer {
+    values: Mutex<HashMap<Key, Precomputed>>,
+    settings: PrecomputeSettings,
+}
+
+impl Precomputer {
+    pub fn new(settings: PrecomputeSettings) -> Self {
+        Self {
+            values: Mutex::new(HashMap::new()),
+            settings,
+        }
+    }
+
+    /// False once a thread panicked while holding the values.
+    pub fn is_healthy(&self) -> bool {
+        !self.values.is_poisoned()
+    }
+
+    /// The precomputed value answering a request to the dedicated route of
+    /// `indicator`, if any.
+    pub fn lookup(
+        &self,
+        coin: &str,
+        indicator: Indicator,
+        query: &OhlcQuery,
+    ) -> Option<Precomputed> {
+        let key = (
+            coin.trim().to_uppercase(),
+            indicator,
+            query.precomputed_interval()?,
+        );
+        self.values.lock().unwrap().get(&key).cloned()
+    }
+
+    /// Refreshes every value of the watchlist as candles close, until the process exits.
+    pub async fn run_scheduler(precomputer: Data<Precomputer>, crypto_client: Data<CryptoClient>) {
+        let mut keys: Vec<Key> = vec![];
+        for watched in &precomputer.settings.watchlist {
+            for indicator in &watched.indicators {
+                let key = (
+                    watched.coin.trim().to_uppercase(),
+                    *indicator,
+                    watched.interval,
+                );
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        if keys.is_empty() {
+            return;
+        }
+        // Milliseconds timestamps after which each value is refreshed.
+        let mut refresh_at: Vec<i64> = vec![0; keys.len()];
+        loop {
+            let now = chrono::Utc::now().timestamp_millis();
+            let due: Vec<usize> = (0..keys.len()).filter(|i| refresh_at[*i] <= now).collect();
+            let jobs: Vec<Job> = due.iter().map(|i| job(&keys[*i])).collect();
+            let outputs = run_jobs(&crypto_client, &jobs, MAX_CONCURRENT_FETCHES).await;
+
+            let computed_at = chrono::Utc::now();
+            let retry_at = computed_at.timestamp_millis()
+                + precomputer.settings.retry_delay_milliseconds as i64;
+            {
+                let mut values = precomputer.values.lock().unwrap();
+                for (i, output) in due.into_iter().zip(outputs) {
+                    let Ok(output) = output else {
+                        refresh_at[i] = retry_at;
+                        continue;
+                    };
+                    let closes_at = output.candles.ohlc[0].ending_at * 1_000
+                        + precomputer.settings.close_delay_milliseconds as i64;
+                    // The upstream may not have the new candle yet.
+                    refresh_at[i] = if closes_at > computed_at.timestamp_millis() {
+                        closes_at
+                    } else {
+                        retry_at
+                    };
+                    values.insert(
+                        keys[i].clone(),
+                        Precomputed {
+                            value: output.value,
+                            meta: OhlcMeta::new(&output.candles)
+                                .computed_at(computed_at.timestamp()),
+                        },
+                    );
+                }
+            }
+
+            let next = refresh_at.iter().min().copied().unwrap_or(retry_at);
+            let wait = (next - chrono::Utc::now().timestamp_millis()).max(0) as u64;
+            tokio::time::sleep(Duration::from_millis(wait)).await;
+        }
+    }
+}
+
+/// The job computing a value as its dedicated route does, bypassing the cache.
+fn job((coin, indicator, interval): &Key) -> Job {
+    Job {
+        coin: coin.clone(),
+        indicator: *indicator,
+        period: indicator.default_period(),
+        candles: CandleRequest {
+            interval: *interval,
+            limit: indicator.default_limit(indicator.default_period()),
+            latest: true,
+            ..CandleRequest::default()
+        },
+    }
+}
@@ -0,0 +1,226 @@
+//! Whether the service can actually answer requests, as opposed to `/health_check`
+//! only telling that the process is up.
+//!
+//! `/readiness` checks the configuration, that the upstream answers and accepts our
+//! token, and that the caches and in-memory stores are usable. The upstream probe is
+//! a single cheap call whose result is reused for a while, so that orchestrators
+//! polling every few seconds don't eat the upstream quota.
+
+use std::time::{Duration, Instant};
+
+use actix_web::web::Data;
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use tokio::sync::Mutex;
+
+use crate::alerts::Alerts;
+use crate::circuit_breaker::CircuitState;
+use crate::configuration::Settings;
+use crate::crypto_client::CryptoClient;
+use crate::live::LiveHub;
+use crate::precompute::Precomputer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessStatus {
+    Ready,
+    NotReady,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+#[schema(as = ReadinessReport)]
+pub struct Report {
+    pub status: ReadinessStatus,
+    pub checks: Checks,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Checks {
+    pub configuration: Check,
+    pub upstream: UpstreamCheck,
+    pub cache: Check,
+    pub stores: Check,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Check {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+impl Check {
+    fn from_problems(problems: Vec<String>) -> Self {
+        let status = if problems.is_empty() {
+            CheckStatus::Up
+        } else {
+            CheckStatus::Down
+        };
+        Self { status, problems }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamCheck {
+    pub status: CheckStatus,
+    /// When the probe was made, in milliseconds since the epoch.
+    pub checked_at: i64,
+    pub latency_ms: u64,
+    /// Whether the result is the one of an earlier probe.
+    pub cached: bool,
+    pub circuit: CircuitState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone)]
+struct Probe {
+    at: Instant,
+    checked_at: i64,
+    latency_ms: u64,
+    error: Option<String>,
+}
+
+pub struct Readiness {
+    configuration_problems: Vec<String>,
+    probe_ttl: Duration,
+    /// Held while probing, so that concurrent checks share a single probe.
+    last_probe: Mutex<Option<Probe>>,
+}
+
+impl Readiness {
+    /// Validates the configuration once, it doesn't change while running.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            configuration_problems: configuration_problems(settings),
+            probe_ttl: settings.readiness.probe_ttl(),
+            last_probe: Mutex::new(None),
+        }
+    }
+
+    pub async fn check(
+        &self,
+        crypto_client: &CryptoClient,
+        alerts: &Data<Alerts>,
+        precomputer: &Data<Precomputer>,
+        live_hub: &Data<LiveHub>,
+    ) -> Report {
+        let configuration = Check::from_problems(self.configuration_problems.clone());
+        let upstream = self.upstream(crypto_client).await;
+
+        let cache = if crypto_client.caches_healthy() {
+            Check::from_problems(vec![])
+        } else {
+            Check::from_problems(vec!["an upstream cache is unusable".to_owned()])
+        };
+        let stores = Check::from_problems(
+            [
+                ("alerts", alerts.is_healthy()),
+                ("precompute", precomputer.is_healthy()),
+                ("live", live_hub.is_healthy()),
+            ]
+            .into_iter()
+            .filter(|(_, healthy)| !healthy)
+            .map(|(store, _)| format!("the {} store is unusable", store))
+            .collect(),
+        );
+
+        let ready = [
+            configuration.status,
+            upstream.status,
+            cache.status,
+            stores.status,
+        ]
+        .iter()
+        .all(|status| *status == CheckStatus::Up);
+        Report {
+            status: if ready {
+                ReadinessStatus::Ready
+            } else {
+                ReadinessStatus::NotReady
+            },
+            checks: Checks {
+                configuration,
+                upstream,
+                cache,
+                stores,
+            },
+        }
+    }
+
+    async fn upstream(&self, crypto_client: &CryptoClient) -> UpstreamCheck {
+        let mut last_probe = self.last_probe.lock().await;
+        let (probe, cached) = match &*last_probe {
+            Some(probe) if probe.at.elapsed() < self.probe_ttl => (probe.clone(), true),
+            _ => {
+                let started = Instant::now();
+                let result = crypto_client.probe().await;
+                let probe = Probe {
+                    at: started,
+                    checked_at: chrono::Utc::now().timestamp_millis(),
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    error: result.err().map(|err| err.to_string()),
+                };
+                *last_probe = Some(probe.clone());
+                (probe, false)
+            }
+        };
+        UpstreamCheck {
+            status: if probe.error.is_none() {
+                CheckStatus::Up
+            } else {
+                CheckStatus::Down
+            },
+            checked_at: probe.checked_at,
+            latency_ms: probe.latency_ms,
+            cached,
+            circuit: crypto_client.circuit_state(),
+            error: probe.error,
+        }
+    }
+}
+
+/// What would keep the service from working, whatever the upstream's state.
+pub fn configuration_problems(settings: &Settings) -> Vec<String> {
+    let mut problems = vec![];
+    let crypto_client = &settings.crypto_client;
+    match Url::parse(&crypto_client.base_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        _ => problems.push(format!(
+            "crypto_client.base_url `{}` is not an HTTP URL",
+            crypto_client.base_url
+        )),
+    }
+    if crypto_client.auth_token.expose_secret().trim().is_empty() {
+        problems.push("crypto_client.auth_token is empty".to_owned());
+    }
+    if crypto_client.timeout_milliseconds == 0 {
+        problems.push("crypto_client.timeout_milliseconds is 0".to_owned());
+    }
+    if settings.live.poll_interval_milliseconds == 0 {
+        problems.push("live.poll_interval_milliseconds is 0".to_owned());
+    }
+    if settings.alerts.evaluation_interval_milliseconds == 0 {
+        problems.push("alerts.evaluation_interval_milliseconds is 0".to_owned());
+    }
+    if settings
+        .precompute
+        .watchlist
+        .iter()
+        .any(|watched| watched.coin.trim().is_empty())
+    {
+        problems.push("precompute.watchlist holds an empty coin".to_owned());
+    }
+    if settings.auth.enabled && settings.auth.keys.is_empty() && settings.auth.keys_file.is_none() {
+        problems.push("auth is enabled without any key".to_owned());
+    }
+    problems
+}
@@ -0,0 +1,166 @@
+//! Aggregation of candles into coarser intervals and handling of missing candles.
+//!
+//! Like everything returned by `CryptoClient`, candles go in and come out newest first.
+
+use crate::crypto_client::ParsedOhlc;
+use crate::interval::Interval;
+use utoipa::ToSchema;
+
+/// What to do with the candles missing from the upstream data.
+#[derive(
+    serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    /// Leave the gaps as they are, as if the series were contiguous.
+    #[default]
+    Skip,
+    /// Insert flat candles at the previous close, flagged as `filled`.
+    ForwardFill,
+    /// Leave the gaps as they are but report them.
+    Mark,
+}
+
+/// A run of missing candles, `from` being the start of the first missing one
+/// and `to` the end of the last one.
+#[derive(serde::Serialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Gap {
+    pub from: i64,
+    pub to: i64,
+    pub missing: i64,
+}
+
+/// Finds the missing candles of an `interval` series. Candles further apart than
+/// one and a half intervals are considered to have a gap between them, which
+/// tolerates the varying length of months.
+pub fn find_gaps(candles: &[ParsedOhlc], interval: Interval) -> Vec<Gap> {
+    let step = interval.seconds();
+    let mut chronological: Vec<&ParsedOhlc> = candles.iter().collect();
+    chronological.sort_by_key(|candle| candle.starting_at);
+
+    chronological
+        .windows(2)
+        .filter_map(|pair| {
+            let distance = pair[1].starting_at - pair[0].starting_at;
+            if distance * 2 <= step * 3 {
+                return None;
+            }
+            let missing = (distance as f64 / step as f64).round() as i64 - 1;
+            Some(Gap {
+                from: pair[0].starting_at + step,
+                to: pair[0].starting_at + step * (missing + 1),
+                missing,
+            })
+        })
+        .collect()
+}
+
+/// Applies `policy` to the gaps of an `interval` series, returning the candles
+/// together with the gaps to report.
+pub fn fill_gaps(
+    candles: Vec<ParsedOhlc>,
+    interval: Interval,
+    policy: GapPolicy,
+) -> (Vec<ParsedOhlc>, Vec<Gap>) {
+    let gaps = find_gaps(&candles, interval);
+    match policy {
+        GapPolicy::Skip => (candles, vec![]),
+        GapPolicy::Mark => (candles, gaps),
+        GapPolicy::ForwardFill => (forward_fill(candles, interval), gaps),
+    }
+}
+
+fn forward_fill(mut candles: Vec<ParsedOhlc>, interval: Interval) -> Vec<ParsedOhlc> {
+    let step = interval.seconds();
+    candles.sort_by_key(|candle| candle.starting_at);
+
+    let mut filled: Vec<ParsedOhlc> = Vec::with_capacity(candles.len());
+    for candle in candles {
+        if let Some(previous) = filled.last().cloned() {
+            let distance = candle.starting_at - previous.starting_at;
+            if distance * 2 > step * 3 {
+                let missing = (distance as f64 / step as f64).round() as i64 - 1;
+                for i in 1..=missing {
+                    let starting_at = previous.starting_at + step * i;
+                    filled.push(ParsedOhlc {
+                        starting_at,
+                        ending_at: starting_at + step,
+                        open: previous.close,
+                        high: previous.close,
+                        low: previous.close,
+                        close: previous.close,
+                        avg: previous.close,
+                        volume: previous.volume.map(|_| 0.0),
+                        filled: true,
+                    });
+                }
+            }
+        }
+        filled.push(candle);
+    }
+
+    filled.reverse();
+    filled
+}
+
+/// Aggregates candles into `target` candles aligned on multiples of its length
+/// since the unix epoch: first open, last close, highest high, lowest low, mean
+/// average and summed volume. The oldest bucket is dropped when the source data
+/// starts in the middle of it, since it would only cover part of its period.
+pub fn resample(candles: &[ParsedOhlc], target: Interval) -> Vec<ParsedOhlc> {
+    let step = target.seconds();
+    let mut chronological: Vec<&ParsedOhlc> = candles.iter().collect();
+    chronological.sort_by_key(|candle| candle.starting_at);
+
+    let mut buckets: Vec<Vec<&ParsedOhlc>> = vec![];
+    for candle in chronological {
+        let bucket_start = candle.starting_at.div_euclid(step) * step;
+        match buckets.last_mut() {
+            Some(bucket) if bucket[0].starting_at.div_euclid(step) * step == bucket_start => {
+                bucket.push(candle)
+            }
+            _ => buckets.push(vec![candle]),
+        }
+    }
+
+    if let Some(oldest) = buckets.first() {
+        if oldest[0].starting_at.rem_euclid(step) != 0 {
+            buckets.remove(0);
+        }
+    }
+
+    let mut resampled: Vec<ParsedOhlc> = buckets
+        .iter()
+        .map(|bucket| aggregate(bucket, step))
+        .collect();
+    resampled.reverse();
+    resampled
+}
+
+fn aggregate(bucket: &[&ParsedOhlc], step: i64) -> ParsedOhlc {
+    let starting_at = bucket[0].starting_at.div_euclid(step) * step;
+    let volumes: Vec<f64> = bucket.iter().filter_map(|candle| candle.volume).collect();
+
+    ParsedOhlc {
+        starting_at,
+        ending_at: starting_at + step,
+        open: bucket[0].open,
+        high: bucket
+            .iter()
+            .map(|candle| candle.high)
+            .fold(f64::MIN, f64::max),
+        low: bucket
+            .iter()
+            .map(|candle| candle.low)
+            .fold(f64::MAX, f64::min),
+        close: bucket[bucket.len() - 1].close,
+        avg: bucket.iter().map(|candle| candle.avg).sum::<f64>() / bucket.len() as f64,
+        volume: if volumes.is_empty() {
+            None
+        } else {
+            Some(volumes.iter().sum())
+        },
+        filled: bucket.iter().all(|candle| candle.filled),
+    }
+}
@@ -0,0 +1,150 @@
+use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::alerts::{Alerts, Delivery, Rule, RuleRequest};
+use crate::auth::Caller;
+use crate::error::ApiError;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    /// The ID of the rule.
+    id: u64,
+}
+
+/// Creates a rule, answering with its secret, which isn't returned afterwards.
+#[utoipa::path(
+    post,
+    path = "/alerts",
+    tag = "alerts",
+    request_body = RuleRequest,
+    responses((status = 201, body = Success<Created>))
+)]
+pub async fn create_alert(
+    body: web::Json<RuleRequest>,
+    alerts: web::Data<Alerts>,
+    caller: Option<web::ReqData<Caller>>,
+) -> Result<HttpResponse, ApiError> {
+    let rule = alerts
+        .create(body.into_inner(), owner(&caller).map(str::to_owned))
+        .await?;
+    Ok(HttpResponse::Created().json(Success {
+        status: "success".to_owned(),
+        data: Created {
+            secret: rule.secret.clone(),
+            rule,
+        },
+    }))
+}
+
+/// Every rule, with the state of its last evaluation.
+#[utoipa::path(
+    get,
+    path = "/alerts",
+    tag = "alerts",
+    responses((status = 200, body = Success<Vec<Rule>>))
+)]
+pub async fn list_alerts(
+    alerts: web::Data<Alerts>,
+    caller: Option<web::ReqData<Caller>>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: alerts.list(owner(&caller)),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/alerts/{id}",
+    tag = "alerts",
+    params(PathData),
+    responses((status = 200, body = Success<Rule>))
+)]
+pub async fn get_alert(
+    path: web::Path<PathData>,
+    alerts: web::Data<Alerts>,
+    caller: Option<web::ReqData<Caller>>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: alerts.get(path.id, owner(&caller))?,
+    }))
+}
+
+/// Replaces a rule, keeping its secret unless a new one is given.
+#[utoipa::path(
+    put,
+    path = "/alerts/{id}",
+    tag = "alerts",
+    params(PathData),
+    request_body = RuleRequest,
+    responses((status = 200, body = Success<Rule>))
+)]
+pub async fn replace_alert(
+    path: web::Path<PathData>,
+    body: web::Json<RuleRequest>,
+    alerts: web::Data<Alerts>,
+    caller: Option<web::ReqData<Caller>>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: alerts
+            .replace(path.id, body.into_inner(), owner(&caller))
+            .await?,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/alerts/{id}",
+    tag = "alerts",
+    params(PathData),
+    responses((status = 204))
+)]
+pub async fn delete_alert(
+    path: web::Path<PathData>,
+    alerts: web::Data<Alerts>,
+    caller: Option<web::ReqData<Caller>>,
+) -> Result<HttpResponse, ApiError> {
+    alerts.delete(path.id, owner(&caller))?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The webhook deliveries of a rule, newest first, with every attempt.
+#[utoipa::path(
+    get,
+    path = "/alerts/{id}/deliveries",
+    tag = "alerts",
+    params(PathData),
+    responses((status = 200, body = Success<Vec<Delivery>>))
+)]
+pub async fn alert_deliveries(
+    path: web::Path<PathData>,
+    alerts: web::Data<Alerts>,
+    caller: Option<web::ReqData<Caller>>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(Success::<Vec<Delivery>> {
+        status: "success".to_owned(),
+        data: alerts.deliveries(path.id, owner(&caller))?,
+    }))
+}
+
+/// The `id` of the key of the request, which rules are scoped to.
+fn owner(caller: &Option<web::ReqData<Caller>>) -> Option<&str> {
+    caller.as_ref().map(|caller| caller.key_id.as_str())
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct Success<T> {
+    status: String,
+    data: T,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = CreatedRule)]
+struct Created {
+    #[serde(flatten)]
+    rule: Rule,
+    secret: String,
+}
@@ -1,67 +1,59 @@
 use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::crypto_client::{CoinUuidErr::*, CryptoClient};
+use crate::candles::fetch_candles;
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+use crate::precompute::Precomputer;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct PathData {
+    /// A symbol, e.g. `btc`.
     coin: String,
 }
 
+/// The Aroon oscillator over 25 candles.
+#[utoipa::path(
+    get,
+    path = "/aroon_oscillator/{coin}",
+    tag = "indicators",
+    params(PathData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
 pub async fn aroon_oscillator(
     path: web::Path<PathData>,
+    query: web::Query<OhlcQuery>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let response = match crypto_client.get_coin_ohlc(&uuid).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let mut i: i8 = 0;
-    let mut highest_high: f64 = 0.0;
-    let mut hh_counter: i8 = 0;
-    let mut lowest_low: f64 = f64::INFINITY;
-    let mut ll_counter: i8 = 0;
-    for ohlc in response.ohlc.iter() {
-        let high = ohlc.high;
-        let low = ohlc.low;
-        if highest_high < high {
-            hh_counter = i;
-            highest_high = high;
-        }
-
-        if lowest_low > low {
-            ll_counter = i;
-            lowest_low = low;
-        }
-
-        i += 1;
-        if i == 25 {
-            break;
-        }
+    precomputer: web::Data<Precomputer>,
+) -> Result<HttpResponse, ApiError> {
+    let params = query.params()?;
+    if let Some(precomputed) = precomputer.lookup(&path.coin, Indicator::AroonOscillator, &query) {
+        return Ok(HttpResponse::Ok().json(Success {
+            status: "success".to_owned(),
+            data: precomputed.value,
+            meta: precomputed.meta,
+        }));
     }
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
 
-    let ap_second_part: f32 = (25. - hh_counter as f32) / 25.;
-    let aroon_up: f32 = 100.0 * ap_second_part;
-
-    let ad_second_part: f32 = (25. - ll_counter as f32) / 25.;
-    let aroon_down: f32 = 100.0 * ad_second_part;
-
-    let aroon_oscillator: f32 = aroon_up - aroon_down;
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    let aroon_oscillator = Indicator::AroonOscillator
+        .compute(&response.ohlc, Indicator::AroonOscillator.default_period())?;
 
-    HttpResponse::Ok().json(Success {
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
         data: aroon_oscillator,
-    })
+        meta: OhlcMeta::new(&response),
+    }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = AroonOscillatorResponse)]
 struct Success {
     status: String,
-    data: f32,
+    data: f64,
+    meta: OhlcMeta,
 }
@@ -0,0 +1,165 @@
+use actix_web::{web, HttpResponse};
+use utoipa::ToSchema;
+
+use crate::backtest::{Costs, Report, Strategy};
+use crate::candles::fetch_candles;
+use crate::crypto_client::{CryptoClient, OhlcParams, ParsedOhlc};
+use crate::error::{require_data, ApiError};
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+
+/// Candles replayed by default, on top of those the rules need to warm up.
+const DEFAULT_BARS: u32 = 500;
+const DEFAULT_CAPITAL: f64 = 10_000.0;
+
+/// Where the replayed candles come from, also used by `/optimize`.
+#[derive(serde::Deserialize, ToSchema)]
+pub struct CandleSource {
+    coin: Option<String>,
+    /// Candles to replay instead of fetching those of `coin`, in any order.
+    candles: Option<Vec<ParsedOhlc>>,
+    /// The candles fetched for `coin`, as the query of the indicator routes.
+    #[serde(default)]
+    params: OhlcQuery,
+}
+
+impl CandleSource {
+    /// The candles oldest first, with the interval and range they were fetched for.
+    pub async fn load(
+        self,
+        crypto_client: &CryptoClient,
+        default_limit: u32,
+    ) -> Result<(Vec<ParsedOhlc>, Option<OhlcMeta>), ApiError> {
+        match (self.coin, self.candles) {
+            (Some(coin), None) => {
+                let params = self
+                    .params
+                    .params_with_default_limit(default_limit.min(OhlcParams::MAX_LIMIT))?;
+                let uuid = crypto_client.get_coin_uuid(&coin).await?;
+                let response = fetch_candles(crypto_client, &uuid, &params).await?;
+                let chronological = response.ohlc.iter().rev().cloned().collect();
+                Ok((chronological, Some(OhlcMeta::new(&response))))
+            }
+            (None, Some(mut candles)) if candles.len() <= OhlcParams::MAX_LIMIT as usize => {
+                check_prices(&candles)?;
+                candles.sort_by_key(|ohlc| ohlc.starting_at);
+                Ok((candles, None))
+            }
+            _ => Err(ApiError::bad_parameter(
+                "coin",
+                format!(
+                    "give either `coin` or up to {} `candles`",
+                    OhlcParams::MAX_LIMIT
+                ),
+            )),
+        }
+    }
+}
+
+/// Positions are sized by dividing by the prices, which must be positive for the
+/// metrics to be defined.
+fn check_prices(candles: &[ParsedOhlc]) -> Result<(), ApiError> {
+    for (i, ohlc) in candles.iter().enumerate() {
+        let prices = [ohlc.open, ohlc.high, ohlc.low, ohlc.close];
+        if !prices.iter().all(|price| *price > 0.0 && price.is_finite()) {
+            return Err(ApiError::bad_parameter(
+                "candles",
+                format!("candle {} has a price that isn't positive", i),
+            ));
+        }
+        if ohlc.high < ohlc.low {
+            return Err(ApiError::bad_parameter(
+                "candles",
+                format!("candle {} has a high below its low", i),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn initial_capital(initial_capital: Option<f64>) -> Result<f64, ApiError> {
+    let initial_capital = initial_capital.unwrap_or(DEFAULT_CAPITAL);
+    if !(initial_capital > 0.0 && initial_capital.is_finite()) {
+        return Err(ApiError::bad_parameter(
+            "initialCapital",
+            "must be positive",
+        ));
+    }
+    Ok(initial_capital)
+}
+
+#[derive(serde::Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestRequest {
+    #[serde(flatten)]
+    source: CandleSource,
+    /// Conditions of the expression language, e.g. `rsi(14) < 30`.
+    entry: String,
+    exit: String,
+    #[serde(flatten)]
+    costs: Costs,
+    initial_capital: Option<f64>,
+}
+
+/// Replays entry and exit rules over candles, reporting the trades, equity and metrics.
+#[utoipa::path(
+    post,
+    path = "/backtest",
+    tag = "strategies",
+    request_body = BacktestRequest,
+    responses((status = 200, body = Success))
+)]
+pub async fn backtest(
+    body: web::Json<BacktestRequest>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let strategy = Strategy::new(&body.entry, &body.exit, body.costs)?;
+    let initial_capital = initial_capital(body.initial_capital)?;
+    let (candles, ohlc_meta) = body
+        .source
+        .load(
+            &crypto_client,
+            (strategy.lookback() as u32).saturating_add(DEFAULT_BARS),
+        )
+        .await?;
+    // The rules must hold on a candle before the one they are filled on.
+    require_data(strategy.lookback().saturating_add(2), candles.len())?;
+
+    let bars = candles.len();
+    let report = web::block(move || strategy.run(&candles, initial_capital))
+        .await
+        .map_err(|err| ApiError::Internal(err.to_string()))??;
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: report,
+        meta: Meta {
+            entry: body.entry,
+            exit: body.exit,
+            costs: body.costs,
+            bars,
+            candles: ohlc_meta,
+        },
+    }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = BacktestResponse)]
+struct Success {
+    status: String,
+    data: Report,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = BacktestMeta)]
+struct Meta {
+    entry: String,
+    exit: String,
+    #[serde(flatten)]
+    costs: Costs,
+    /// Candles replayed.
+    bars: usize,
+    /// Where the candles were fetched, absent for given candles.
+    #[serde(flatten)]
+    candles: Option<OhlcMeta>,
+}
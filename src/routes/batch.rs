@@ -0,0 +1,162 @@
+use actix_web::{web, HttpResponse};
+use utoipa::ToSchema;
+
+use crate::batch::{run_jobs, Job};
+use crate::crypto_client::CryptoClient;
+use crate::error::{ApiError, ErrorBody};
+use crate::indicators::Indicator;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+
+const MAX_JOBS: usize = 100;
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+#[derive(serde::Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Kept as raw JSON so that a malformed job only fails itself.
+    #[schema(value_type = Vec<JobData>)]
+    jobs: Vec<serde_json::Value>,
+}
+
+#[derive(serde::Deserialize, ToSchema)]
+struct JobData {
+    coin: String,
+    indicator: Indicator,
+    #[serde(default)]
+    params: JobParams,
+}
+
+/// The query parameters of the indicator routes, plus the indicator period.
+#[derive(serde::Deserialize, Default, ToSchema)]
+struct JobParams {
+    #[serde(flatten)]
+    candles: OhlcQuery,
+    period: Option<usize>,
+}
+
+impl JobData {
+    fn parse(job: &serde_json::Value) -> Result<Self, ApiError> {
+        serde_json::from_value(job.clone())
+            .map_err(|err| ApiError::bad_parameter("job", err.to_string()))
+    }
+
+    fn job(&self) -> Result<Job, ApiError> {
+        let period = self.indicator.period(self.params.period)?;
+        let candles = self
+            .params
+            .candles
+            .params_with_default_limit(self.indicator.default_limit(period))?;
+        Ok(Job {
+            coin: self.coin.clone(),
+            indicator: self.indicator,
+            period,
+            candles,
+        })
+    }
+}
+
+/// Computes up to 100 indicators at once, each job succeeding or failing on its own.
+#[utoipa::path(
+    post,
+    path = "/batch",
+    tag = "indicators",
+    request_body = BatchRequest,
+    responses((status = 200, body = Success))
+)]
+pub async fn batch(
+    body: web::Json<BatchRequest>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    if body.jobs.is_empty() || body.jobs.len() > MAX_JOBS {
+        return Err(ApiError::bad_parameter(
+            "jobs",
+            format!("must hold between 1 and {} jobs", MAX_JOBS),
+        ));
+    }
+
+    let parsed: Vec<Result<(JobData, Job), ApiError>> = body
+        .jobs
+        .iter()
+        .map(|job| {
+            let data = JobData::parse(job)?;
+            let job = data.job()?;
+            Ok((data, job))
+        })
+        .collect();
+    let jobs: Vec<Job> = parsed
+        .iter()
+        .filter_map(|job| job.as_ref().ok())
+        .map(|(_, job)| job.clone())
+        .collect();
+    let mut outputs = run_jobs(&crypto_client, &jobs, MAX_CONCURRENT_FETCHES)
+        .await
+        .into_iter();
+
+    let data: Vec<JobOutcome> = parsed
+        .into_iter()
+        .map(|job| {
+            let output = job.and_then(|(data, job)| {
+                let output = outputs.next().expect("One output per valid job")?;
+                Ok(JobResult {
+                    status: "success".to_owned(),
+                    coin: data.coin,
+                    indicator: job.indicator,
+                    period: job.period,
+                    value: output.value,
+                    meta: OhlcMeta::new(&output.candles),
+                })
+            });
+            match output {
+                Ok(result) => JobOutcome::Result(result),
+                Err(err) => JobOutcome::Error(err.error_body()),
+            }
+        })
+        .collect();
+    let failed = data
+        .iter()
+        .filter(|outcome| matches!(outcome, JobOutcome::Error(_)))
+        .count();
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        meta: Meta {
+            jobs: data.len(),
+            succeeded: data.len() - failed,
+            failed,
+        },
+        data,
+    }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = BatchResponse)]
+struct Success {
+    status: String,
+    /// One entry per job, in order: either a result or an error body.
+    data: Vec<JobOutcome>,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(untagged)]
+enum JobOutcome {
+    Result(JobResult),
+    Error(ErrorBody),
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct JobResult {
+    status: String,
+    coin: String,
+    indicator: Indicator,
+    period: usize,
+    value: f64,
+    meta: OhlcMeta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = BatchMeta)]
+struct Meta {
+    jobs: usize,
+    succeeded: usize,
+    failed: usize,
+}
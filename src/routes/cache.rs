@@ -0,0 +1,70 @@
+use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::crypto_client::{CacheKind, CryptoClient, CryptoClientCacheStats};
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    kind: CacheKind,
+}
+
+/// Entries, hits and misses of every upstream cache.
+#[utoipa::path(
+    get,
+    path = "/admin/cache",
+    tag = "admin",
+    responses((status = 200, body = Success))
+)]
+pub async fn cache_stats(crypto_client: web::Data<CryptoClient>) -> HttpResponse {
+    HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: crypto_client.cache_stats(),
+    })
+}
+
+/// Empties every upstream cache.
+#[utoipa::path(
+    delete,
+    path = "/admin/cache",
+    tag = "admin",
+    responses((status = 200, body = Purged))
+)]
+pub async fn purge_cache(crypto_client: web::Data<CryptoClient>) -> HttpResponse {
+    HttpResponse::Ok().json(Purged {
+        status: "success".to_owned(),
+        purged: crypto_client.purge_cache(None),
+    })
+}
+
+/// Empties one upstream cache.
+#[utoipa::path(
+    delete,
+    path = "/admin/cache/{kind}",
+    tag = "admin",
+    params(PathData),
+    responses((status = 200, body = Purged))
+)]
+pub async fn purge_cache_kind(
+    path: web::Path<PathData>,
+    crypto_client: web::Data<CryptoClient>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(Purged {
+        status: "success".to_owned(),
+        purged: crypto_client.purge_cache(Some(path.kind)),
+    })
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = CacheStatsResponse)]
+struct Success {
+    status: String,
+    data: CryptoClientCacheStats,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = PurgedResponse)]
+struct Purged {
+    status: String,
+    purged: usize,
+}
@@ -0,0 +1,115 @@
+use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::candles::fetch_candles;
+use crate::crypto_client::{CryptoClient, OhlcParams, ParsedOhlc};
+use crate::divergence::{detect, Divergence, Oscillator, SwingParams};
+use crate::error::ApiError;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+
+/// Candles searched for swings by default, on top of the oscillator warm-up.
+const DEFAULT_WINDOW: u32 = 100;
+const MAX_SWING: usize = 50;
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    /// A symbol, e.g. `btc`.
+    coin: String,
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryData {
+    /// `rsi` (the default), `macd` or `stochastic`.
+    oscillator: Option<String>,
+    period: Option<usize>,
+    swing: Option<usize>,
+    max_distance: Option<usize>,
+}
+
+/// Price swings the oscillator disagrees with, regular and hidden ones.
+#[utoipa::path(
+    get,
+    path = "/divergences/{coin}",
+    tag = "analysis",
+    params(PathData, QueryData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
+pub async fn divergences(
+    path: web::Path<PathData>,
+    query: web::Query<QueryData>,
+    ohlc_query: web::Query<OhlcQuery>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let oscillator = match &query.oscillator {
+        Some(oscillator) => oscillator
+            .parse::<Oscillator>()
+            .map_err(|message| ApiError::bad_parameter("oscillator", message))?,
+        None => Oscillator::Rsi,
+    };
+    let period = oscillator.period(query.period)?;
+    let swing_params = swing_params(&query)?;
+    let params = ohlc_query
+        .params_with_default_limit((period as u32 + DEFAULT_WINDOW).min(OhlcParams::MAX_LIMIT))?;
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
+
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    let chronological: Vec<ParsedOhlc> = response.ohlc.iter().rev().cloned().collect();
+    let values = oscillator.series(&chronological, period);
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: detect(&chronological, &values, &swing_params),
+        meta: Meta {
+            oscillator,
+            period,
+            swing: swing_params.swing,
+            max_distance: swing_params.max_distance,
+            candles: OhlcMeta::new(&response),
+        },
+    }))
+}
+
+fn swing_params(query: &QueryData) -> Result<SwingParams, ApiError> {
+    let defaults = SwingParams::default();
+    let swing = query.swing.unwrap_or(defaults.swing);
+    if !(1..=MAX_SWING).contains(&swing) {
+        return Err(ApiError::bad_parameter(
+            "swing",
+            format!("must be between 1 and {}", MAX_SWING),
+        ));
+    }
+    let max_distance = query.max_distance.unwrap_or(defaults.max_distance);
+    if max_distance <= swing {
+        return Err(ApiError::bad_parameter(
+            "max_distance",
+            "must be greater than `swing`",
+        ));
+    }
+    Ok(SwingParams {
+        swing,
+        max_distance,
+    })
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = DivergencesResponse)]
+struct Success {
+    status: String,
+    /// Ordered by their latest price swing.
+    data: Vec<Divergence>,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = DivergencesMeta)]
+struct Meta {
+    oscillator: Oscillator,
+    period: usize,
+    swing: usize,
+    max_distance: usize,
+    #[serde(flatten)]
+    candles: OhlcMeta,
+}
@@ -1,38 +1,60 @@
 use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::crypto_client::{CoinUuidErr::*, CryptoClient};
+use crate::candles::fetch_candles;
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+use crate::precompute::Precomputer;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct PathData {
+    /// A symbol, e.g. `btc`.
     coin: String,
 }
 
+/// The exponential moving average over 20 candles, warmed up over the 40 newest ones.
+#[utoipa::path(
+    get,
+    path = "/exponential_moving_average/{coin}",
+    tag = "indicators",
+    params(PathData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
 pub async fn exponential_moving_average(
     path: web::Path<PathData>,
+    query: web::Query<OhlcQuery>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
+    precomputer: web::Data<Precomputer>,
+) -> Result<HttpResponse, ApiError> {
+    let indicator = Indicator::ExponentialMovingAverage;
+    let params =
+        query.params_with_default_limit(indicator.default_limit(indicator.default_period()))?;
+    if let Some(precomputed) = precomputer.lookup(&path.coin, indicator, &query) {
+        return Ok(HttpResponse::Ok().json(Success {
+            status: "success".to_owned(),
+            data: precomputed.value,
+            meta: precomputed.meta,
+        }));
+    }
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
 
-    let response = match crypto_client.get_coin_ohlc(&uuid).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-    let ema = response.ohlc.iter().take(20).fold(0., |acc: f64, x| {
-        x.close * (2. / (1. + 20.)) + acc * (1. - (2. / (1. + 20.)))
-    });
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    let ema = indicator.compute(&response.ohlc, indicator.default_period())?;
 
-    HttpResponse::Ok().json(Success {
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
         data: ema,
-    })
+        meta: OhlcMeta::new(&response),
+    }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = ExponentialMovingAverageResponse)]
 struct Success {
     status: String,
     data: f64,
+    meta: OhlcMeta,
 }
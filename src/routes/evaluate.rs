@@ -0,0 +1,131 @@
+use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::candles::fetch_candles;
+use crate::crypto_client::{CryptoClient, OhlcParams, ParsedOhlc};
+use crate::error::{require_data, ApiError};
+use crate::expression::{CandleContext, Expr, Series};
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    /// A symbol, e.g. `btc`.
+    coin: String,
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryData {
+    /// e.g. `ema(close, 12) - ema(close, 26)` or `rsi(14) < 30`.
+    expression: String,
+}
+
+/// Evaluates an expression over the candles of a coin. Without a `limit`, enough
+/// candles are fetched for the expression to be defined on the default number of them.
+#[utoipa::path(
+    get,
+    path = "/evaluate/{coin}",
+    tag = "analysis",
+    params(PathData, QueryData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
+pub async fn evaluate(
+    path: web::Path<PathData>,
+    query: web::Query<QueryData>,
+    ohlc_query: web::Query<OhlcQuery>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let expression = parse(&query.expression)?;
+    let lookback = expression.lookback();
+    let params = ohlc_query.params_with_default_limit(
+        (lookback.min(OhlcParams::MAX_LIMIT as usize) as u32 + OhlcParams::DEFAULT_LIMIT)
+            .min(OhlcParams::MAX_LIMIT),
+    )?;
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
+
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    require_data(lookback.saturating_add(1), response.ohlc.len())?;
+    let chronological: Vec<ParsedOhlc> = response.ohlc.iter().rev().cloned().collect();
+    // Long periods over many candles are costly, so they are kept off the async workers.
+    let (chronological, series) = web::block(move || {
+        let series = expression.evaluate(&CandleContext::new(&chronological));
+        (chronological, series)
+    })
+    .await
+    .map_err(|err| ApiError::Internal(err.to_string()))?;
+    let series = series.map_err(|err| ApiError::bad_parameter("expression", err.to_string()))?;
+
+    let values: Vec<Option<Value>> = match series {
+        Series::Numbers(numbers) => numbers
+            .into_iter()
+            .map(|number| (!number.is_nan()).then_some(Value::Number(number)))
+            .collect(),
+        Series::Conditions(conditions) => conditions
+            .into_iter()
+            .map(|condition| condition.map(Value::Condition))
+            .collect(),
+    };
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: chronological
+            .iter()
+            .zip(values)
+            .skip(lookback)
+            .map(|(ohlc, value)| Point {
+                timestamp: ohlc.starting_at,
+                value,
+            })
+            .collect(),
+        meta: Meta {
+            expression: query.into_inner().expression,
+            lookback,
+            candles: OhlcMeta::new(&response),
+        },
+    }))
+}
+
+/// Parses the expression, checking its identifiers before anything is fetched.
+fn parse(source: &str) -> Result<Expr, ApiError> {
+    let expression = Expr::parse(source)
+        .map_err(|err| ApiError::bad_parameter("expression", err.to_string()))?;
+    CandleContext::check(&expression)
+        .map_err(|message| ApiError::bad_parameter("expression", message))?;
+    Ok(expression)
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = EvaluateResponse)]
+struct Success {
+    status: String,
+    /// Oldest first, from the first candle with enough history.
+    data: Vec<Point>,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = EvaluatePoint)]
+struct Point {
+    timestamp: i64,
+    /// `null` where the value is undefined, e.g. for missing candles.
+    value: Option<Value>,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(untagged)]
+#[schema(as = ExpressionValue)]
+enum Value {
+    Number(f64),
+    Condition(bool),
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = EvaluateMeta)]
+struct Meta {
+    expression: String,
+    /// The candles needed before the first point.
+    lookback: usize,
+    #[serde(flatten)]
+    candles: OhlcMeta,
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web::{Bytes, Data};
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures::stream::{self, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use utoipa::IntoParams;
+
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+use crate::interval::Interval;
+use crate::live::{Event, LiveHub, Published, Subscription};
+
+/// Sent as a comment when nothing else was, so that proxies keep the connection open.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    /// A symbol, e.g. `btc`.
+    coin: String,
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryData {
+    /// Comma separated, e.g. `rsi,ema`, every indicator by default.
+    indicators: Option<String>,
+    /// The period of every indicator, their default one when absent.
+    period: Option<usize>,
+    interval: Option<Interval>,
+}
+
+/// Streams the updates of the indicators of a coin as Server-Sent Events, the same
+/// ones as the `/live` WebSocket. Every event has an ID, and a client reconnecting
+/// with `Last-Event-ID` first gets the events it missed, or the latest values when
+/// they are no longer kept.
+#[utoipa::path(
+    get,
+    path = "/events/{coin}",
+    tag = "live",
+    params(
+        PathData,
+        QueryData,
+        ("Last-Event-ID" = Option<u64>, Header, description = "The last event received, to resume from")
+    ),
+    responses((
+        status = 200,
+        description = "A stream of events, each one's `data` being the JSON of the event",
+        body = Event,
+        content_type = "text/event-stream"
+    ))
+)]
+pub async fn events(
+    req: HttpRequest,
+    path: web::Path<PathData>,
+    query: web::Query<QueryData>,
+    hub: web::Data<LiveHub>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriptions = parse_indicators(query.indicators.as_deref())?
+        .into_iter()
+        .map(|indicator| Subscription::new(&path.coin, indicator, query.period, query.interval))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Unknown coins are refused here rather than looked up again on every poll.
+    if let Err(err @ ApiError::UnknownCoin(_)) = crypto_client.get_coin_uuid(&path.coin).await {
+        return Err(err);
+    }
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+
+    // Listening before subscribing, so that no event is published in between.
+    let receiver = hub.events();
+    let mut feed = Feed {
+        hub: hub.clone(),
+        receiver,
+        sent: HashMap::new(),
+        subscriptions: Vec::with_capacity(subscriptions.len()),
+    };
+    let mut latest: Vec<Arc<Published>> = vec![];
+    for subscription in subscriptions {
+        // Those already made are dropped with the feed on failure.
+        latest.extend(hub.subscribe(&subscription)?);
+        feed.subscriptions.push(subscription);
+    }
+    let resumed = last_event_id.and_then(|id| Some((id, hub.replay(id)?)));
+    let initial = match resumed {
+        Some((last_event_id, missed)) => {
+            for subscription in &feed.subscriptions {
+                feed.sent.insert(subscription.clone(), last_event_id);
+            }
+            missed
+        }
+        None => latest,
+    };
+    let initial: Vec<Bytes> = initial
+        .into_iter()
+        .filter_map(|published| feed.accept(&published).then(|| frame(&published)))
+        .collect();
+
+    let body = stream::iter(initial)
+        .chain(stream::unfold(feed, |mut feed| async move {
+            let frame = feed.next().await?;
+            Some((frame, feed))
+        }))
+        .map(Ok::<_, Infallible>);
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body))
+}
+
+fn parse_indicators(indicators: Option<&str>) -> Result<Vec<Indicator>, ApiError> {
+    let Some(indicators) = indicators else {
+        return Ok(Indicator::ALL.to_vec());
+    };
+    let mut parsed: Vec<Indicator> = vec![];
+    for indicator in indicators.split(',') {
+        let indicator = indicator
+            .trim()
+            .parse::<Indicator>()
+            .map_err(|message| ApiError::bad_parameter("indicators", message))?;
+        if !parsed.contains(&indicator) {
+            parsed.push(indicator);
+        }
+    }
+    Ok(parsed)
+}
+
+/// The events of one client, unsubscribing it once the response is dropped.
+struct Feed {
+    hub: Data<LiveHub>,
+    receiver: Receiver<Arc<Published>>,
+    /// The ID of the last event sent for each subscription.
+    sent: HashMap<Subscription, u64>,
+    subscriptions: Vec<Subscription>,
+}
+
+impl Feed {
+    /// Whether an event is for this client and newer than the one it has.
+    fn accept(&mut self, published: &Published) -> bool {
+        let Some(subscription) = published.event.subscription() else {
+            return false;
+        };
+        if !self.subscriptions.contains(subscription) {
+            return false;
+        }
+        let sent = self.sent.entry(subscription.clone()).or_default();
+        if published.id <= *sent {
+            return false;
+        }
+        *sent = published.id;
+        true
+    }
+
+    /// The next frame to send, `None` once the hub is gone.
+    async fn next(&mut self) -> Option<Bytes> {
+        loop {
+            match tokio::time::timeout(KEEP_ALIVE, self.receiver.recv()).await {
+                Err(_) => return Some(Bytes::from_static(b": keep-alive\n\n")),
+                Ok(Ok(published)) if self.accept(&published) => return Some(frame(&published)),
+                // A lagging client misses intermediate values, the next ones still come.
+                Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => {}
+                Ok(Err(RecvError::Closed)) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        for subscription in &self.subscriptions {
+            self.hub.unsubscribe(subscription);
+        }
+    }
+}
+
+fn frame(published: &Published) -> Bytes {
+    Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        published.id,
+        published.event.name(),
+        serde_json::to_string(&published.event).expect("Serializable event")
+    ))
+}
@@ -1,31 +1,41 @@
 use actix_web::web;
 use actix_web::HttpResponse;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::crypto_client::CoinUuidErr::*;
+use crate::candles::fetch_candles;
 use crate::crypto_client::CryptoClient;
+use crate::error::{require_data, ApiError};
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct PathData {
+    /// A symbol, e.g. `btc`.
     coin: String,
+    /// `u` or `uptrend`, `d` or `downtrend`.
     market: String,
 }
 
+/// Retracement levels between the high and low of the latest candle.
+#[utoipa::path(
+    get,
+    path = "/fibonacci_retracement/{coin}/{market}",
+    tag = "indicators",
+    params(PathData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
 pub async fn fibonacci_retracement(
     path: web::Path<PathData>,
+    query: web::Query<OhlcQuery>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let response = match crypto_client.get_coin_ohlc(&uuid).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-    //TODO remove unwrap
-    let ohlc = response.ohlc.into_iter().nth(0).unwrap();
+) -> Result<HttpResponse, ApiError> {
+    let params = query.params()?;
+    let trend = Trend::parse(&path.market)?;
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
+
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    require_data(1, response.ohlc.len())?;
+    let ohlc = &response.ohlc[0];
 
     let high = ohlc.high;
     let low = ohlc.low;
@@ -37,46 +47,44 @@ pub async fn fibonacci_retracement(
 
     for percentage in percentages {
         let second_part = (high - low) * percentage;
-        let first_part = match path.market.to_ascii_uppercase().as_str() {
-            "U" | "UPTREND" => high - second_part,
-            "D" | "DOWNTREND" => low + second_part,
-            &_ => {
-                return HttpResponse::Ok().json(Success {
-                    status: "400 | Bad Request, try inserting the correct market data".to_owned(),
-                    data: vec,
-                })
-            }
+        let first_part = match trend {
+            Trend::Uptrend => high - second_part,
+            Trend::Downtrend => low + second_part,
         };
 
         vec.push(Percentage {
-            percentage: format!("{}%", (percentage * 100.0).to_string()),
+            percentage: format!("{}%", percentage * 100.0),
             value: first_part - second_part,
         });
     }
 
-    HttpResponse::Ok().json(Success {
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
         data: vec,
-    })
+        meta: OhlcMeta::new(&response),
+    }))
 }
 
+/// Extension levels beyond the high and low of the latest candle.
+#[utoipa::path(
+    get,
+    path = "/fibonacci_extension/{coin}/{market}",
+    tag = "indicators",
+    params(PathData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
 pub async fn fibonacci_extension(
     path: web::Path<PathData>,
+    query: web::Query<OhlcQuery>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let response = match crypto_client.get_coin_ohlc(&uuid).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
+) -> Result<HttpResponse, ApiError> {
+    let params = query.params()?;
+    let trend = Trend::parse(&path.market)?;
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
 
-    //TODO remove unwrap
-    let ohlc = response.ohlc.into_iter().nth(0).unwrap();
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    require_data(1, response.ohlc.len())?;
+    let ohlc = &response.ohlc[0];
 
     let high = ohlc.high;
     let low = ohlc.low;
@@ -88,37 +96,51 @@ pub async fn fibonacci_extension(
 
     for percentage in percentages {
         let second_part = (high - low) * percentage;
-        let first_part = match path.market.to_ascii_uppercase().as_str() {
-            "U" | "UPTREND" => high + second_part,
-            "D" | "DOWNTREND" => low - second_part,
-            &_ => {
-                return HttpResponse::Ok().json(Success {
-                    status: "400 | Bad Request, try inserting the correct market trend data"
-                        .to_owned(),
-                    data: vec,
-                })
-            }
+        let first_part = match trend {
+            Trend::Uptrend => high + second_part,
+            Trend::Downtrend => low - second_part,
         };
 
         vec.push(Percentage {
-            percentage: format!("{}%", (percentage * 100.0).to_string()),
+            percentage: format!("{}%", percentage * 100.0),
             value: first_part - second_part,
         });
     }
 
-    HttpResponse::Ok().json(Success {
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
         data: vec,
-    })
+        meta: OhlcMeta::new(&response),
+    }))
+}
+
+enum Trend {
+    Uptrend,
+    Downtrend,
+}
+
+impl Trend {
+    fn parse(market: &str) -> Result<Self, ApiError> {
+        match market.to_ascii_uppercase().as_str() {
+            "U" | "UPTREND" => Ok(Trend::Uptrend),
+            "D" | "DOWNTREND" => Ok(Trend::Downtrend),
+            _ => Err(ApiError::bad_parameter(
+                "market",
+                "expected one of `u`, `uptrend`, `d`, `downtrend`",
+            )),
+        }
+    }
 }
 
 //success Response
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = FibonacciResponse)]
 struct Success {
     status: String,
     data: Vec<Percentage>,
+    meta: OhlcMeta,
 }
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 struct Percentage {
     percentage: String,
     value: f64,
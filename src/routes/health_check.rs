@@ -1,6 +1,12 @@
-
 use actix_web::HttpResponse;
 
+/// Liveness: answers as long as the process is up.
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "operations",
+    responses((status = 200))
+)]
 pub async fn health_check() -> HttpResponse {
     HttpResponse::Ok().finish()
-}
\ No newline at end of file
+}
@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, Session};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::live::{Event, LiveHub, Subscription, SubscriptionRequest};
+
+const MAX_SUBSCRIPTIONS: usize = 50;
+
+/// What a client sends, e.g. `{"action": "subscribe", "coin": "btc", "indicator": "rsi"}`.
+#[derive(serde::Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe(SubscriptionRequest),
+    Unsubscribe(SubscriptionRequest),
+}
+
+/// A WebSocket receiving `subscribe` and `unsubscribe` messages, e.g.
+/// `{"action": "subscribe", "coin": "btc", "indicator": "rsi"}`, and sending events.
+#[utoipa::path(
+    get,
+    path = "/live",
+    tag = "live",
+    responses((status = 101, description = "Switching to the WebSocket protocol", body = Event))
+)]
+pub async fn live(
+    req: HttpRequest,
+    body: web::Payload,
+    hub: web::Data<LiveHub>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    actix_web::rt::spawn(connection(
+        hub,
+        crypto_client,
+        session,
+        messages.aggregate_continuations(),
+    ));
+    Ok(response)
+}
+
+/// Serves one client until either side closes the socket.
+async fn connection(
+    hub: web::Data<LiveHub>,
+    crypto_client: web::Data<CryptoClient>,
+    mut session: Session,
+    mut messages: AggregatedMessageStream,
+) {
+    let mut events = hub.events();
+    let mut subscriptions: HashSet<Subscription> = HashSet::new();
+    'connection: loop {
+        let outgoing = tokio::select! {
+            message = messages.recv() => match message {
+                Some(Ok(AggregatedMessage::Text(text))) => {
+                    handle(&hub, &crypto_client, &mut subscriptions, &text).await
+                }
+                Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        break 'connection;
+                    }
+                    vec![]
+                }
+                Some(Ok(AggregatedMessage::Binary(_))) => vec![error(
+                    None,
+                    ApiError::bad_parameter("message", "must be JSON text"),
+                )],
+                Some(Ok(AggregatedMessage::Pong(_))) => vec![],
+                Some(Ok(AggregatedMessage::Close(_))) | Some(Err(_)) | None => break 'connection,
+            },
+            event = events.recv() => match event {
+                Ok(published)
+                    if published.event.subscription().is_some_and(|s| subscriptions.contains(s)) =>
+                {
+                    vec![published.event.clone()]
+                }
+                // Lagging clients miss intermediate values, the next ones still come.
+                Ok(_) | Err(RecvError::Lagged(_)) => vec![],
+                Err(RecvError::Closed) => break 'connection,
+            },
+        };
+        for event in outgoing {
+            let text = serde_json::to_string(&event).expect("Serializable event");
+            if session.text(text).await.is_err() {
+                break 'connection;
+            }
+        }
+    }
+
+    for subscription in &subscriptions {
+        hub.unsubscribe(subscription);
+    }
+    let _ = session.close(None).await;
+}
+
+/// The events answering a client message.
+async fn handle(
+    hub: &LiveHub,
+    crypto_client: &CryptoClient,
+    subscriptions: &mut HashSet<Subscription>,
+    text: &str,
+) -> Vec<Event> {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(err) => {
+            return vec![error(
+                None,
+                ApiError::bad_parameter("message", err.to_string()),
+            )]
+        }
+    };
+    match message {
+        ClientMessage::Subscribe(request) => {
+            let subscription = match request.subscription() {
+                Ok(subscription) => subscription,
+                Err(err) => return vec![error(None, err)],
+            };
+            if subscriptions.contains(&subscription) {
+                return vec![Event::Subscribed { subscription }];
+            }
+            if subscriptions.len() >= MAX_SUBSCRIPTIONS {
+                return vec![error(
+                    Some(subscription),
+                    ApiError::bad_parameter(
+                        "subscription",
+                        format!("at most {} per connection", MAX_SUBSCRIPTIONS),
+                    ),
+                )];
+            }
+            // Unknown coins are refused here rather than looked up again on every poll.
+            if let Err(err @ ApiError::UnknownCoin(_)) =
+                crypto_client.get_coin_uuid(&subscription.coin).await
+            {
+                return vec![error(Some(subscription), err)];
+            }
+            let latest = match hub.subscribe(&subscription) {
+                Ok(latest) => latest,
+                Err(err) => return vec![error(Some(subscription), err)],
+            };
+            subscriptions.insert(subscription.clone());
+            std::iter::once(Event::Subscribed { subscription })
+                .chain(latest.map(|published| published.event.clone()))
+                .collect()
+        }
+        ClientMessage::Unsubscribe(request) => {
+            let subscription = match request.subscription() {
+                Ok(subscription) => subscription,
+                Err(err) => return vec![error(None, err)],
+            };
+            if subscriptions.remove(&subscription) {
+                hub.unsubscribe(&subscription);
+            }
+            vec![Event::Unsubscribed { subscription }]
+        }
+    }
+}
+
+fn error(subscription: Option<Subscription>, err: ApiError) -> Event {
+    Event::Error {
+        subscription,
+        error: err.body(),
+    }
+}
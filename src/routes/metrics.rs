@@ -0,0 +1,20 @@
+use actix_web::{web, HttpResponse};
+
+use crate::crypto_client::CryptoClient;
+use crate::metrics::Metrics;
+
+/// Prometheus metrics in the text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "operations",
+    responses((status = 200, body = String, content_type = "text/plain"))
+)]
+pub async fn metrics(
+    metrics: web::Data<Metrics>,
+    crypto_client: web::Data<CryptoClient>,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(metrics.render(&crypto_client))
+}
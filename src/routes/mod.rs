@@ -1,15 +1,48 @@
+pub mod alerts;
 pub mod aroon_oscillator;
+pub mod backtest;
+pub mod batch;
+pub mod cache;
+pub mod divergences;
+pub mod docs;
 pub mod ema;
+pub mod evaluate;
+pub mod events;
 pub mod fibonacci_retracement;
 pub mod health_check;
+pub mod live;
+pub mod metrics;
+pub mod multi_timeframe;
+pub mod ohlc_query;
+pub mod optimize;
+pub mod readiness;
 pub mod rsi;
+pub mod screener;
+pub mod signals;
 pub mod simple_moving_average;
 pub mod stochastic_oscillator;
 
-pub use aroon_oscillator::*;
-pub use ema::*;
-pub use fibonacci_retracement::*;
+pub use alerts::{
+    alert_deliveries, create_alert, delete_alert, get_alert, list_alerts, replace_alert,
+};
+pub use aroon_oscillator::aroon_oscillator;
+pub use backtest::backtest;
+pub use batch::batch;
+pub use cache::*;
+pub use divergences::divergences;
+pub use docs::{docs, openapi_json};
+pub use ema::exponential_moving_average;
+pub use evaluate::evaluate;
+pub use events::events;
+pub use fibonacci_retracement::{fibonacci_extension, fibonacci_retracement};
 pub use health_check::*;
-pub use rsi::*;
-pub use simple_moving_average::*;
-pub use stochastic_oscillator::*;
+pub use live::live;
+pub use metrics::metrics;
+pub use multi_timeframe::multi_timeframe;
+pub use optimize::optimize;
+pub use readiness::readiness;
+pub use rsi::rsi;
+pub use screener::screener;
+pub use signals::signals;
+pub use simple_moving_average::simple_moving_average;
+pub use stochastic_oscillator::stochastic_oscillator;
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use futures::future::try_join_all;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::candles::{fetch_candles, CandleRequest};
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::{Bias, Indicator};
+use crate::interval::Interval;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+
+const DEFAULT_INTERVALS: [Interval; 3] = [Interval::Hour, Interval::FourHours, Interval::Day];
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    /// A symbol, e.g. `btc`.
+    coin: String,
+    indicator: Indicator,
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryData {
+    /// Comma separated, e.g. `hour,4hours,day`.
+    intervals: Option<String>,
+    period: Option<usize>,
+}
+
+/// Computes one indicator over several intervals of the same coin. Every interval
+/// shares the `from`, `to`, `limit` and `gaps` parameters so that the values are
+/// comparable, the `interval` parameter being ignored.
+#[utoipa::path(
+    get,
+    path = "/multi_timeframe/{coin}/{indicator}",
+    tag = "indicators",
+    params(PathData, QueryData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
+pub async fn multi_timeframe(
+    path: web::Path<PathData>,
+    query: web::Query<QueryData>,
+    ohlc_query: web::Query<OhlcQuery>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let intervals = parse_intervals(query.intervals.as_deref())?;
+    let indicator = path.indicator;
+    let period = indicator.period(query.period)?;
+    let requests = intervals
+        .iter()
+        .map(|interval| ohlc_query.params_in(*interval, indicator.default_limit(period)))
+        .collect::<Result<Vec<CandleRequest>, ApiError>>()?;
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
+
+    let responses = try_join_all(requests.iter().map(|request| {
+        let crypto_client = &crypto_client;
+        let uuid = &uuid;
+        async move { fetch_candles(crypto_client, uuid, request).await }
+    }))
+    .await?;
+
+    let mut data = BTreeMap::new();
+    for response in responses {
+        let value = indicator.compute(&response.ohlc, period)?;
+        let latest = &response.ohlc[0];
+        data.insert(
+            response.interval,
+            Timeframe {
+                value,
+                bias: indicator.bias(value, latest.close),
+                timestamp: latest.starting_at,
+                meta: OhlcMeta::new(&response),
+            },
+        );
+    }
+    let agreement = Agreement::new(data.values().map(|timeframe| timeframe.bias));
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data,
+        agreement,
+        meta: Meta { indicator, period },
+    }))
+}
+
+fn parse_intervals(intervals: Option<&str>) -> Result<Vec<Interval>, ApiError> {
+    let Some(intervals) = intervals else {
+        return Ok(DEFAULT_INTERVALS.to_vec());
+    };
+    let mut parsed = intervals
+        .split(',')
+        .map(|interval| interval.trim().parse::<Interval>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|message| ApiError::bad_parameter("intervals", message))?;
+    parsed.sort();
+    parsed.dedup();
+    Ok(parsed)
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = MultiTimeframeResponse)]
+struct Success {
+    status: String,
+    /// Keyed by interval, shortest first.
+    data: BTreeMap<Interval, Timeframe>,
+    agreement: Agreement,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct Timeframe {
+    value: f64,
+    bias: Bias,
+    /// Start of the most recent candle the value was computed on.
+    timestamp: i64,
+    meta: OhlcMeta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct Agreement {
+    bullish: usize,
+    bearish: usize,
+    neutral: usize,
+    /// The bias shared by every interval, `mixed` otherwise.
+    consensus: Consensus,
+    /// Share of the intervals agreeing with the most common bias.
+    ratio: f64,
+}
+
+impl Agreement {
+    fn new(biases: impl Iterator<Item = Bias>) -> Self {
+        let (mut bullish, mut bearish, mut neutral) = (0, 0, 0);
+        for bias in biases {
+            match bias {
+                Bias::Bullish => bullish += 1,
+                Bias::Bearish => bearish += 1,
+                Bias::Neutral => neutral += 1,
+            }
+        }
+        let total = bullish + bearish + neutral;
+        let consensus = if bullish == total {
+            Consensus::Bullish
+        } else if bearish == total {
+            Consensus::Bearish
+        } else if neutral == total {
+            Consensus::Neutral
+        } else {
+            Consensus::Mixed
+        };
+        Self {
+            bullish,
+            bearish,
+            neutral,
+            consensus,
+            ratio: bullish.max(bearish).max(neutral) as f64 / total.max(1) as f64,
+        }
+    }
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum Consensus {
+    Bullish,
+    Bearish,
+    Neutral,
+    Mixed,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = MultiTimeframeMeta)]
+struct Meta {
+    indicator: Indicator,
+    period: usize,
+}
@@ -0,0 +1,113 @@
+use utoipa::{IntoParams, ToSchema};
+
+use crate::candles::{CandleRequest, Candles};
+use crate::crypto_client::OhlcParams;
+use crate::error::ApiError;
+use crate::interval::Interval;
+use crate::resample::{Gap, GapPolicy};
+
+/// Query string accepted by every OHLC based route, e.g. `?interval=4hours&limit=100&gaps=mark`.
+#[derive(serde::Deserialize, Default, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct OhlcQuery {
+    /// `day` by default.
+    interval: Option<Interval>,
+    /// Unix timestamps in seconds bounding the candles.
+    from: Option<i64>,
+    to: Option<i64>,
+    /// The number of candles, between 1 and 5000, fewer for resampled intervals.
+    limit: Option<u32>,
+    /// How missing candles are handled, `skip` by default.
+    gaps: Option<GapPolicy>,
+}
+
+impl OhlcQuery {
+    pub fn params(&self) -> Result<CandleRequest, ApiError> {
+        self.params_with_default_limit(OhlcParams::DEFAULT_LIMIT)
+    }
+
+    /// Same as `params`, for routes needing more candles than usual when no `limit` is given.
+    pub fn params_with_default_limit(&self, default_limit: u32) -> Result<CandleRequest, ApiError> {
+        self.params_in(self.interval.unwrap_or_default(), default_limit)
+    }
+
+    /// Same as `params_with_default_limit`, for candles of another interval than the
+    /// asked one. The default limit is cut to the most candles of that interval.
+    pub fn params_in(
+        &self,
+        interval: Interval,
+        default_limit: u32,
+    ) -> Result<CandleRequest, ApiError> {
+        let max_limit = CandleRequest::max_limit(interval);
+        let limit = self.limit.unwrap_or(default_limit.min(max_limit));
+        if limit == 0 || limit > max_limit {
+            return Err(ApiError::bad_parameter(
+                "limit",
+                format!(
+                    "must be between 1 and {} for `{}` candles",
+                    max_limit, interval
+                ),
+            ));
+        }
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err(ApiError::bad_parameter("from", "must be before `to`"));
+            }
+        }
+
+        Ok(CandleRequest {
+            interval,
+            from: self.from,
+            to: self.to,
+            limit,
+            gaps: self.gaps.unwrap_or_default(),
+            latest: false,
+        })
+    }
+
+    /// The interval asked for when nothing else is, the only queries a precomputed
+    /// value answers.
+    pub fn precomputed_interval(&self) -> Option<Interval> {
+        let defaults =
+            self.from.is_none() && self.to.is_none() && self.limit.is_none() && self.gaps.is_none();
+        defaults.then(|| self.interval.unwrap_or_default())
+    }
+}
+
+/// The interval and time range the returned value was actually computed on.
+#[derive(serde::Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OhlcMeta {
+    interval: Interval,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resampled_from: Option<Interval>,
+    from: Option<i64>,
+    to: Option<i64>,
+    candles: usize,
+    gaps: Vec<Gap>,
+    /// When a precomputed value was computed, absent for the ones computed on request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    computed_at: Option<i64>,
+}
+
+impl OhlcMeta {
+    pub fn new(candles: &Candles) -> Self {
+        Self {
+            interval: candles.interval,
+            resampled_from: Some(candles.source_interval)
+                .filter(|source| *source != candles.interval),
+            from: candles.ohlc.iter().map(|ohlc| ohlc.starting_at).min(),
+            to: candles.ohlc.iter().map(|ohlc| ohlc.ending_at).max(),
+            candles: candles.ohlc.len(),
+            gaps: candles.gaps.clone(),
+            computed_at: None,
+        }
+    }
+
+    pub fn computed_at(self, timestamp: i64) -> Self {
+        Self {
+            computed_at: Some(timestamp),
+            ..self
+        }
+    }
+}
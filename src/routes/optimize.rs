@@ -0,0 +1,144 @@
+use actix_web::{web, HttpResponse};
+use utoipa::ToSchema;
+
+use crate::backtest::Costs;
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::optimize::{Objective, Optimization, Parameter, Report, Search, WalkForward, Workers};
+use crate::routes::backtest::{initial_capital, CandleSource};
+use crate::routes::ohlc_query::OhlcMeta;
+
+/// Candles split into folds by default, on top of those the rules need to warm up.
+const DEFAULT_BARS: u32 = 1000;
+const DEFAULT_SAMPLES: usize = 100;
+const DEFAULT_TOP: usize = 10;
+
+#[derive(serde::Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeRequest {
+    #[serde(flatten)]
+    source: CandleSource,
+    /// Conditions with `{name}` placeholders, e.g. `rsi({period}) < {oversold}`.
+    entry: String,
+    exit: String,
+    parameters: Vec<Parameter>,
+    #[serde(default)]
+    search: Search,
+    samples: Option<usize>,
+    seed: Option<u64>,
+    #[serde(default)]
+    objective: Objective,
+    folds: Option<usize>,
+    in_sample: Option<f64>,
+    top: Option<usize>,
+    #[serde(flatten)]
+    costs: Costs,
+    initial_capital: Option<f64>,
+}
+
+/// Searches the parameters of a strategy, scored by walk-forward validation.
+#[utoipa::path(
+    post,
+    path = "/optimize",
+    tag = "strategies",
+    request_body = OptimizeRequest,
+    responses((status = 200, body = Success))
+)]
+pub async fn optimize(
+    body: web::Json<OptimizeRequest>,
+    crypto_client: web::Data<CryptoClient>,
+    workers: web::Data<Workers>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let defaults = WalkForward::default();
+    let optimization = Optimization {
+        entry: body.entry,
+        exit: body.exit,
+        costs: body.costs,
+        initial_capital: initial_capital(body.initial_capital)?,
+        objective: body.objective,
+        walk_forward: WalkForward {
+            folds: body.folds.unwrap_or(defaults.folds),
+            in_sample: body.in_sample.unwrap_or(defaults.in_sample),
+        },
+    };
+    optimization.walk_forward.validate()?;
+    let top = body.top.unwrap_or(DEFAULT_TOP).max(1);
+    // Without a seed a random search differs from one request to the next.
+    let seed = body.seed.unwrap_or_else(rand::random);
+    let candidates = optimization.candidates(
+        &body.parameters,
+        body.search,
+        body.samples.unwrap_or(DEFAULT_SAMPLES),
+        seed,
+    )?;
+    let lookback = optimization.lookback(&candidates)?;
+    let (candles, ohlc_meta) = body
+        .source
+        .load(
+            &crypto_client,
+            (lookback as u32).saturating_add(DEFAULT_BARS),
+        )
+        .await?;
+
+    let count = candidates.len();
+    let bars = candles.len();
+    let reserved = workers.reserve(count).await;
+    let (optimization, report) = web::block(move || {
+        let report = optimization.run(&candles, candidates, reserved.num_permits());
+        drop(reserved);
+        (optimization, report)
+    })
+    .await
+    .map_err(|err| ApiError::Internal(err.to_string()))?;
+    let mut report = report?;
+    report.best.truncate(top);
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: report,
+        meta: Meta {
+            entry: optimization.entry,
+            exit: optimization.exit,
+            candidates: count,
+            search: body.search,
+            seed: (body.search == Search::Random).then_some(seed),
+            objective: optimization.objective,
+            folds: optimization.walk_forward.folds,
+            in_sample: optimization.walk_forward.in_sample,
+            costs: optimization.costs,
+            bars,
+            candles: ohlc_meta,
+        },
+    }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = OptimizeResponse)]
+struct Success {
+    status: String,
+    data: Report,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = OptimizeMeta)]
+struct Meta {
+    entry: String,
+    exit: String,
+    /// Parameter sets evaluated on every fold.
+    candidates: usize,
+    search: Search,
+    /// The seed of a random search, to repeat it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    objective: Objective,
+    folds: usize,
+    in_sample: f64,
+    #[serde(flatten)]
+    costs: Costs,
+    bars: usize,
+    #[serde(flatten)]
+    candles: Option<OhlcMeta>,
+}
@@ -0,0 +1,33 @@
+use actix_web::{web, HttpResponse};
+
+use crate::alerts::Alerts;
+use crate::crypto_client::CryptoClient;
+use crate::live::LiveHub;
+use crate::precompute::Precomputer;
+use crate::readiness::{Readiness, ReadinessStatus, Report};
+
+/// 200 when every dependency is usable, 503 otherwise, with the report of each.
+#[utoipa::path(
+    get,
+    path = "/readiness",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Ready", body = Report),
+        (status = 503, description = "Not ready", body = Report)
+    )
+)]
+pub async fn readiness(
+    readiness: web::Data<Readiness>,
+    crypto_client: web::Data<CryptoClient>,
+    alerts: web::Data<Alerts>,
+    precomputer: web::Data<Precomputer>,
+    live_hub: web::Data<LiveHub>,
+) -> HttpResponse {
+    let report = readiness
+        .check(&crypto_client, &alerts, &precomputer, &live_hub)
+        .await;
+    match report.status {
+        ReadinessStatus::Ready => HttpResponse::Ok().json(report),
+        ReadinessStatus::NotReady => HttpResponse::ServiceUnavailable().json(report),
+    }
+}
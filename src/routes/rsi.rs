@@ -1,64 +1,58 @@
 use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::crypto_client::{CoinUuidErr::*, CryptoClient};
+use crate::candles::fetch_candles;
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+use crate::precompute::Precomputer;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct PathData {
+    /// A symbol, e.g. `btc`.
     coin: String,
 }
 
+/// The relative strength index over 14 candles.
+#[utoipa::path(
+    get,
+    path = "/rsi/{coin}",
+    tag = "indicators",
+    params(PathData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
 pub async fn rsi(
     path: web::Path<PathData>,
+    query: web::Query<OhlcQuery>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let response = match crypto_client.get_coin_ohlc(&uuid).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let mut i: i8 = 0;
-    let mut prev_close: f64 = 0.0;
-    let mut sum_of_gains = 0.0;
-    let mut sum_of_losses = 0.0;
-    for ohlc in response.ohlc.iter() {
-        if i != 0 {
-            let x = prev_close - ohlc.close;
-            match x {
-                res if res > 0.0 => sum_of_gains += x,
-                res if res < 0.0 => sum_of_losses += x.abs(),
-                _ => {}
-            };
-        }
-
-        i += 1;
-        prev_close = ohlc.close;
-        if i == 14 {
-            break;
-        }
+    precomputer: web::Data<Precomputer>,
+) -> Result<HttpResponse, ApiError> {
+    let params = query.params()?;
+    if let Some(precomputed) = precomputer.lookup(&path.coin, Indicator::Rsi, &query) {
+        return Ok(HttpResponse::Ok().json(Success {
+            status: "success".to_owned(),
+            data: precomputed.value,
+            meta: precomputed.meta,
+        }));
     }
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
 
-    let average_gain = sum_of_gains / 14.0;
-    let average_losses = sum_of_losses / 14.0;
-
-    let rs = average_gain / average_losses;
-
-    let second_part = 100.0 / (1.0 + rs);
-    let rsi = 100.0 - second_part;
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    let rsi = Indicator::Rsi.compute(&response.ohlc, Indicator::Rsi.default_period())?;
 
-    HttpResponse::Ok().json(Success {
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
         data: rsi,
-    })
+        meta: OhlcMeta::new(&response),
+    }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = RsiResponse)]
 struct Success {
     status: String,
     data: f64,
+    meta: OhlcMeta,
 }
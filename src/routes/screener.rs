@@ -0,0 +1,140 @@
+use actix_web::{web, HttpResponse};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::crypto_client::{CryptoClient, OhlcParams};
+use crate::error::{ApiError, ErrorBody};
+use crate::routes::ohlc_query::OhlcQuery;
+use crate::screener::{Screen, SortKey, UniverseCoin};
+
+const MAX_UNIVERSE: usize = 100;
+const MAX_CONCURRENT_COINS: usize = 8;
+
+#[derive(serde::Deserialize, ToSchema)]
+pub struct ScreenerRequest {
+    /// Explicit universe, e.g. `["btc", "eth"]`.
+    coins: Option<Vec<String>>,
+    /// Universe of the `top` coins by market capitalisation.
+    top: Option<u32>,
+    /// A condition over values, e.g. `rsi < 30 and price > ema_200`.
+    filter: String,
+    #[serde(default)]
+    sort: Vec<SortKey>,
+    /// The candles every value is computed on, as the query of the indicator routes.
+    #[serde(default)]
+    params: OhlcQuery,
+    /// The most matches returned.
+    limit: Option<usize>,
+}
+
+/// Filters and sorts a universe of coins by their indicator values.
+#[utoipa::path(
+    post,
+    path = "/screener",
+    tag = "analysis",
+    request_body = ScreenerRequest,
+    responses((status = 200, body = Success))
+)]
+pub async fn screener(
+    body: web::Json<ScreenerRequest>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let screen = Screen::new(&body.filter, body.sort)?;
+    let candles = body.params.params_with_default_limit(
+        (screen
+            .candles_required()
+            .min(OhlcParams::MAX_LIMIT as usize) as u32)
+            .max(OhlcParams::DEFAULT_LIMIT),
+    )?;
+
+    let universe: Vec<UniverseCoin> = match (body.coins, body.top) {
+        (Some(coins), None) if (1..=MAX_UNIVERSE).contains(&coins.len()) => coins
+            .into_iter()
+            .map(|symbol| UniverseCoin { symbol, uuid: None })
+            .collect(),
+        (None, Some(top)) if (1..=MAX_UNIVERSE).contains(&(top as usize)) => crypto_client
+            .get_top_coins(top)
+            .await?
+            .into_iter()
+            .map(|coin| UniverseCoin {
+                symbol: coin.symbol,
+                uuid: Some(coin.uuid),
+            })
+            .collect(),
+        _ => {
+            return Err(ApiError::bad_parameter(
+                "coins",
+                format!(
+                    "give either `coins` or `top`, for between 1 and {} coins",
+                    MAX_UNIVERSE
+                ),
+            ))
+        }
+    };
+    let universe_size = universe.len();
+
+    let mut result = screen
+        .run(&crypto_client, universe, &candles, MAX_CONCURRENT_COINS)
+        .await;
+    let matched = result.matches.len();
+    if let Some(limit) = body.limit {
+        result.matches.truncate(limit);
+    }
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: result
+            .matches
+            .into_iter()
+            .map(|matched| ScreenedCoin {
+                coin: matched.coin,
+                values: matched.values,
+            })
+            .collect(),
+        errors: result
+            .errors
+            .into_iter()
+            .map(|(coin, err)| CoinError {
+                coin,
+                error: err.error_body(),
+            })
+            .collect(),
+        meta: Meta {
+            universe: universe_size,
+            matched,
+            filter: body.filter,
+        },
+    }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = ScreenerResponse)]
+struct Success {
+    status: String,
+    data: Vec<ScreenedCoin>,
+    /// The coins that couldn't be evaluated, left out of `data`.
+    errors: Vec<CoinError>,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct ScreenedCoin {
+    coin: String,
+    values: BTreeMap<String, f64>,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct CoinError {
+    coin: String,
+    error: ErrorBody,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = ScreenerMeta)]
+struct Meta {
+    universe: usize,
+    /// Matches before `limit` is applied.
+    matched: usize,
+    filter: String,
+}
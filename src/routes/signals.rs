@@ -0,0 +1,97 @@
+use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::candles::fetch_candles;
+use crate::crypto_client::{CryptoClient, OhlcParams, ParsedOhlc};
+use crate::error::{require_data, ApiError};
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
+use crate::signals::{detect, Signal, SignalKind, SignalParams};
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct PathData {
+    /// A symbol, e.g. `btc`.
+    coin: String,
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryData {
+    /// Comma separated, e.g. `ma_crossover,rsi`, every kind by default.
+    signals: Option<String>,
+}
+
+/// Detects buy and sell signals over the candles of a coin. Without a `limit`,
+/// enough candles are fetched for every detector to cover the default number of them.
+#[utoipa::path(
+    get,
+    path = "/signals/{coin}",
+    tag = "analysis",
+    params(PathData, QueryData, SignalParams, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
+pub async fn signals(
+    path: web::Path<PathData>,
+    query: web::Query<QueryData>,
+    signal_params: web::Query<SignalParams>,
+    ohlc_query: web::Query<OhlcQuery>,
+    crypto_client: web::Data<CryptoClient>,
+) -> Result<HttpResponse, ApiError> {
+    let kinds = parse_kinds(query.signals.as_deref())?;
+    let signal_params = signal_params.into_inner();
+    signal_params.validate()?;
+    let lookback = kinds
+        .iter()
+        .map(|kind| signal_params.lookback(*kind))
+        .max()
+        .unwrap_or(0);
+    let params = ohlc_query.params_with_default_limit(
+        (lookback as u32 + OhlcParams::DEFAULT_LIMIT).min(OhlcParams::MAX_LIMIT),
+    )?;
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
+
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    // A crossing also needs the value on the candle before it.
+    require_data(lookback + 1, response.ohlc.len())?;
+    let chronological: Vec<ParsedOhlc> = response.ohlc.iter().rev().cloned().collect();
+
+    Ok(HttpResponse::Ok().json(Success {
+        status: "success".to_owned(),
+        data: detect(&chronological, &kinds, &signal_params),
+        meta: Meta {
+            signals: kinds,
+            candles: OhlcMeta::new(&response),
+        },
+    }))
+}
+
+fn parse_kinds(kinds: Option<&str>) -> Result<Vec<SignalKind>, ApiError> {
+    let Some(kinds) = kinds else {
+        return Ok(SignalKind::ALL.to_vec());
+    };
+    let mut parsed = kinds
+        .split(',')
+        .map(|kind| kind.trim().parse::<SignalKind>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|message| ApiError::bad_parameter("signals", message))?;
+    parsed.sort();
+    parsed.dedup();
+    Ok(parsed)
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = SignalsResponse)]
+struct Success {
+    status: String,
+    /// Oldest first.
+    data: Vec<Signal>,
+    meta: Meta,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = SignalsMeta)]
+struct Meta {
+    signals: Vec<SignalKind>,
+    #[serde(flatten)]
+    candles: OhlcMeta,
+}
@@ -2,49 +2,97 @@ use actix_web::{
     web::{self},
     HttpResponse,
 };
+use utoipa::{IntoParams, ToSchema};
 
-use crate::crypto_client::{CoinUuidErr::*, CryptoClient};
+use crate::crypto_client::{CryptoClient, TimePeriod};
+use crate::error::{require_data, ApiError};
+use crate::indicators;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct PathData {
+    /// A symbol, e.g. `btc`.
     coin: String,
-    time: String,
+    /// The span of the price history.
+    time: TimePeriod,
 }
 
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryData {
+    /// Number of history points in each average, the whole history when missing.
+    window: Option<usize>,
+}
+
+/// Moving averages of the price history.
+#[utoipa::path(
+    get,
+    path = "/simple_moving_average/{coin}/{time}",
+    tag = "indicators",
+    params(PathData, QueryData),
+    responses((status = 200, body = Success))
+)]
 pub async fn simple_moving_average(
     path: web::Path<PathData>,
+    query: web::Query<QueryData>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let response = match crypto_client.get_history_prices(&uuid, &path.time).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let mut sum = 0.0;
-    let mut n = 0.0;
-    for entry in response.history {
-        sum += entry.price;
-        n += 1.0;
+) -> Result<HttpResponse, ApiError> {
+    if query.window == Some(0) {
+        return Err(ApiError::bad_parameter("window", "must be at least 1"));
     }
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
 
-    let sma = sum / n;
+    let mut history = crypto_client
+        .get_history_prices(&uuid, path.time)
+        .await?
+        .history;
+    history.sort_by_key(|entry| entry.timestamp);
+    let window = query.window.unwrap_or(history.len());
+    require_data(window.max(1), history.len())?;
 
-    HttpResponse::Ok().json(Success {
+    let prices: Vec<f64> = history.iter().map(|entry| entry.price).collect();
+    let series: Vec<SeriesPoint> = indicators::simple_moving_average(&prices, window)
+        .into_iter()
+        .zip(&history[window - 1..])
+        .map(|(value, entry)| SeriesPoint {
+            timestamp: entry.timestamp,
+            value,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
-        data: SimpleMovingAverageData(sma),
-    })
+        data: SimpleMovingAverageData(series[series.len() - 1].value),
+        series,
+        meta: Meta {
+            period: path.time,
+            window,
+        },
+    }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = SimpleMovingAverageResponse)]
 struct Success {
     status: String,
+    /// The most recent average.
     data: SimpleMovingAverageData,
+    /// Every average, oldest first.
+    series: Vec<SeriesPoint>,
+    meta: Meta,
 }
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 struct SimpleMovingAverageData(f64);
+
+#[derive(serde::Serialize, ToSchema)]
+struct SeriesPoint {
+    timestamp: i64,
+    value: f64,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = SimpleMovingAverageMeta)]
+struct Meta {
+    period: TimePeriod,
+    window: usize,
+}
@@ -1,61 +1,63 @@
 use actix_web::{web, HttpResponse};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::crypto_client::{CoinUuidErr::*, CryptoClient};
+use crate::candles::fetch_candles;
+use crate::crypto_client::CryptoClient;
+use crate::error::ApiError;
+use crate::indicators::Indicator;
+use crate::precompute::Precomputer;
+use crate::routes::ohlc_query::{OhlcMeta, OhlcQuery};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct PathData {
+    /// A symbol, e.g. `btc`.
     coin: String,
 }
 
+/// The stochastic oscillator over 14 candles.
+#[utoipa::path(
+    get,
+    path = "/stochastic_oscillator/{coin}",
+    tag = "indicators",
+    params(PathData, OhlcQuery),
+    responses((status = 200, body = Success))
+)]
 pub async fn stochastic_oscillator(
     path: web::Path<PathData>,
+    query: web::Query<OhlcQuery>,
     crypto_client: web::Data<CryptoClient>,
-) -> HttpResponse {
-    let uuid = match crypto_client.get_coin_uuid(&path.coin).await {
-        Ok(uuid) => uuid,
-        Err(CoinNotFound) => return HttpResponse::BadRequest().finish(),
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let response = match crypto_client.get_coin_ohlc(&uuid).await {
-        Ok(response) => response,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-
-    let mut i: i8 = 0;
-    let mut highest_high: f64 = 0.0;
-    let mut lowest_low: f64 = f64::INFINITY;
-    for ohlc in response.ohlc.iter() {
-        let high = ohlc.high;
-        let low = ohlc.low;
-        if highest_high < high {
-            highest_high = high;
-        }
-
-        if lowest_low > low {
-            lowest_low = low;
-        }
-
-        i += 1;
-        if i == 14 {
-            break;
-        }
+    precomputer: web::Data<Precomputer>,
+) -> Result<HttpResponse, ApiError> {
+    let params = query.params()?;
+    if let Some(precomputed) =
+        precomputer.lookup(&path.coin, Indicator::StochasticOscillator, &query)
+    {
+        return Ok(HttpResponse::Ok().json(Success {
+            status: "success".to_owned(),
+            data: precomputed.value,
+            meta: precomputed.meta,
+        }));
     }
+    let uuid = crypto_client.get_coin_uuid(&path.coin).await?;
 
-    let numerator = (response.ohlc.into_iter().nth(0).unwrap().close) - lowest_low;
-
-    let denominator = highest_high - lowest_low;
-
-    let stochastic_oscillator: f64 = (numerator / denominator) * 100.;
+    let response = fetch_candles(&crypto_client, &uuid, &params).await?;
+    let stochastic_oscillator = Indicator::StochasticOscillator.compute(
+        &response.ohlc,
+        Indicator::StochasticOscillator.default_period(),
+    )?;
 
-    HttpResponse::Ok().json(Success {
+    Ok(HttpResponse::Ok().json(Success {
         status: "success".to_owned(),
         data: stochastic_oscillator,
-    })
+        meta: OhlcMeta::new(&response),
+    }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+#[schema(as = StochasticOscillatorResponse)]
 struct Success {
     status: String,
     data: f64,
+    meta: OhlcMeta,
 }
@@ -0,0 +1,265 @@
+//! Filtering and ranking a universe of coins by a condition over indicator values.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use actix_web::web;
+use futures::stream::{self, StreamExt};
+
+use crate::candles::{fetch_candles, CandleRequest};
+use crate::crypto_client::{CryptoClient, ParsedOhlc};
+use crate::error::{require_data, ApiError};
+use crate::expression::{CandleContext, Context, Expr, Series};
+use crate::indicators::Indicator;
+
+/// A value the filter and the sort keys can refer to: a field of the latest candle,
+/// one of `CandleContext::FIELDS` as in expressions, or an indicator with its
+/// default period (`rsi`) or a given one (`ema_200`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Field(&'static str),
+    Indicator(Indicator, usize),
+}
+
+impl Quantity {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        if let Some(field) = CandleContext::FIELDS.iter().find(|field| **field == name) {
+            return Ok(Quantity::Field(field));
+        }
+        if let Ok(indicator) = name.parse::<Indicator>() {
+            return Ok(Quantity::Indicator(indicator, indicator.default_period()));
+        }
+        name.rsplit_once('_')
+            .and_then(|(indicator, period)| {
+                let indicator = indicator.parse::<Indicator>().ok()?;
+                let period = indicator.period(Some(period.parse().ok()?)).ok()?;
+                Some(Quantity::Indicator(indicator, period))
+            })
+            .ok_or_else(|| format!("unknown value `{}`", name))
+    }
+
+    fn candles_required(&self) -> usize {
+        match self {
+            Quantity::Indicator(indicator, period) => indicator.candles_required(*period),
+            _ => 1,
+        }
+    }
+
+    /// One value per candle, `candles` being oldest first.
+    fn series(&self, candles: &[ParsedOhlc]) -> Vec<f64> {
+        match self {
+            Quantity::Indicator(indicator, period) => indicator.series(candles, *period),
+            Quantity::Field(name) => CandleContext::new(candles)
+                .variable(name)
+                .expect("Every field of `CandleContext::FIELDS` is a variable"),
+        }
+    }
+}
+
+/// Resolves the quantities of a screen, the functions of the filter reading the
+/// fields of the candles.
+struct ScreenContext<'a> {
+    candles: CandleContext<'a>,
+    series: &'a BTreeMap<String, Vec<f64>>,
+}
+
+impl Context for ScreenContext<'_> {
+    fn points(&self) -> usize {
+        self.candles.points()
+    }
+
+    fn variable(&self, name: &str) -> Option<Vec<f64>> {
+        match self.series.get(name) {
+            Some(series) => Some(series.clone()),
+            None => self.candles.variable(name),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct SortKey {
+    pub key: String,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// A coin of the universe, with its UUID when already known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniverseCoin {
+    pub symbol: String,
+    pub uuid: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub coin: String,
+    /// Every value used by the filter and the sort keys, by name.
+    pub values: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScreenResult {
+    /// Sorted by the sort keys.
+    pub matches: Vec<Match>,
+    /// The coins that couldn't be evaluated, in the order of the universe.
+    pub errors: Vec<(String, ApiError)>,
+}
+
+#[derive(Clone)]
+pub struct Screen {
+    filter: Expr,
+    sort: Vec<SortKey>,
+    quantities: Vec<(String, Quantity)>,
+}
+
+impl Screen {
+    pub fn new(filter: &str, sort: Vec<SortKey>) -> Result<Self, ApiError> {
+        let filter = Expr::parse(filter)
+            .map_err(|err| ApiError::bad_parameter("filter", err.to_string()))?;
+        if !filter.is_condition() {
+            return Err(ApiError::bad_parameter(
+                "filter",
+                "must be a condition, e.g. `rsi < 30`",
+            ));
+        }
+
+        let mut quantities: Vec<(String, Quantity)> = vec![];
+        let names = filter
+            .variables()
+            .into_iter()
+            .map(|name| ("filter", name))
+            .chain(sort.iter().map(|key| ("sort", key.key.as_str())));
+        for (parameter, name) in names {
+            if quantities.iter().all(|(known, _)| known != name) {
+                let quantity = Quantity::parse(name)
+                    .map_err(|message| ApiError::bad_parameter(parameter, message))?;
+                quantities.push((name.to_owned(), quantity));
+            }
+        }
+
+        Ok(Self {
+            filter,
+            sort,
+            quantities,
+        })
+    }
+
+    /// The number of candles needed to compute every value and the filter.
+    pub fn candles_required(&self) -> usize {
+        self.quantities
+            .iter()
+            .map(|(_, quantity)| quantity.candles_required())
+            .max()
+            .unwrap_or(1)
+            .max(self.filter.lookback().saturating_add(1))
+    }
+
+    /// Evaluates every coin of the universe, at most `concurrency` at a time.
+    pub async fn run(
+        &self,
+        crypto_client: &CryptoClient,
+        universe: Vec<UniverseCoin>,
+        candles: &CandleRequest,
+        concurrency: usize,
+    ) -> ScreenResult {
+        let mut evaluated: Vec<(usize, String, Result<Option<Match>, ApiError>)> =
+            stream::iter(universe.into_iter().enumerate())
+                .map(|(index, coin)| async move {
+                    let outcome = self.evaluate(crypto_client, &coin, candles).await;
+                    (index, coin.symbol, outcome)
+                })
+                .buffer_unordered(concurrency.max(1))
+                .collect()
+                .await;
+        evaluated.sort_by_key(|(index, _, _)| *index);
+
+        let mut matches = vec![];
+        let mut errors = vec![];
+        for (_, coin, outcome) in evaluated {
+            match outcome {
+                Ok(Some(matched)) => matches.push(matched),
+                Ok(None) => {}
+                Err(err) => errors.push((coin, err)),
+            }
+        }
+        matches.sort_by(|a, b| self.compare(a, b));
+
+        ScreenResult { matches, errors }
+    }
+
+    async fn evaluate(
+        &self,
+        crypto_client: &CryptoClient,
+        coin: &UniverseCoin,
+        candles: &CandleRequest,
+    ) -> Result<Option<Match>, ApiError> {
+        let uuid = match &coin.uuid {
+            Some(uuid) => uuid.clone(),
+            None => crypto_client.get_coin_uuid(&coin.symbol).await?,
+        };
+        let candles = fetch_candles(crypto_client, &uuid, candles).await?;
+        require_data(self.candles_required(), candles.ohlc.len())?;
+        let chronological: Vec<ParsedOhlc> = candles.ohlc.iter().rev().cloned().collect();
+
+        // Long periods over many candles are costly, so they are kept off the async workers.
+        let screen = self.clone();
+        let symbol = coin.symbol.clone();
+        web::block(move || screen.check(&symbol, &chronological))
+            .await
+            .map_err(|err| ApiError::Internal(err.to_string()))?
+    }
+
+    /// Computes the values of a coin and whether its candles, oldest first, pass the filter.
+    fn check(&self, symbol: &str, chronological: &[ParsedOhlc]) -> Result<Option<Match>, ApiError> {
+        let series: BTreeMap<String, Vec<f64>> = self
+            .quantities
+            .iter()
+            .map(|(name, quantity)| (name.clone(), quantity.series(chronological)))
+            .collect();
+        let context = ScreenContext {
+            candles: CandleContext::new(chronological),
+            series: &series,
+        };
+        let matched = match self.filter.evaluate(&context) {
+            Ok(Series::Conditions(conditions)) => conditions.last() == Some(&Some(true)),
+            Ok(Series::Numbers(_)) => false,
+            Err(err) => return Err(ApiError::bad_parameter("filter", err.to_string())),
+        };
+
+        Ok(matched.then(|| Match {
+            coin: symbol.to_ascii_uppercase(),
+            values: series
+                .into_iter()
+                .map(|(name, series)| (name, series.last().copied().unwrap_or(f64::NAN)))
+                .collect(),
+        }))
+    }
+
+    /// Orders by each sort key in turn, NaN values last.
+    fn compare(&self, a: &Match, b: &Match) -> Ordering {
+        for key in &self.sort {
+            let (x, y) = (a.values[&key.key], b.values[&key.key]);
+            let ordering = match (x.is_nan(), y.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => match key.order {
+                    SortOrder::Asc => x.total_cmp(&y),
+                    SortOrder::Desc => y.total_cmp(&x),
+                },
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
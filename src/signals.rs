@@ -0,0 +1,363 @@
+//! Buy and sell events detected on candles: moving average crossovers, MACD and
+//! signal line crosses, RSI and stochastic entering or leaving the oversold and
+//! overbought zones, and closes breaking out of the Bollinger bands.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::crypto_client::{OhlcParams, ParsedOhlc};
+use crate::error::ApiError;
+use crate::indicators;
+
+#[derive(
+    serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalKind {
+    /// The fast SMA crossing the slow one.
+    MaCrossover,
+    /// The MACD line crossing its signal line.
+    MacdCross,
+    Rsi,
+    Stochastic,
+    /// The close crossing a Bollinger band.
+    BollingerBreakout,
+}
+
+impl SignalKind {
+    pub const ALL: [SignalKind; 5] = [
+        SignalKind::MaCrossover,
+        SignalKind::MacdCross,
+        SignalKind::Rsi,
+        SignalKind::Stochastic,
+        SignalKind::BollingerBreakout,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignalKind::MaCrossover => "ma_crossover",
+            SignalKind::MacdCross => "macd_cross",
+            SignalKind::Rsi => "rsi",
+            SignalKind::Stochastic => "stochastic",
+            SignalKind::BollingerBreakout => "bollinger_breakout",
+        }
+    }
+}
+
+impl FromStr for SignalKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SignalKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| {
+                let expected: Vec<&str> =
+                    SignalKind::ALL.iter().map(|kind| kind.as_str()).collect();
+                format!(
+                    "unknown signal `{}`, expected one of {}",
+                    s,
+                    expected.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+    /// An oscillator entering a zone, only worth acting on once it leaves it.
+    Neutral,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct Signal {
+    /// Start of the candle the signal fired on.
+    pub timestamp: i64,
+    pub kind: SignalKind,
+    pub side: Side,
+    /// What happened, e.g. `golden_cross` or `exit_oversold`.
+    pub trigger: &'static str,
+    /// The values that triggered the signal, on that candle.
+    pub values: BTreeMap<&'static str, f64>,
+}
+
+/// The periods and thresholds of every detector, named as their query parameters.
+#[derive(serde::Deserialize, Clone, Debug, PartialEq, utoipa::IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+pub struct SignalParams {
+    pub ma_fast: usize,
+    pub ma_slow: usize,
+    pub macd_fast: usize,
+    pub macd_slow: usize,
+    pub macd_signal: usize,
+    pub rsi_period: usize,
+    pub rsi_oversold: f64,
+    pub rsi_overbought: f64,
+    pub stochastic_period: usize,
+    pub stochastic_oversold: f64,
+    pub stochastic_overbought: f64,
+    pub bollinger_period: usize,
+    /// In standard deviations.
+    pub bollinger_width: f64,
+}
+
+impl Default for SignalParams {
+    fn default() -> Self {
+        Self {
+            ma_fast: 50,
+            ma_slow: 200,
+            macd_fast: 12,
+            macd_slow: 26,
+            macd_signal: 9,
+            rsi_period: 14,
+            rsi_oversold: 30.0,
+            rsi_overbought: 70.0,
+            stochastic_period: 14,
+            stochastic_oversold: 20.0,
+            stochastic_overbought: 80.0,
+            bollinger_period: 20,
+            bollinger_width: 2.0,
+        }
+    }
+}
+
+impl SignalParams {
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let max = OhlcParams::MAX_LIMIT as usize;
+        for (parameter, period) in [
+            ("ma_fast", self.ma_fast),
+            ("ma_slow", self.ma_slow),
+            ("macd_fast", self.macd_fast),
+            ("macd_slow", self.macd_slow),
+            ("macd_signal", self.macd_signal),
+            ("rsi_period", self.rsi_period),
+            ("stochastic_period", self.stochastic_period),
+            ("bollinger_period", self.bollinger_period),
+        ] {
+            if !(2..=max).contains(&period) {
+                return Err(ApiError::bad_parameter(
+                    parameter,
+                    format!("must be between 2 and {}", max),
+                ));
+            }
+        }
+        if self.ma_fast >= self.ma_slow {
+            return Err(ApiError::bad_parameter(
+                "ma_fast",
+                "must be shorter than `ma_slow`",
+            ));
+        }
+        if self.macd_fast >= self.macd_slow {
+            return Err(ApiError::bad_parameter(
+                "macd_fast",
+                "must be shorter than `macd_slow`",
+            ));
+        }
+        for (parameter, oversold, overbought) in [
+            ("rsi_oversold", self.rsi_oversold, self.rsi_overbought),
+            (
+                "stochastic_oversold",
+                self.stochastic_oversold,
+                self.stochastic_overbought,
+            ),
+        ] {
+            if !(0.0 < oversold && oversold < overbought && overbought < 100.0) {
+                return Err(ApiError::bad_parameter(
+                    parameter,
+                    "the thresholds must satisfy 0 < oversold < overbought < 100",
+                ));
+            }
+        }
+        if !(self.bollinger_width > 0.0 && self.bollinger_width.is_finite()) {
+            return Err(ApiError::bad_parameter(
+                "bollinger_width",
+                "must be positive",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The candles needed before a signal of `kind` can fire.
+    pub fn lookback(&self, kind: SignalKind) -> usize {
+        match kind {
+            SignalKind::MaCrossover => self.ma_slow,
+            SignalKind::MacdCross => self.macd_slow + self.macd_signal - 1,
+            SignalKind::Rsi => self.rsi_period,
+            SignalKind::Stochastic => self.stochastic_period,
+            SignalKind::BollingerBreakout => self.bollinger_period,
+        }
+    }
+}
+
+/// Every signal of the given kinds, `candles` and the signals being oldest first.
+pub fn detect(candles: &[ParsedOhlc], kinds: &[SignalKind], params: &SignalParams) -> Vec<Signal> {
+    let column =
+        |field: fn(&ParsedOhlc) -> f64| -> Vec<f64> { candles.iter().map(field).collect() };
+    let closes = column(|ohlc| ohlc.close);
+
+    let mut signals = vec![];
+    let mut push = |i: usize, kind, side, trigger, values: &[(&'static str, f64)]| {
+        signals.push(Signal {
+            timestamp: candles[i].starting_at,
+            kind,
+            side,
+            trigger,
+            values: values.iter().copied().collect(),
+        })
+    };
+    for kind in kinds {
+        match kind {
+            SignalKind::MaCrossover => {
+                let fast = indicators::sma(&closes, params.ma_fast);
+                let slow = indicators::sma(&closes, params.ma_slow);
+                for (i, side) in crossings(&fast, &slow) {
+                    let trigger = if side == Side::Buy {
+                        "golden_cross"
+                    } else {
+                        "death_cross"
+                    };
+                    push(
+                        i,
+                        *kind,
+                        side,
+                        trigger,
+                        &[("fast", fast[i]), ("slow", slow[i])],
+                    );
+                }
+            }
+            SignalKind::MacdCross => {
+                let macd = indicators::macd(
+                    &closes,
+                    params.macd_fast,
+                    params.macd_slow,
+                    params.macd_signal,
+                );
+                for (i, side) in crossings(&macd.macd, &macd.signal) {
+                    let trigger = if side == Side::Buy {
+                        "bullish_cross"
+                    } else {
+                        "bearish_cross"
+                    };
+                    let values = [
+                        ("macd", macd.macd[i]),
+                        ("signal", macd.signal[i]),
+                        ("histogram", macd.histogram[i]),
+                    ];
+                    push(i, *kind, side, trigger, &values);
+                }
+            }
+            SignalKind::Rsi | SignalKind::Stochastic => {
+                let (values, oversold, overbought) = if *kind == SignalKind::Rsi {
+                    (
+                        indicators::rsi(&closes, params.rsi_period),
+                        params.rsi_oversold,
+                        params.rsi_overbought,
+                    )
+                } else {
+                    (
+                        indicators::stochastic_oscillator(
+                            &column(|ohlc| ohlc.high),
+                            &column(|ohlc| ohlc.low),
+                            &closes,
+                            params.stochastic_period,
+                        ),
+                        params.stochastic_oversold,
+                        params.stochastic_overbought,
+                    )
+                };
+                let name = kind.as_str();
+                for (i, trigger, side, threshold) in thresholds(&values, oversold, overbought) {
+                    push(
+                        i,
+                        *kind,
+                        side,
+                        trigger,
+                        &[(name, values[i]), ("threshold", threshold)],
+                    );
+                }
+            }
+            SignalKind::BollingerBreakout => {
+                let bands = indicators::bollinger_bands(
+                    &closes,
+                    params.bollinger_period,
+                    params.bollinger_width,
+                );
+                let breakouts = crossings(&closes, &bands.upper)
+                    .into_iter()
+                    .filter(|(_, side)| *side == Side::Buy)
+                    .map(|(i, _)| (i, Side::Buy, "upper_breakout"))
+                    .chain(
+                        crossings(&closes, &bands.lower)
+                            .into_iter()
+                            .filter(|(_, side)| *side == Side::Sell)
+                            .map(|(i, _)| (i, Side::Sell, "lower_breakout")),
+                    );
+                for (i, side, trigger) in breakouts {
+                    let values = [
+                        ("close", closes[i]),
+                        ("upper", bands.upper[i]),
+                        ("middle", bands.middle[i]),
+                        ("lower", bands.lower[i]),
+                    ];
+                    push(i, *kind, side, trigger, &values);
+                }
+            }
+        }
+    }
+    signals.sort_by_key(|signal| (signal.timestamp, signal.kind));
+    signals
+}
+
+/// Where `a` crosses above `b` (a buy) or below it (a sell), skipping the points
+/// where either is undefined.
+fn crossings(a: &[f64], b: &[f64]) -> Vec<(usize, Side)> {
+    (1..a.len())
+        .filter(|i| {
+            ![a[i - 1], a[*i], b[i - 1], b[*i]]
+                .iter()
+                .any(|x| x.is_nan())
+        })
+        .filter_map(|i| {
+            if a[i - 1] <= b[i - 1] && a[i] > b[i] {
+                Some((i, Side::Buy))
+            } else if a[i - 1] >= b[i - 1] && a[i] < b[i] {
+                Some((i, Side::Sell))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Where an oscillator enters or leaves the oversold and the overbought zones, with
+/// the threshold crossed. Leaving the oversold zone is a buy and leaving the
+/// overbought one a sell, entering either being neutral.
+fn thresholds(
+    values: &[f64],
+    oversold: f64,
+    overbought: f64,
+) -> Vec<(usize, &'static str, Side, f64)> {
+    let oversold_line = vec![oversold; values.len()];
+    let overbought_line = vec![overbought; values.len()];
+    let mut events: Vec<(usize, &'static str, Side, f64)> = crossings(values, &oversold_line)
+        .into_iter()
+        .map(|(i, side)| match side {
+            Side::Buy => (i, "exit_oversold", Side::Buy, oversold),
+            _ => (i, "enter_oversold", Side::Neutral, oversold),
+        })
+        .chain(
+            crossings(values, &overbought_line)
+                .into_iter()
+                .map(|(i, side)| match side {
+                    Side::Sell => (i, "exit_overbought", Side::Sell, overbought),
+                    _ => (i, "enter_overbought", Side::Neutral, overbought),
+                }),
+        )
+        .collect();
+    events.sort_by_key(|(i, _, _, _)| *i);
+    events
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Deduplicates concurrent calls sharing the same key: the first caller runs the
+/// work, everyone arriving while it is in flight waits for and receives a clone
+/// of its result.
+///
+/// If the leading caller is cancelled (e.g. the client hung up), one of the
+/// waiting callers takes over and runs the work itself.
+pub struct SingleFlight<K, V> {
+    in_flight: Mutex<HashMap<K, broadcast::Sender<V>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn run<F, Fut>(&self, key: K, work: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let mut work = Some(work);
+        loop {
+            let mut receiver = {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                match in_flight.get(&key) {
+                    Some(sender) => sender.subscribe(),
+                    None => {
+                        let (sender, _) = broadcast::channel(1);
+                        in_flight.insert(key.clone(), sender);
+                        break;
+                    }
+                }
+            };
+
+            match receiver.recv().await {
+                Ok(value) => return value,
+                // The leader was dropped before finishing, try to become the new one.
+                Err(_) => continue,
+            }
+        }
+
+        let guard = InFlightGuard {
+            flights: self,
+            key: Some(key),
+        };
+        let work = work.take().expect("the work is only run by the leader");
+        let value = work().await;
+        guard.complete(value.clone());
+        value
+    }
+}
+
+/// Removes the in-flight marker even when the leader's future is dropped midway,
+/// which wakes the waiting callers up with an error.
+struct InFlightGuard<'a, K, V>
+where
+    K: Eq + Hash,
+{
+    flights: &'a SingleFlight<K, V>,
+    key: Option<K>,
+}
+
+impl<K, V> InFlightGuard<'_, K, V>
+where
+    K: Eq + Hash,
+{
+    fn complete(mut self, value: V) {
+        if let Some(key) = self.key.take() {
+            // Sending while holding the lock guarantees nobody subscribes after the send.
+            let mut in_flight = self.flights.in_flight.lock().unwrap();
+            if let Some(sender) = in_flight.remove(&key) {
+                // Nobody waiting is not an error.
+                let _ = sender.send(value);
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for InFlightGuard<'_, K, V>
+where
+    K: Eq + Hash,
+{
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            if let Ok(mut in_flight) = self.flights.in_flight.lock() {
+                in_flight.remove(&key);
+            }
+        }
+    }
+}
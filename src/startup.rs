@@ -1,27 +1,68 @@
-use crate::configuration::get_configuration;
+use crate::alerts::Alerts;
+use crate::auth::{authenticate, ApiKeys};
+use crate::configuration::Settings;
 use crate::crypto_client::CryptoClient;
+use crate::error::{extractor_error, not_found};
+use crate::live::LiveHub;
+use crate::metrics::{track, Metrics};
+use crate::openapi::ApiDoc;
+use crate::optimize::Workers;
+use crate::precompute::Precomputer;
+use crate::readiness::Readiness;
 use crate::routes::fibonacci_retracement::fibonacci_extension;
 use crate::routes::{
-    aroon_oscillator, exponential_moving_average, fibonacci_retracement, health_check, rsi,
+    alert_deliveries, aroon_oscillator, backtest, batch, cache_stats, create_alert, delete_alert,
+    divergences, docs, evaluate, events, exponential_moving_average, fibonacci_retracement,
+    get_alert, health_check, list_alerts, live, metrics, multi_timeframe, openapi_json, optimize,
+    purge_cache, purge_cache_kind, readiness, replace_alert, rsi, screener, signals,
     simple_moving_average, stochastic_oscillator,
 };
-use actix_files::NamedFile;
+use crate::telemetry::trace_request;
 use actix_web::dev::Server;
+use actix_web::middleware::from_fn;
 use actix_web::web::Data;
-use actix_web::{web, App, HttpRequest, HttpServer};
+use actix_web::{web, App, HttpServer};
+use utoipa::OpenApi;
 
 use std::net::TcpListener;
-use std::path::PathBuf;
+use std::sync::Arc;
 
-pub fn run(listener: TcpListener) -> Result<Server, std::io::Error> {
-    let configuration = get_configuration().expect("Failed to read configuration.");
+pub fn run(listener: TcpListener, configuration: Settings) -> Result<Server, std::io::Error> {
+    let api_keys = Data::new(
+        ApiKeys::new(&configuration.auth)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?,
+    );
+    let checks = Data::new(Readiness::new(&configuration));
+    let openapi = Data::new(ApiDoc::openapi());
+    let registry = Arc::new(Metrics::new());
     let crypto_client = Data::new(CryptoClient::new(
-        configuration.crypto_client.base_url,
-        configuration.crypto_client.auth_token,
+        configuration.crypto_client,
+        registry.clone(),
+    ));
+    let registry = Data::from(registry);
+    let workers = Data::new(Workers::new(configuration.optimize.threads));
+    let live_hub = Data::new(LiveHub::new(
+        configuration.live.poll_interval(),
+        configuration.live.max_subscriptions,
+    ));
+    tokio::spawn(LiveHub::run_poller(live_hub.clone(), crypto_client.clone()));
+    let alerts = Data::new(Alerts::new(configuration.alerts));
+    tokio::spawn(Alerts::run_scheduler(alerts.clone(), crypto_client.clone()));
+    let precomputer = Data::new(Precomputer::new(configuration.precompute));
+    tokio::spawn(Precomputer::run_scheduler(
+        precomputer.clone(),
+        crypto_client.clone(),
     ));
     let server = HttpServer::new(move || {
         App::new()
             .route("/health_check", web::get().to(health_check))
+            .route("/openapi.json", web::get().to(openapi_json))
+            .route("/docs", web::get().to(docs))
+            .route("/readiness", web::get().to(readiness))
+            .route("/metrics", web::get().to(metrics))
+            .route("/admin/cache", web::get().to(cache_stats))
+            .route("/admin/cache", web::delete().to(purge_cache))
+            .route("/admin/cache/{kind}", web::delete().to(purge_cache_kind))
             .route(
                 "/simple_moving_average/{coin}/{time}",
                 web::get().to(simple_moving_average),
@@ -44,24 +85,49 @@ pub fn run(listener: TcpListener) -> Result<Server, std::io::Error> {
                 "/exponential_moving_average/{coin}",
                 web::get().to(exponential_moving_average),
             )
-            /* .route("/docs", web::get().to(docs))
-            .route("/json", web::get().to(json_get)) */
+            .route(
+                "/multi_timeframe/{coin}/{indicator}",
+                web::get().to(multi_timeframe),
+            )
+            .route("/batch", web::post().to(batch))
+            .route("/screener", web::post().to(screener))
+            .route("/evaluate/{coin}", web::get().to(evaluate))
+            .route("/signals/{coin}", web::get().to(signals))
+            .route("/divergences/{coin}", web::get().to(divergences))
+            .route("/backtest", web::post().to(backtest))
+            .route("/optimize", web::post().to(optimize))
+            .route("/live", web::get().to(live))
+            .route("/events/{coin}", web::get().to(events))
+            .route("/alerts", web::post().to(create_alert))
+            .route("/alerts", web::get().to(list_alerts))
+            .route("/alerts/{id}", web::get().to(get_alert))
+            .route("/alerts/{id}", web::put().to(replace_alert))
+            .route("/alerts/{id}", web::delete().to(delete_alert))
+            .route("/alerts/{id}/deliveries", web::get().to(alert_deliveries))
+            .default_service(web::route().to(not_found))
+            .wrap(from_fn(authenticate))
+            .wrap(from_fn(track))
+            .wrap(from_fn(trace_request))
+            .app_data(api_keys.to_owned())
+            .app_data(openapi.to_owned())
+            .app_data(checks.to_owned())
+            .app_data(registry.to_owned())
             .app_data(crypto_client.to_owned())
+            .app_data(workers.to_owned())
+            .app_data(live_hub.to_owned())
+            .app_data(alerts.to_owned())
+            .app_data(precomputer.to_owned())
+            .app_data(
+                web::PathConfig::default().error_handler(|err, _| extractor_error("path", err)),
+            )
+            .app_data(
+                web::QueryConfig::default().error_handler(|err, _| extractor_error("query", err)),
+            )
+            .app_data(
+                web::JsonConfig::default().error_handler(|err, _| extractor_error("body", err)),
+            )
     })
     .listen(listener)?
     .run();
     Ok(server)
 }
-
-/* async fn docs(_req: HttpRequest) -> Result<NamedFile, std::io::Error> {
-    let base_path = std::env::current_dir().expect("Failed to determine the current directory");
-    let html_path = base_path.join("configuration/static/index.html");
-    Ok(NamedFile::open(html_path)?)
-}
-
-async fn json_get(_req: HttpRequest) -> Result<NamedFile, std::io::Error> {
-    let base_path = std::env::current_dir().expect("Failed to determine the current directory");
-    let json_path = base_path.join("configuration/static/openapi.json");
-    Ok(NamedFile::open(json_path)?)
-}
- */
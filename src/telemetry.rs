@@ -0,0 +1,102 @@
+//! Structured logging with `tracing`, every request being logged in a span carrying
+//! its ID.
+//!
+//! The ID comes from the `X-Request-Id` header of the request when it is a sensible
+//! one, or is generated. It is echoed in the same header of the response, and in
+//! the `requestId` field of error bodies, so that a client reporting an error can
+//! point at its logs.
+
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use tracing::field::Empty;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+use crate::configuration::{LogFormat, LoggingSettings};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Installs the global subscriber, `RUST_LOG` overriding the configured level.
+/// Fails if one is already installed.
+pub fn init(settings: &LoggingSettings) -> Result<(), String> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&settings.level))
+        .map_err(|err| format!("Invalid log level `{}`: {}", settings.level, err))?;
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let installed = match settings.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+    installed.map_err(|err| err.to_string())
+}
+
+/// The ID of the request being handled, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Printable ASCII without spaces, so that the ID can't garble a log line.
+fn valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Logs every request in a span with its ID, method, route and status.
+pub async fn trace_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| valid_request_id(id))
+        .map(str::to_owned)
+        .unwrap_or_else(|| hex::encode(rand::random::<[u8; 16]>()));
+    // The path only: the query may hold an API key.
+    let span = tracing::info_span!(
+        "http_request",
+        request_id = %request_id,
+        method = %req.method(),
+        route = req.match_pattern().as_deref().unwrap_or("unmatched"),
+        path = req.path(),
+        status = Empty,
+        duration_ms = Empty,
+    );
+    let started = Instant::now();
+
+    let response = REQUEST_ID
+        .scope(request_id.clone(), next.call(req).instrument(span.clone()))
+        .await;
+
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    span.record("status", status.as_u16());
+    span.record("duration_ms", started.elapsed().as_millis() as u64);
+    span.in_scope(|| {
+        if status.is_server_error() {
+            tracing::error!("request failed");
+        } else {
+            tracing::info!("request handled");
+        }
+    });
+
+    let mut response = response?;
+    response.headers_mut().insert(
+        HeaderName::from_static("x-request-id"),
+        HeaderValue::from_str(&request_id).expect("A valid request ID is a valid header value"),
+    );
+    Ok(response)
+}
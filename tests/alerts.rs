@@ -0,0 +1,340 @@
+mod helpers;
+
+use std::time::Duration;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, spawn_app_with, TestApp, BTC_UUID};
+use serde_json::json;
+use taindicators::alerts::{sign, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, Request, ResponseTemplate};
+
+const SECRET: &str = "a-long-enough-secret";
+
+impl TestApp {
+    async fn send_rule(
+        &self,
+        method: reqwest::Method,
+        route: &str,
+        rule: serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .request(method, format!("{}{}", self.address, route))
+            .json(&rule)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    async fn create_rule(&self, rule: serde_json::Value) -> serde_json::Value {
+        let response = self.send_rule(reqwest::Method::POST, "/alerts", rule).await;
+        assert_eq!(201, response.status().as_u16());
+        response.json::<serde_json::Value>().await.unwrap()["data"].clone()
+    }
+
+    /// The deliveries of a rule once `done` holds for them, failing after a few seconds.
+    async fn deliveries_once(
+        &self,
+        id: &serde_json::Value,
+        done: impl Fn(&[serde_json::Value]) -> bool,
+    ) -> Vec<serde_json::Value> {
+        for _ in 0..100 {
+            let response = self.get(&format!("/alerts/{}/deliveries", id)).await;
+            let body: serde_json::Value = response.json().await.unwrap();
+            let deliveries = body["data"].as_array().unwrap().clone();
+            if done(&deliveries) {
+                return deliveries;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("The deliveries never got there.");
+    }
+}
+
+/// A rule on the EMA of BTC, which always holds for the fixture candles closing around 100.
+fn rule(webhook_url: &str) -> serde_json::Value {
+    json!({
+        "coin": "btc",
+        "indicator": "ema",
+        "period": 5,
+        "interval": "hour",
+        "condition": { "operator": "above", "threshold": 50 },
+        "webhookUrl": webhook_url,
+        "secret": SECRET,
+    })
+}
+
+fn header(request: &Request, name: &str) -> String {
+    let (_, values) = request
+        .headers
+        .iter()
+        .find(|(header, _)| header.as_str().eq_ignore_ascii_case(name))
+        .expect("Missing header.");
+    values.last().as_str().to_owned()
+}
+
+async fn spawn_evaluating_app() -> TestApp {
+    let app = spawn_app_with(|settings| {
+        settings.alerts.evaluation_interval_milliseconds = 50;
+        settings.alerts.initial_backoff_milliseconds = 10;
+        // The webhooks are served by the mock server, on localhost.
+        settings.alerts.allow_private_webhooks = true;
+    })
+    .await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    app
+}
+
+#[tokio::test]
+async fn rules_can_be_created_read_replaced_and_deleted() {
+    let app = spawn_app().await;
+
+    let mut request = rule("https://example.com/hook");
+    request.as_object_mut().unwrap().remove("secret");
+    let created = app.create_rule(request).await;
+    let id = created["id"].clone();
+    assert_eq!("BTC", created["coin"]);
+    assert_eq!(5, created["period"]);
+    assert_eq!(
+        json!({ "operator": "above", "threshold": 50.0 }),
+        created["condition"]
+    );
+    assert_eq!(64, created["secret"].as_str().unwrap().len());
+
+    let fetched: serde_json::Value = app
+        .get(&format!("/alerts/{}", id))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(id, fetched["data"]["id"]);
+    assert!(fetched["data"].get("secret").is_none());
+
+    let mut replacement = rule("https://example.com/other");
+    replacement["cooldownSeconds"] = json!(60);
+    let response = app
+        .send_rule(
+            reqwest::Method::PUT,
+            &format!("/alerts/{}", id),
+            replacement,
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let listed: serde_json::Value = app.get("/alerts").await.json().await.unwrap();
+    assert_eq!(1, listed["data"].as_array().unwrap().len());
+    assert_eq!("https://example.com/other", listed["data"][0]["webhookUrl"]);
+    assert_eq!(60, listed["data"][0]["cooldownSeconds"]);
+
+    assert_eq!(
+        204,
+        app.delete(&format!("/alerts/{}", id))
+            .await
+            .status()
+            .as_u16()
+    );
+    assert_eq!(
+        404,
+        app.get(&format!("/alerts/{}", id)).await.status().as_u16()
+    );
+    assert_eq!(
+        404,
+        app.delete(&format!("/alerts/{}", id))
+            .await
+            .status()
+            .as_u16()
+    );
+}
+
+#[tokio::test]
+async fn invalid_rules_are_rejected() {
+    let app = spawn_app().await;
+
+    for (field, value, parameter) in [
+        ("webhookUrl", json!("ftp://example.com/hook"), "webhookUrl"),
+        ("webhookUrl", json!("not a url"), "webhookUrl"),
+        (
+            "webhookUrl",
+            json!("http://127.0.0.1:8080/hook"),
+            "webhookUrl",
+        ),
+        ("webhookUrl", json!("http://localhost/hook"), "webhookUrl"),
+        ("webhookUrl", json!("http://10.1.2.3/hook"), "webhookUrl"),
+        (
+            "webhookUrl",
+            json!("http://169.254.169.254/latest/meta-data"),
+            "webhookUrl",
+        ),
+        ("webhookUrl", json!("http://[::1]/hook"), "webhookUrl"),
+        (
+            "webhookUrl",
+            json!("http://[::ffff:192.168.0.1]/hook"),
+            "webhookUrl",
+        ),
+        // IPv4-compatible, NAT64 and 6to4 addresses of loopback and private ones.
+        (
+            "webhookUrl",
+            json!("http://[::127.0.0.1]/hook"),
+            "webhookUrl",
+        ),
+        (
+            "webhookUrl",
+            json!("http://[64:ff9b::7f00:1]/hook"),
+            "webhookUrl",
+        ),
+        (
+            "webhookUrl",
+            json!("http://[2002:a00:1::]/hook"),
+            "webhookUrl",
+        ),
+        ("secret", json!("short"), "secret"),
+        ("period", json!(1), "period"),
+        ("coin", json!(" "), "coin"),
+    ] {
+        let mut request = rule("https://example.com/hook");
+        request[field] = value;
+
+        let response = app
+            .send_rule(reqwest::Method::POST, "/alerts", request)
+            .await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", field);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", field);
+    }
+
+    // The fields are checked before the webhook is resolved.
+    let mut request = rule("http://localhost/hook");
+    request["secret"] = json!("short");
+    let response = app
+        .send_rule(reqwest::Method::POST, "/alerts", request)
+        .await;
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("secret", error["details"]["parameter"]);
+}
+
+#[tokio::test]
+async fn a_firing_rule_posts_a_signed_payload_once_per_cooldown() {
+    let app = spawn_evaluating_app().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.upstream)
+        .await;
+
+    let created = app
+        .create_rule(rule(&format!("{}/hook", app.upstream.uri())))
+        .await;
+    let deliveries = app
+        .deliveries_once(&created["id"], |deliveries| {
+            deliveries
+                .first()
+                .is_some_and(|delivery| delivery["status"] == "delivered")
+        })
+        .await;
+    // Several evaluations later, the rule is still cooling down.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let hooks: Vec<_> = app
+        .upstream
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path() == "/hook")
+        .collect();
+    assert_eq!(1, hooks.len());
+    assert_eq!(1, deliveries[0]["attempts"].as_array().unwrap().len());
+    let hook = &hooks[0];
+    let body = std::str::from_utf8(&hook.body).unwrap();
+    let timestamp: i64 = header(hook, TIMESTAMP_HEADER).parse().unwrap();
+    assert_eq!(
+        format!("sha256={}", sign(SECRET, timestamp, body)),
+        header(hook, SIGNATURE_HEADER)
+    );
+    let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(created["id"], payload["ruleId"]);
+    assert_eq!("BTC", payload["coin"]);
+    assert_eq!(100.0, payload["close"]);
+    assert_eq!(deliveries[0]["payload"], payload);
+}
+
+#[tokio::test]
+async fn a_failing_webhook_is_retried() {
+    let app = spawn_evaluating_app().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&app.upstream)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.upstream)
+        .await;
+
+    let created = app
+        .create_rule(rule(&format!("{}/hook", app.upstream.uri())))
+        .await;
+    let deliveries = app
+        .deliveries_once(&created["id"], |deliveries| {
+            deliveries
+                .first()
+                .is_some_and(|delivery| delivery["status"] == "delivered")
+        })
+        .await;
+
+    let attempts = deliveries[0]["attempts"].as_array().unwrap();
+    assert_eq!(2, attempts.len());
+    assert_eq!(503, attempts[0]["statusCode"]);
+    assert_eq!(200, attempts[1]["statusCode"]);
+}
+
+#[tokio::test]
+async fn a_webhook_rejecting_the_payload_is_not_retried() {
+    let app = spawn_evaluating_app().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&app.upstream)
+        .await;
+
+    let created = app
+        .create_rule(rule(&format!("{}/hook", app.upstream.uri())))
+        .await;
+    let deliveries = app
+        .deliveries_once(&created["id"], |deliveries| {
+            deliveries
+                .first()
+                .is_some_and(|delivery| delivery["status"] == "failed")
+        })
+        .await;
+
+    assert_eq!(1, deliveries[0]["attempts"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn redirects_are_not_followed() {
+    let app = spawn_evaluating_app().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(
+            ResponseTemplate::new(307).insert_header("Location", "http://169.254.169.254/"),
+        )
+        .mount(&app.upstream)
+        .await;
+
+    let created = app
+        .create_rule(rule(&format!("{}/hook", app.upstream.uri())))
+        .await;
+    let deliveries = app
+        .deliveries_once(&created["id"], |deliveries| {
+            deliveries
+                .first()
+                .is_some_and(|delivery| delivery["status"] == "failed")
+        })
+        .await;
+
+    let attempts = deliveries[0]["attempts"].as_array().unwrap();
+    assert!(attempts.iter().all(|attempt| attempt["statusCode"] == 307));
+}
@@ -0,0 +1,237 @@
+mod helpers;
+
+use helpers::{spawn_app_with, TestApp};
+use serde_json::json;
+use taindicators::auth::{hash_key, ApiKeys};
+use taindicators::configuration::{ApiKeySettings, AuthSettings, Scope, Settings};
+
+const KEY: &str = "a-test-api-key";
+
+fn key(id: &str, key: &str) -> ApiKeySettings {
+    ApiKeySettings {
+        id: id.to_owned(),
+        secret_hash: hash_key(key),
+        requests_per_second: None,
+        burst: None,
+        daily_quota: None,
+        scopes: vec![],
+    }
+}
+
+async fn spawn_protected_app(customise: impl FnOnce(&mut Settings)) -> TestApp {
+    spawn_app_with(|settings| {
+        settings.auth.enabled = true;
+        settings.auth.keys = vec![key("tests", KEY)];
+        customise(settings);
+    })
+    .await
+}
+
+impl TestApp {
+    async fn get_with_key(&self, route: &str, key: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", self.address, route))
+            .header("X-API-Key", key)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    async fn send_with_key(
+        &self,
+        method: reqwest::Method,
+        route: &str,
+        key: &str,
+        body: Option<serde_json::Value>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .api_client
+            .request(method, format!("{}{}", self.address, route))
+            .header("X-API-Key", key);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+}
+
+fn header(response: &reqwest::Response, name: &str) -> String {
+    response.headers()[name].to_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn requests_without_a_valid_key_are_rejected() {
+    let app = spawn_protected_app(|_| {}).await;
+
+    for response in [
+        app.get("/alerts").await,
+        app.get_with_key("/alerts", "not-the-key").await,
+        app.get("/alerts?api_key=not-the-key").await,
+    ] {
+        assert_eq!(401, response.status().as_u16());
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!("unauthorized", error["code"]);
+    }
+    assert_eq!(200, app.get("/health_check").await.status().as_u16());
+    assert_eq!(200, app.get("/metrics").await.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_key_is_accepted_in_a_header_or_the_query() {
+    let app = spawn_protected_app(|settings| {
+        settings.auth.burst = 10;
+        settings.auth.daily_quota = Some(100);
+    })
+    .await;
+
+    let response = app.get_with_key("/alerts", KEY).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("10", header(&response, "X-RateLimit-Limit"));
+    assert_eq!("9", header(&response, "X-RateLimit-Remaining"));
+    assert_eq!("100", header(&response, "X-Quota-Limit"));
+    assert_eq!("99", header(&response, "X-Quota-Remaining"));
+
+    let response = app.get(&format!("/alerts?api_key={}", KEY)).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("98", header(&response, "X-Quota-Remaining"));
+}
+
+#[tokio::test]
+async fn a_burst_beyond_the_bucket_is_rate_limited() {
+    let app = spawn_protected_app(|settings| {
+        settings.auth.keys[0].burst = Some(2);
+        settings.auth.keys[0].requests_per_second = Some(0.1);
+    })
+    .await;
+
+    assert_eq!(
+        200,
+        app.get_with_key("/alerts", KEY).await.status().as_u16()
+    );
+    assert_eq!(
+        200,
+        app.get_with_key("/alerts", KEY).await.status().as_u16()
+    );
+    let response = app.get_with_key("/alerts", KEY).await;
+
+    assert_eq!(429, response.status().as_u16());
+    let retry_after: u64 = header(&response, "Retry-After").parse().unwrap();
+    assert!((9..=10).contains(&retry_after));
+    assert_eq!("0", header(&response, "X-RateLimit-Remaining"));
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("rate_limited", error["code"]);
+}
+
+#[tokio::test]
+async fn a_used_up_daily_quota_is_reported() {
+    let app = spawn_protected_app(|settings| {
+        settings.auth.keys[0].daily_quota = Some(2);
+    })
+    .await;
+
+    app.get_with_key("/alerts", KEY).await;
+    let response = app.get_with_key("/alerts", KEY).await;
+    assert_eq!("0", header(&response, "X-Quota-Remaining"));
+    let response = app.get_with_key("/alerts", KEY).await;
+
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!("0", header(&response, "X-Quota-Remaining"));
+    let retry_after: u64 = header(&response, "Retry-After").parse().unwrap();
+    assert!((1..=86_400).contains(&retry_after));
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("quota_exceeded", error["code"]);
+}
+
+#[tokio::test]
+async fn keys_are_also_read_from_a_file() {
+    let path = std::env::temp_dir().join(format!("api-keys-{}.yaml", rand::random::<u64>()));
+    std::fs::write(
+        &path,
+        format!(
+            "keys:\n  - id: from-file\n    secret_hash: {}\n",
+            hash_key("another-key")
+        ),
+    )
+    .unwrap();
+    let keys_file = path.to_str().unwrap().to_owned();
+    let app = spawn_protected_app(|settings| settings.auth.keys_file = Some(keys_file)).await;
+    std::fs::remove_file(&path).unwrap();
+
+    for key in [KEY, "another-key"] {
+        let response = app.get_with_key("/alerts", key).await;
+        assert_eq!(200, response.status().as_u16(), "{}", key);
+    }
+}
+
+#[test]
+fn keys_are_required_by_default() {
+    assert!(AuthSettings::default().enabled);
+    assert!(ApiKeys::new(&AuthSettings::default()).is_err());
+}
+
+#[tokio::test]
+async fn the_admin_routes_need_the_admin_scope() {
+    let app = spawn_protected_app(|settings| {
+        let mut admin = key("admin", "an-admin-key");
+        admin.scopes = vec![Scope::Admin];
+        settings.auth.keys.push(admin);
+    })
+    .await;
+
+    for method in [reqwest::Method::GET, reqwest::Method::DELETE] {
+        let response = app
+            .send_with_key(method.clone(), "/admin/cache", KEY, None)
+            .await;
+        assert_eq!(403, response.status().as_u16(), "{}", method);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!("forbidden", error["code"]);
+
+        let response = app
+            .send_with_key(method.clone(), "/admin/cache", "an-admin-key", None)
+            .await;
+        assert!(response.status().is_success(), "{}", method);
+    }
+}
+
+#[tokio::test]
+async fn alert_rules_are_only_seen_by_the_key_that_created_them() {
+    let app = spawn_protected_app(|settings| {
+        settings.auth.keys.push(key("other", "another-key"));
+    })
+    .await;
+    let rule = json!({
+        "coin": "btc",
+        "indicator": "rsi",
+        "condition": { "operator": "above", "threshold": 70 },
+        "webhookUrl": "https://example.com/hook",
+    });
+    let response = app
+        .send_with_key(reqwest::Method::POST, "/alerts", KEY, Some(rule.clone()))
+        .await;
+    assert_eq!(201, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    let route = format!("/alerts/{}", created["data"]["id"]);
+
+    let listed: serde_json::Value = app
+        .get_with_key("/alerts", "another-key")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(json!([]), listed["data"]);
+    for (method, route, body) in [
+        (reqwest::Method::GET, route.clone(), None),
+        (reqwest::Method::PUT, route.clone(), Some(rule)),
+        (reqwest::Method::DELETE, route.clone(), None),
+        (reqwest::Method::GET, format!("{}/deliveries", route), None),
+    ] {
+        let response = app
+            .send_with_key(method.clone(), &route, "another-key", body)
+            .await;
+        assert_eq!(404, response.status().as_u16(), "{} {}", method, route);
+    }
+
+    let listed: serde_json::Value = app.get_with_key("/alerts", KEY).await.json().await.unwrap();
+    assert_eq!(1, listed["data"].as_array().unwrap().len());
+    assert_eq!(200, app.get_with_key(&route, KEY).await.status().as_u16());
+}
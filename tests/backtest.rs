@@ -0,0 +1,211 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, TestApp, BTC_UUID};
+use serde_json::json;
+
+impl TestApp {
+    async fn backtest(&self, body: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/backtest", self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+/// Daily candles opening and closing at the given prices, oldest first.
+fn candles(closes: &[f64]) -> serde_json::Value {
+    closes
+        .iter()
+        .enumerate()
+        .map(|(i, close)| {
+            json!({
+                "startingAt": i * 86400, "endingAt": (i + 1) * 86400,
+                "open": close, "high": close, "low": close, "close": close, "avg": close
+            })
+        })
+        .collect()
+}
+
+fn assert_close(expected: f64, actual: &serde_json::Value) {
+    let actual = actual.as_f64().unwrap();
+    assert!(
+        (expected - actual).abs() < 1e-6,
+        "{} != {}",
+        expected,
+        actual
+    );
+}
+
+#[tokio::test]
+async fn rules_are_filled_at_the_next_open() {
+    let app = spawn_app().await;
+
+    let response = app
+        .backtest(json!({
+            "candles": candles(&[10.0, 11.0, 12.0, 11.0, 10.0, 12.0]),
+            "entry": "close > close[1]",
+            "exit": "close < close[1]",
+            "fee": 0.0,
+            "slippage": 0.0
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let trades = body["data"]["trades"].as_array().unwrap();
+    assert_eq!(1, trades.len());
+    // Entered on the open after the first rise, left on the open after the first fall.
+    assert_eq!(2 * 86400, trades[0]["entryTime"]);
+    assert_eq!(12.0, trades[0]["entryPrice"]);
+    assert_eq!(4 * 86400, trades[0]["exitTime"]);
+    assert_eq!(10.0, trades[0]["exitPrice"]);
+    assert_eq!("signal", trades[0]["exitReason"]);
+    assert_close(-1.0 / 6.0, &trades[0]["return"]);
+
+    let equity: Vec<f64> = body["data"]["equity"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|point| point["equity"].as_f64().unwrap())
+        .collect();
+    assert_eq!(6, equity.len());
+    assert_eq!(10_000.0, equity[2]);
+    assert!((equity[5] - 10_000.0 * 10.0 / 12.0).abs() < 1e-6);
+
+    let metrics = &body["data"]["metrics"];
+    assert_close(-1.0 / 6.0, &metrics["totalReturn"]);
+    assert_close(1.0 / 6.0, &metrics["maxDrawdown"]);
+    assert_eq!(0.0, metrics["winRate"]);
+    assert!(metrics["sharpe"].as_f64().unwrap() < 0.0);
+    assert!(metrics["sortino"].as_f64().unwrap() < 0.0);
+}
+
+#[tokio::test]
+async fn fees_and_slippage_are_paid_on_both_fills() {
+    let app = spawn_app().await;
+
+    let body: serde_json::Value = app
+        .backtest(json!({
+            "candles": candles(&[100.0, 100.0, 110.0]),
+            "entry": "close > 0",
+            "exit": "close < 0",
+            "fee": 0.01,
+            "slippage": 0.001,
+            "initialCapital": 1000.0
+        }))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let trade = &body["data"]["trades"][0];
+    assert_close(100.1, &trade["entryPrice"]);
+    assert_close(110.0 * 0.999, &trade["exitPrice"]);
+    assert_eq!("end_of_data", trade["exitReason"]);
+    let quantity = 1000.0 / (100.1 * 1.01);
+    let proceeds = quantity * 110.0 * 0.999 * 0.99;
+    assert_close(proceeds - 1000.0, &trade["pnl"]);
+    assert_close(
+        1000.0 - quantity * 100.1 + quantity * 110.0 * 0.999 * 0.01,
+        &trade["fees"],
+    );
+    assert_close(proceeds, &body["data"]["metrics"]["finalEquity"]);
+    assert_eq!(1.0, body["data"]["metrics"]["winRate"]);
+}
+
+#[tokio::test]
+async fn a_coin_is_replayed_on_its_fetched_candles() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    let response = app
+        .backtest(json!({
+            "coin": "btc",
+            "entry": "crosses_above(ema(close, 3), sma(close, 10))",
+            "exit": "crosses_below(ema(close, 3), sma(close, 10))",
+            "params": { "interval": "hour", "limit": 60 }
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(60, body["data"]["equity"].as_array().unwrap().len());
+    assert_eq!(60, body["meta"]["bars"]);
+    assert_eq!("hour", body["meta"]["interval"]);
+    assert_eq!(0.001, body["meta"]["fee"]);
+    let metrics = &body["data"]["metrics"];
+    assert_eq!(
+        body["data"]["trades"].as_array().unwrap().len(),
+        metrics["trades"].as_u64().unwrap() as usize
+    );
+    assert!(metrics["maxDrawdown"].as_f64().unwrap() >= 0.0);
+}
+
+#[tokio::test]
+async fn invalid_strategies_are_rejected() {
+    let app = spawn_app().await;
+    let few = candles(&[1.0, 2.0, 3.0]);
+
+    for (body, parameter) in [
+        (
+            json!({ "candles": few, "entry": "close", "exit": "close < 1" }),
+            "entry",
+        ),
+        (
+            json!({ "candles": few, "entry": "close > 1", "exit": "closing < 1" }),
+            "exit",
+        ),
+        (
+            json!({ "candles": few, "entry": "close > 1", "exit": "close < 1", "fee": 0.6 }),
+            "fee",
+        ),
+        (
+            json!({ "coin": "btc", "candles": few, "entry": "close > 1", "exit": "close < 1" }),
+            "coin",
+        ),
+        (
+            json!({ "candles": few, "entry": "close > 1", "exit": "close < 1", "initialCapital": 0 }),
+            "initialCapital",
+        ),
+        (
+            json!({ "candles": candles(&[1.0, 0.0, 3.0]), "entry": "close > 1", "exit": "close < 1" }),
+            "candles",
+        ),
+        (
+            json!({ "candles": candles(&[1.0, -2.0, 3.0]), "entry": "close > 1", "exit": "close < 1" }),
+            "candles",
+        ),
+        (
+            json!({ "candles": [{
+                "startingAt": 0, "endingAt": 86400,
+                "open": 2.0, "high": 1.0, "low": 3.0, "close": 2.0, "avg": 2.0
+            }], "entry": "close > 1", "exit": "close < 1" }),
+            "candles",
+        ),
+    ] {
+        let response = app.backtest(body.clone()).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", body);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn a_lookback_beyond_the_largest_limit_is_insufficient_data() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    for body in [
+        json!({ "coin": "btc", "entry": "close[5000][5000] > 1", "exit": "close < 1" }),
+        json!({ "candles": candles(&[1.0, 2.0, 3.0]), "entry": "close > 1", "exit": "close[5000][5000][5000] < 1" }),
+    ] {
+        let response = app.backtest(body.clone()).await;
+
+        assert_eq!(422, response.status().as_u16(), "{}", body);
+    }
+}
@@ -0,0 +1,144 @@
+mod helpers;
+
+use helpers::{ohlc_body, search_suggestions_body, spawn_app, TestApp, BTC_UUID};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+const ETH_UUID: &str = "razxDUgYGNAdQ";
+
+impl TestApp {
+    async fn post_batch(&self, body: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/batch", self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+async fn mount_coin_once(app: &TestApp, symbol: &str, uuid: &str) {
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .and(query_param("query", symbol))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(search_suggestions_body(symbol, uuid)),
+        )
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+}
+
+#[tokio::test]
+async fn jobs_share_coin_lookups_and_candle_fetches() {
+    let app = spawn_app().await;
+    mount_coin_once(&app, "BTC", BTC_UUID).await;
+    mount_coin_once(&app, "ETH", ETH_UUID).await;
+    for uuid in [BTC_UUID, ETH_UUID] {
+        Mock::given(method("GET"))
+            .and(path(format!("/coin/{}/ohlc", uuid)))
+            .and(query_param("interval", "hour"))
+            .and(query_param("limit", "40"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(40)))
+            .expect(1)
+            .mount(&app.upstream)
+            .await;
+    }
+    // The EMA warms up over 40 candles, the others asking for the same ones.
+    let params = json!({ "interval": "hour", "limit": 40 });
+
+    let response = app
+        .post_batch(json!({ "jobs": [
+            { "coin": "btc", "indicator": "rsi", "params": params },
+            { "coin": "BTC", "indicator": "stochastic_oscillator", "params": params },
+            { "coin": "eth", "indicator": "ema", "params": params },
+            { "coin": "eth", "indicator": "aroon_oscillator", "params": params },
+        ]}))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let results = body["data"].as_array().unwrap();
+    assert_eq!(4, results.len());
+    assert!(results.iter().all(|result| result["status"] == "success"));
+    assert_eq!("rsi", results[0]["indicator"]);
+    assert_eq!("exponential_moving_average", results[2]["indicator"]);
+    assert_eq!(20, results[2]["period"]);
+    assert_eq!("hour", results[3]["meta"]["interval"]);
+    assert_eq!(4, body["meta"]["succeeded"]);
+}
+
+#[tokio::test]
+async fn results_match_the_dedicated_routes() {
+    let app = spawn_app().await;
+    helpers::mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    helpers::mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    let single: serde_json::Value = app.get("/rsi/btc").await.json().await.unwrap();
+    let batch: serde_json::Value = app
+        .post_batch(json!({ "jobs": [{ "coin": "btc", "indicator": "rsi" }] }))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(single["data"], batch["data"][0]["value"]);
+}
+
+#[tokio::test]
+async fn failing_jobs_do_not_fail_the_batch() {
+    let app = spawn_app().await;
+    helpers::mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .and(query_param("query", "NOPE"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success", "data": { "coins": [] }
+        })))
+        .mount(&app.upstream)
+        .await;
+    helpers::mount_ohlc(&app.upstream, BTC_UUID, 10).await;
+
+    let response = app
+        .post_batch(json!({ "jobs": [
+            { "coin": "btc", "indicator": "rsi", "params": { "limit": 10, "period": 5 } },
+            { "coin": "nope", "indicator": "rsi" },
+            { "coin": "btc", "indicator": "rsi", "params": { "limit": 10 } },
+            { "coin": "btc", "indicator": "macd" },
+            { "coin": "btc", "indicator": "rsi", "params": { "limit": 0 } },
+        ]}))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let codes: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["code"].as_str().unwrap_or("ok"))
+        .collect();
+    assert_eq!(
+        vec![
+            "ok",
+            "unknown_coin",
+            "insufficient_data",
+            "bad_parameter",
+            "bad_parameter"
+        ],
+        codes
+    );
+    assert_eq!(1, body["meta"]["succeeded"]);
+    assert_eq!(4, body["meta"]["failed"]);
+}
+
+#[tokio::test]
+async fn an_empty_or_malformed_batch_is_rejected() {
+    let app = spawn_app().await;
+
+    for body in [json!({ "jobs": [] }), json!([{ "coin": "btc" }])] {
+        let response = app.post_batch(body).await;
+
+        assert_eq!(400, response.status().as_u16());
+    }
+}
@@ -0,0 +1,115 @@
+mod helpers;
+
+use helpers::{ohlc_body, search_suggestions_body, spawn_app, spawn_app_with, BTC_UUID};
+use std::time::Duration;
+use taindicators::cache::TtlCache;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn repeated_requests_hit_the_upstream_once() {
+    let app = spawn_app().await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(search_suggestions_body("BTC", BTC_UUID)),
+        )
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(30)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let first = app.get("/rsi/btc").await;
+    let second = app.get("/rsi/BTC").await;
+
+    assert_eq!(200, first.status().as_u16());
+    assert_eq!(200, second.status().as_u16());
+}
+
+#[tokio::test]
+async fn cache_stats_report_hits_and_misses() {
+    let app = spawn_app().await;
+    helpers::mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    helpers::mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    app.get("/rsi/btc").await;
+    app.get("/rsi/btc").await;
+    let stats: serde_json::Value = app.get("/admin/cache").await.json().await.unwrap();
+
+    assert_eq!(1, stats["data"]["uuid"]["hits"]);
+    assert_eq!(1, stats["data"]["uuid"]["misses"]);
+    assert_eq!(1, stats["data"]["ohlc"]["entries"]);
+    assert_eq!(0.5, stats["data"]["ohlc"]["hitRatio"]);
+}
+
+#[tokio::test]
+async fn purging_forces_a_new_upstream_call() {
+    let app = spawn_app().await;
+    helpers::mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(30)))
+        .expect(2)
+        .mount(&app.upstream)
+        .await;
+
+    app.get("/rsi/btc").await;
+    let purged: serde_json::Value = app.delete("/admin/cache/ohlc").await.json().await.unwrap();
+    app.get("/rsi/btc").await;
+
+    assert_eq!(1, purged["purged"]);
+}
+
+#[tokio::test]
+async fn purging_an_unknown_kind_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = app.delete("/admin/cache/everything").await;
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_zero_sized_cache_always_goes_upstream() {
+    let app = spawn_app_with(|settings| settings.crypto_client.cache.max_entries = 0).await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(search_suggestions_body("BTC", BTC_UUID)),
+        )
+        .expect(2)
+        .mount(&app.upstream)
+        .await;
+    helpers::mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    app.get("/rsi/btc").await;
+    app.get("/rsi/btc").await;
+}
+
+#[test]
+fn entries_expire_after_their_ttl() {
+    let cache = TtlCache::new(10);
+    cache.insert("btc", 1, Duration::from_millis(20));
+
+    assert_eq!(Some(1), cache.get("btc"));
+    std::thread::sleep(Duration::from_millis(30));
+    assert_eq!(None, cache.get("btc"));
+}
+
+#[test]
+fn a_full_cache_evicts_the_entry_closest_to_expiry() {
+    let cache = TtlCache::new(2);
+    cache.insert("short", 1, Duration::from_secs(10));
+    cache.insert("long", 2, Duration::from_secs(100));
+    cache.insert("new", 3, Duration::from_secs(50));
+
+    assert_eq!(None, cache.get("short"));
+    assert_eq!(Some(2), cache.get("long"));
+    assert_eq!(Some(3), cache.get("new"));
+    assert_eq!(1, cache.stats().evictions);
+}
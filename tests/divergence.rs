@@ -0,0 +1,179 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, BTC_UUID};
+use taindicators::crypto_client::ParsedOhlc;
+use taindicators::divergence::{detect, DivergenceKind, SwingParams};
+
+/// Candles with the given highs, oldest first, each low 2 below its high.
+fn candles(highs: &[f64]) -> Vec<ParsedOhlc> {
+    highs
+        .iter()
+        .enumerate()
+        .map(|(i, high)| ParsedOhlc {
+            starting_at: i as i64 * 3600,
+            ending_at: (i as i64 + 1) * 3600,
+            open: high - 1.0,
+            high: *high,
+            low: high - 2.0,
+            close: high - 1.0,
+            avg: high - 1.0,
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn kinds(highs: &[f64], oscillator: &[f64], params: &SwingParams) -> Vec<DivergenceKind> {
+    detect(&candles(highs), oscillator, params)
+        .iter()
+        .map(|divergence| divergence.kind)
+        .collect()
+}
+
+const TIGHT: SwingParams = SwingParams {
+    swing: 1,
+    max_distance: 10,
+};
+
+#[test]
+fn lows_diverging_are_bullish() {
+    assert_eq!(
+        vec![DivergenceKind::RegularBullish],
+        kinds(
+            &[12.0, 10.0, 12.0, 9.0, 12.0],
+            &[50.0, 20.0, 50.0, 30.0, 50.0],
+            &TIGHT
+        )
+    );
+    assert_eq!(
+        vec![DivergenceKind::HiddenBullish],
+        kinds(
+            &[12.0, 9.0, 12.0, 10.0, 12.0],
+            &[50.0, 30.0, 50.0, 20.0, 50.0],
+            &TIGHT
+        )
+    );
+}
+
+#[test]
+fn highs_diverging_are_bearish() {
+    assert_eq!(
+        vec![DivergenceKind::RegularBearish],
+        kinds(
+            &[10.0, 12.0, 10.0, 13.0, 10.0],
+            &[50.0, 80.0, 50.0, 70.0, 50.0],
+            &TIGHT
+        )
+    );
+    assert_eq!(
+        vec![DivergenceKind::HiddenBearish],
+        kinds(
+            &[10.0, 13.0, 10.0, 12.0, 10.0],
+            &[50.0, 70.0, 50.0, 80.0, 50.0],
+            &TIGHT
+        )
+    );
+}
+
+#[test]
+fn divergences_carry_their_anchor_points_and_strength() {
+    let divergences = detect(
+        &candles(&[12.0, 10.0, 12.0, 9.0, 12.0]),
+        &[50.0, 20.0, 50.0, 30.0, 50.0],
+        &TIGHT,
+    );
+
+    let divergence = &divergences[0];
+    assert_eq!(
+        [(3600, 8.0), (3 * 3600, 7.0)],
+        divergence.price.map(|point| (point.timestamp, point.value))
+    );
+    assert_eq!(
+        [(3600, 20.0), (3 * 3600, 30.0)],
+        divergence
+            .oscillator
+            .map(|point| (point.timestamp, point.value))
+    );
+    // The low falls by 1/8 while the oscillator rises by a third of its range.
+    assert!((divergence.strength - (1.0 / 8.0 + 10.0 / 30.0)).abs() < 1e-9);
+}
+
+#[test]
+fn strength_stays_finite_without_a_scale() {
+    // The first low is 0, the price change having nothing to be relative to.
+    let divergences = detect(
+        &candles(&[12.0, 2.0, 12.0, 1.0, 12.0]),
+        &[50.0, 20.0, 50.0, 30.0, 50.0],
+        &TIGHT,
+    );
+    assert!((divergences[0].strength - 10.0 / 30.0).abs() < 1e-9);
+
+    // A flat oscillator has no swing, let alone a divergence.
+    assert!(kinds(&[12.0, 10.0, 12.0, 9.0, 12.0], &[50.0; 5], &TIGHT).is_empty());
+}
+
+#[test]
+fn agreeing_or_distant_swings_are_not_divergences() {
+    // Both make lower lows.
+    assert!(kinds(
+        &[12.0, 10.0, 12.0, 9.0, 12.0],
+        &[50.0, 30.0, 50.0, 20.0, 50.0],
+        &TIGHT
+    )
+    .is_empty());
+    // The oscillator swings are too far from the price ones.
+    assert!(kinds(
+        &[12.0, 10.0, 12.0, 12.0, 12.0, 9.0, 12.0],
+        &[50.0, 50.0, 50.0, 20.0, 50.0, 50.0, 50.0],
+        &TIGHT
+    )
+    .is_empty());
+    // The price swings are too far apart.
+    let params = SwingParams {
+        swing: 1,
+        max_distance: 1,
+    };
+    assert!(kinds(
+        &[12.0, 10.0, 12.0, 9.0, 12.0],
+        &[50.0, 20.0, 50.0, 30.0, 50.0],
+        &params
+    )
+    .is_empty());
+}
+
+#[tokio::test]
+async fn the_route_reports_divergences_with_their_parameters() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 120).await;
+
+    let response = app
+        .get("/divergences/btc?oscillator=stochastic&period=5&swing=2&limit=120")
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["data"].is_array());
+    assert_eq!("stochastic", body["meta"]["oscillator"]);
+    assert_eq!(5, body["meta"]["period"]);
+    assert_eq!(2, body["meta"]["swing"]);
+    assert_eq!(120, body["meta"]["candles"]);
+}
+
+#[tokio::test]
+async fn invalid_parameters_are_rejected() {
+    let app = spawn_app().await;
+
+    for (query, parameter) in [
+        ("oscillator=cci", "oscillator"),
+        ("oscillator=macd&period=10", "period"),
+        ("period=1", "period"),
+        ("swing=0", "swing"),
+        ("swing=5&max_distance=5", "max_distance"),
+    ] {
+        let response = app.get(&format!("/divergences/btc?{}", query)).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", query);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", query);
+    }
+}
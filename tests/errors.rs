@@ -0,0 +1,97 @@
+mod helpers;
+
+use helpers::{mount_coin, spawn_app, spawn_app_with, BTC_UUID};
+use taindicators::missing_data::MissingDataPolicy;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn an_unknown_coin_is_a_json_404() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+
+    let response = app.get("/rsi/doesnotexist").await;
+
+    assert_eq!(404, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("error", body["status"]);
+    assert_eq!("unknown_coin", body["code"]);
+    assert_eq!("DOESNOTEXIST", body["details"]["coin"]);
+    assert!(body["message"].is_string());
+}
+
+#[tokio::test]
+async fn an_invalid_market_is_rejected_before_calling_the_upstream() {
+    let app = spawn_app().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.upstream)
+        .await;
+
+    for route in [
+        "/fibonacci_retracement/btc/sideways",
+        "/fibonacci_extension/btc/sideways",
+    ] {
+        let response = app.get(route).await;
+
+        assert_eq!(400, response.status().as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!("bad_parameter", body["code"]);
+        assert_eq!("market", body["details"]["parameter"]);
+    }
+}
+
+#[tokio::test]
+async fn malformed_upstream_numbers_are_a_bad_gateway_when_configured_to_fail() {
+    let app =
+        spawn_app_with(|settings| settings.crypto_client.missing_data = MissingDataPolicy::Error)
+            .await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "status": "success",
+            "data": { "ohlc": [{
+                "startingAt": 0, "endingAt": 3600,
+                "open": "1", "high": "abc", "low": "1", "close": "1", "avg": "1"
+            }] }
+        })))
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(502, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("upstream_failure", body["code"]);
+}
+
+#[tokio::test]
+async fn upstream_rate_limiting_is_reported_with_retry_after() {
+    let app = spawn_app().await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "60"))
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!("60", response.headers()["Retry-After"]);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("upstream_rate_limited", body["code"]);
+    assert_eq!(60, body["details"]["retryAfterSeconds"]);
+}
+
+#[tokio::test]
+async fn unknown_routes_are_a_json_404() {
+    let app = spawn_app().await;
+
+    let response = app.get("/macd/btc").await;
+
+    assert_eq!(404, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("not_found", body["code"]);
+}
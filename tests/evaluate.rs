@@ -0,0 +1,122 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, BTC_UUID};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn a_numeric_expression_returns_one_value_per_candle() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    let response = app
+        .get("/evaluate/btc?expression=ema(close,%2012)%20-%20ema(close,%2026)&limit=60")
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let data = body["data"].as_array().unwrap();
+    // The first 25 candles only warm the slow average up.
+    assert_eq!(35, data.len());
+    assert!(data.iter().all(|point| point["value"].is_number()));
+    assert!(data[0]["timestamp"].as_i64() < data[34]["timestamp"].as_i64());
+    assert_eq!(25, body["meta"]["lookback"]);
+    assert_eq!(60, body["meta"]["candles"]);
+}
+
+#[tokio::test]
+async fn a_condition_returns_booleans() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 40).await;
+
+    let body: serde_json::Value = app
+        .get("/evaluate/btc?expression=close%20%3E%20close[1]%20or%20crosses_above(close,%20sma(close,%205))&limit=40")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(35, data.len());
+    assert!(data.iter().all(|point| point["value"].is_boolean()));
+}
+
+#[tokio::test]
+async fn the_last_rsi_matches_the_rsi_route() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    let single: serde_json::Value = app.get("/rsi/btc").await.json().await.unwrap();
+    let evaluated: serde_json::Value = app
+        .get("/evaluate/btc?expression=rsi(14)&limit=30")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let data = evaluated["data"].as_array().unwrap();
+    assert_eq!(single["data"], data[data.len() - 1]["value"]);
+}
+
+#[tokio::test]
+async fn the_default_limit_covers_the_lookback() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("limit", "79"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::ohlc_body(79)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let body: serde_json::Value = app
+        .get("/evaluate/btc?expression=sma(close,%2050)")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(30, body["data"].as_array().unwrap().len());
+}
+
+#[tokio::test]
+async fn invalid_expressions_are_rejected_before_fetching() {
+    let app = spawn_app().await;
+
+    for expression in [
+        "rsi(",
+        "macd(12)",
+        "sma(close,%200)",
+        "closing%20%3E%201",
+        "close[99999999999999999999999][1]%20%3E%201",
+    ] {
+        let response = app
+            .get(&format!("/evaluate/btc?expression={}", expression))
+            .await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", expression);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            "expression", error["details"]["parameter"],
+            "{}",
+            expression
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_lookback_beyond_the_largest_limit_is_insufficient_data() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    let response = app
+        .get("/evaluate/btc?expression=close[5000][5000]%20%3E%201")
+        .await;
+
+    assert_eq!(422, response.status().as_u16());
+}
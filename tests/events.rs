@@ -0,0 +1,166 @@
+mod helpers;
+
+use std::time::Duration;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, spawn_app_with, TestApp, BTC_UUID};
+
+const ROUTE: &str = "/events/btc?indicators=rsi,ema&period=5&interval=hour";
+
+/// An SSE response read event by event.
+struct Events {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    id: u64,
+    name: String,
+    data: serde_json::Value,
+}
+
+impl Events {
+    /// The next event, `None` when none comes within `wait`.
+    async fn next_within(&mut self, wait: Duration) -> Option<Event> {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                if frame.starts_with(':') {
+                    continue;
+                }
+                let field = |name: &str| {
+                    frame
+                        .lines()
+                        .find_map(|line| line.strip_prefix(&format!("{}: ", name)))
+                        .unwrap()
+                        .to_owned()
+                };
+                return Some(Event {
+                    id: field("id").parse().unwrap(),
+                    name: field("event"),
+                    data: serde_json::from_str(&field("data")).unwrap(),
+                });
+            }
+            let chunk = tokio::time::timeout(wait, self.response.chunk())
+                .await
+                .ok()?
+                .expect("Failed to read the stream.")
+                .expect("The stream ended.");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    async fn next(&mut self) -> Event {
+        self.next_within(Duration::from_secs(5))
+            .await
+            .expect("No event within 5 seconds.")
+    }
+}
+
+impl TestApp {
+    async fn events(&self, route: &str, last_event_id: Option<u64>) -> Events {
+        let mut request = self.api_client.get(format!("{}{}", self.address, route));
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id.to_string());
+        }
+        let response = request.send().await.expect("Failed to execute request.");
+        assert_eq!(200, response.status().as_u16());
+        Events {
+            response,
+            buffer: String::new(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn indicator_updates_are_streamed_as_server_sent_events() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    let mut events = app
+        .events("/events/btc?indicators=rsi&period=5&interval=hour", None)
+        .await;
+
+    assert_eq!(
+        "text/event-stream",
+        events.response.headers()["content-type"]
+    );
+    let event = events.next().await;
+    assert_eq!("update", event.name);
+    assert_eq!("update", event.data["type"]);
+    assert_eq!("BTC", event.data["subscription"]["coin"]);
+    assert_eq!("rsi", event.data["subscription"]["indicator"]);
+    assert_eq!(100.0, event.data["close"]);
+}
+
+#[tokio::test]
+async fn a_resuming_client_gets_the_events_it_missed() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    // Kept open so that the values stay subscribed.
+    let mut first = app.events(ROUTE, None).await;
+    let mut seen = [first.next().await, first.next().await];
+    seen.sort_by_key(|event| event.id);
+
+    let mut resumed = app.events(ROUTE, Some(seen[0].id)).await;
+    assert_eq!(seen[1], resumed.next().await);
+    assert_eq!(None, resumed.next_within(Duration::from_millis(300)).await);
+
+    let mut caught_up = app.events(ROUTE, Some(seen[1].id)).await;
+    assert_eq!(
+        None,
+        caught_up.next_within(Duration::from_millis(300)).await
+    );
+}
+
+#[tokio::test]
+async fn an_unknown_event_id_falls_back_to_the_latest_values() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    let mut first = app.events(ROUTE, None).await;
+    let mut seen = [first.next().await, first.next().await];
+
+    let mut resumed = app.events(ROUTE, Some(1_000_000)).await;
+    let mut replayed = [resumed.next().await, resumed.next().await];
+
+    seen.sort_by_key(|event| event.id);
+    replayed.sort_by_key(|event| event.id);
+    assert_eq!(seen, replayed);
+}
+
+#[tokio::test]
+async fn invalid_parameters_are_rejected() {
+    let app = spawn_app().await;
+
+    for (query, parameter) in [
+        ("indicators=rsi,macd", "indicators"),
+        ("indicators=rsi&period=1", "period"),
+    ] {
+        let response = app.get(&format!("/events/btc?{}", query)).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", query);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn unknown_coins_and_subscriptions_past_the_limit_are_rejected() {
+    let app = spawn_app_with(|settings| settings.live.max_subscriptions = 1).await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    let response = app.get("/events/nope?indicators=rsi").await;
+    assert_eq!(404, response.status().as_u16());
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("unknown_coin", error["code"]);
+
+    let _feed = app.events("/events/btc?indicators=rsi", None).await;
+    let response = app.get("/events/btc?indicators=rsi,ema").await;
+    assert_eq!(400, response.status().as_u16());
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("subscription", error["details"]["parameter"]);
+}
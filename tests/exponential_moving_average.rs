@@ -0,0 +1,67 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, ohlc_body, spawn_app, BTC_UUID};
+use taindicators::crypto_client::ParsedOhlc;
+use taindicators::indicators::{ema, Indicator};
+
+fn candles(closes: &[f64]) -> Vec<ParsedOhlc> {
+    closes
+        .iter()
+        .enumerate()
+        .map(|(i, &close)| ParsedOhlc {
+            starting_at: i as i64 * 3600,
+            ending_at: (i as i64 + 1) * 3600,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            avg: close,
+            ..ParsedOhlc::default()
+        })
+        .collect()
+}
+
+#[test]
+fn the_ema_is_warmed_up_over_twice_its_period() {
+    let closes = [50.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+    let indicator = Indicator::ExponentialMovingAverage;
+
+    let series = indicator.series(&candles(&closes), 2);
+
+    assert!(series[2].is_nan());
+    assert_eq!(ema(&closes[..4], 2)[3], series[3]);
+    // The 50 is out of the window of the newest value.
+    assert_eq!(ema(&closes[2..], 2)[3], series[5]);
+    assert_eq!(4, indicator.candles_required(2));
+    assert!(indicator.period(Some(2500)).is_ok());
+    assert!(indicator.period(Some(2501)).is_err());
+}
+
+#[tokio::test]
+async fn fetching_more_candles_does_not_move_the_value() {
+    let mut values = vec![];
+    for (count, limit) in [(40, None), (200, Some(200))] {
+        let app = spawn_app().await;
+        mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+        mount_ohlc(&app.upstream, BTC_UUID, count).await;
+        let query = limit.map_or(String::new(), |limit| format!("?limit={}", limit));
+
+        let response = app
+            .get(&format!("/exponential_moving_average/btc{}", query))
+            .await;
+
+        assert_eq!(200, response.status().as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        values.push(body["data"].as_f64().unwrap());
+    }
+
+    let closes: Vec<f64> = ohlc_body(40)["data"]["ohlc"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .rev()
+        .map(|candle| candle["close"].as_str().unwrap().parse().unwrap())
+        .collect();
+    assert!((ema(&closes, 20)[39] - values[0]).abs() < 1e-9);
+    assert_eq!(values[0], values[1]);
+}
@@ -0,0 +1,182 @@
+use taindicators::expression::{Context, Expr, Series};
+
+/// A handful of named series over five points, oldest first.
+struct Values;
+
+impl Context for Values {
+    fn points(&self) -> usize {
+        5
+    }
+
+    fn variable(&self, name: &str) -> Option<Vec<f64>> {
+        match name {
+            "close" => Some(vec![10.0, 12.0, 11.0, 13.0, 15.0]),
+            "fast" => Some(vec![1.0, 2.0, 4.0, 3.0, 1.0]),
+            "slow" => Some(vec![2.0, 3.0, 3.0, 3.0, 2.0]),
+            "gappy" => Some(vec![1.0, f64::NAN, 3.0, 4.0, 5.0]),
+            _ => None,
+        }
+    }
+}
+
+fn evaluate(source: &str) -> Series {
+    Expr::parse(source).unwrap().evaluate(&Values).unwrap()
+}
+
+fn numbers(source: &str) -> Vec<f64> {
+    match evaluate(source) {
+        Series::Numbers(numbers) => numbers,
+        series => panic!("{} is not a number: {:?}", source, series),
+    }
+}
+
+fn conditions(source: &str) -> Vec<Option<bool>> {
+    match evaluate(source) {
+        Series::Conditions(conditions) => conditions,
+        series => panic!("{} is not a condition: {:?}", source, series),
+    }
+}
+
+#[test]
+fn arithmetic_follows_the_usual_precedence() {
+    assert_eq!(vec![7.0; 5], numbers("1 + 2 * 3"));
+    assert_eq!(vec![9.0; 5], numbers("(1 + 2) * 3"));
+    assert_eq!(vec![-1.5; 5], numbers("-3 / 2"));
+    assert_eq!(vec![9.0, 10.0, 7.0, 10.0, 14.0], numbers("close - fast"));
+}
+
+#[test]
+fn functions_and_offsets_apply_to_whole_series() {
+    let sma = numbers("sma(close, 2)");
+    assert!(sma[0].is_nan());
+    assert_eq!(vec![11.0, 11.5, 12.0, 14.0], sma[1..]);
+
+    let change = numbers("close - close[1]");
+    assert!(change[0].is_nan());
+    assert_eq!(vec![2.0, -1.0, 2.0, 2.0], change[1..]);
+
+    assert_eq!(sma[1..], numbers("sma(2)")[1..]);
+    assert_eq!(numbers("ema(close, 3)")[2..], numbers("ema(3)")[2..]);
+
+    assert_eq!(vec![2.0, 3.0, 4.0, 3.0, 2.0], numbers("max(fast, slow)"));
+    assert_eq!(15.0, numbers("highest(close, 3)")[4]);
+}
+
+#[test]
+fn conditions_combine_comparisons() {
+    assert_eq!(
+        vec![
+            Some(false),
+            Some(false),
+            Some(true),
+            Some(false),
+            Some(false)
+        ],
+        conditions("fast > slow")
+    );
+    assert_eq!(
+        vec![Some(true), Some(true), Some(false), Some(false), Some(true)],
+        conditions("not (fast >= slow) or close > 14 and fast < 2")
+    );
+    assert_eq!(
+        vec![None, Some(false), Some(true), Some(false), Some(false)],
+        conditions("crosses_above(fast, slow)")
+    );
+    assert_eq!(
+        vec![None, Some(false), Some(false), Some(false), Some(true)],
+        conditions("crosses_below(fast, slow)")
+    );
+}
+
+#[test]
+fn undefined_points_are_unknown() {
+    assert_eq!(
+        vec![Some(false), None, Some(true), Some(true), Some(true)],
+        conditions("gappy > 2")
+    );
+    // `false and unknown` is false, `true or unknown` is true.
+    assert_eq!(Some(false), conditions("gappy > 2 and close > 100")[1]);
+    assert_eq!(Some(true), conditions("gappy > 2 or close > 0")[1]);
+    assert_eq!(None, conditions("gappy > 2 and close > 0")[1]);
+}
+
+#[test]
+fn variables_and_lookback_are_known_from_the_expression() {
+    let expr = Expr::parse("rsi(14) < 30 and close > sma(close, 50) and close[3] > open").unwrap();
+
+    assert_eq!(vec!["close", "open"], expr.variables());
+    assert_eq!(49, expr.lookback());
+    assert!(expr.is_condition());
+    let defaulted = Expr::parse("rsi(14) < 30 and close > sma(50)").unwrap();
+    assert_eq!(vec!["close"], defaulted.variables());
+    assert_eq!(49, defaulted.lookback());
+    assert!(defaulted.is_condition());
+    assert_eq!(
+        26,
+        Expr::parse("crosses_above(ema(close, 12), ema(close, 26))")
+            .unwrap()
+            .lookback()
+    );
+    assert_eq!(
+        10000,
+        Expr::parse("close[5000][5000] > 1").unwrap().lookback()
+    );
+    assert!(!Expr::parse("close - ema(close, 20)")
+        .unwrap()
+        .is_condition());
+}
+
+#[test]
+fn errors_report_their_position() {
+    for (source, position) in [
+        ("close <", 7),
+        ("close < 30 )", 11),
+        ("close # 2", 6),
+        ("(close", 6),
+        ("(close < 30) + 1", 0),
+        ("close and fast", 0),
+        ("macd(close, 12)", 0),
+        ("sma(close)", 4),
+        ("sma(close, 0)", 11),
+        ("sma(close, fast)", 11),
+        ("crosses_above(close)", 0),
+        ("close[-1]", 6),
+        ("close[99999999999999999999999] > 1", 6),
+    ] {
+        let err = Expr::parse(source).unwrap_err();
+
+        assert_eq!(position, err.position, "{}: {}", source, err);
+    }
+}
+
+#[test]
+fn deep_nesting_and_long_sources_are_rejected() {
+    let nested = |depth: usize| format!("{}close{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(Expr::parse(&nested(Expr::MAX_DEPTH)).is_ok());
+    assert_eq!(
+        Expr::MAX_DEPTH + 1,
+        Expr::parse(&nested(Expr::MAX_DEPTH + 1))
+            .unwrap_err()
+            .position
+    );
+    // Deep enough to overflow the stack if it were parsed.
+    assert!(Expr::parse(&nested(5000)).is_err());
+    assert!(Expr::parse(&format!("{}close", "-".repeat(500))).is_err());
+    assert!(Expr::parse(&format!("{}close > 1", "not ".repeat(100))).is_err());
+
+    let long = format!("close{}", " + close".repeat(Expr::MAX_LENGTH / 8));
+    assert_eq!(Expr::MAX_LENGTH, Expr::parse(&long).unwrap_err().position);
+}
+
+#[test]
+fn unknown_values_are_reported_when_evaluating() {
+    assert!(Expr::parse("unknown > 1")
+        .unwrap()
+        .evaluate(&Values)
+        .is_err());
+    // `stochastic` reads the highs and lows, which `Values` doesn't have.
+    assert!(Expr::parse("stochastic(3)")
+        .unwrap()
+        .evaluate(&Values)
+        .is_err());
+}
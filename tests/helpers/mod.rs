@@ -0,0 +1,160 @@
+#![allow(dead_code)]
+
+use secrecy::Secret;
+use serde_json::json;
+use std::net::TcpListener;
+use std::sync::OnceLock;
+use taindicators::configuration::{
+    AlertSettings, ApplicationSettings, AuthSettings, CacheSettings, CircuitBreakerSettings,
+    CryptoClientSettings, LiveSettings, LoggingSettings, OptimizeSettings, PrecomputeSettings,
+    ReadinessSettings, RetrySettings, Settings,
+};
+use taindicators::missing_data::MissingDataPolicy;
+use taindicators::startup::run;
+use taindicators::telemetry;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub const BTC_UUID: &str = "Qwsogvtv82FCd";
+
+pub struct TestApp {
+    pub address: String,
+    pub upstream: MockServer,
+    pub api_client: reqwest::Client,
+}
+
+impl TestApp {
+    pub async fn get(&self, route: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", self.address, route))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete(&self, route: &str) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}{}", self.address, route))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the application against a mock Coinranking server, letting the caller
+/// tweak the settings before the server starts.
+pub async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
+    // Logs are only printed with `TEST_LOG` set, e.g. `TEST_LOG=1 cargo test`.
+    static LOGGING: OnceLock<()> = OnceLock::new();
+    LOGGING.get_or_init(|| {
+        if std::env::var_os("TEST_LOG").is_some() {
+            telemetry::init(&LoggingSettings::default()).expect("Failed to set up logging.");
+        }
+    });
+    let upstream = MockServer::start().await;
+    let mut settings = Settings {
+        application: ApplicationSettings {
+            port: 0,
+            host: "127.0.0.1".to_owned(),
+        },
+        crypto_client: CryptoClientSettings {
+            base_url: upstream.uri(),
+            auth_token: Secret::new("test-token".to_owned()),
+            timeout_milliseconds: 2_000,
+            missing_data: MissingDataPolicy::default(),
+            // Keep the retries but don't make the test suite wait for them.
+            retry: RetrySettings {
+                max_retries: 2,
+                initial_backoff_milliseconds: 1,
+                max_backoff_milliseconds: 10,
+            },
+            circuit_breaker: CircuitBreakerSettings::default(),
+            cache: CacheSettings::default(),
+        },
+        optimize: OptimizeSettings::default(),
+        live: LiveSettings::default(),
+        alerts: AlertSettings::default(),
+        precompute: PrecomputeSettings::default(),
+        // Enabled by default, but most tests are about the routes rather than the keys.
+        auth: AuthSettings {
+            enabled: false,
+            ..AuthSettings::default()
+        },
+        logging: LoggingSettings::default(),
+        readiness: ReadinessSettings::default(),
+    };
+    customise(&mut settings);
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    // We retrieve the port assigned to us by the OS
+    let port = listener.local_addr().unwrap().port();
+    let server = run(listener, settings).expect("Failed to bind address");
+    tokio::spawn(server);
+
+    TestApp {
+        address: format!("http://127.0.0.1:{}", port),
+        upstream,
+        api_client: reqwest::Client::new(),
+    }
+}
+
+pub fn search_suggestions_body(symbol: &str, uuid: &str) -> serde_json::Value {
+    json!({
+        "status": "success",
+        "data": {
+            "coins": [{
+                "uuid": uuid,
+                "iconUrl": "https://cdn.coinranking.com/icon.svg",
+                "name": symbol,
+                "symbol": symbol,
+                "price": "20000"
+            }]
+        }
+    })
+}
+
+/// `count` hourly candles, newest first as Coinranking returns them, the newest
+/// one still open.
+pub fn ohlc_body(count: usize) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp();
+    let current_start = now - now % 3600;
+    let candles: Vec<serde_json::Value> = (0..count)
+        .map(|i| {
+            let starting_at = current_start - i as i64 * 3600;
+            let close = 100.0 + (i % 7) as f64 - (i % 3) as f64;
+            json!({
+                "startingAt": starting_at,
+                "endingAt": starting_at + 3600,
+                "open": format!("{}", close - 1.0),
+                "high": format!("{}", close + 2.0),
+                "low": format!("{}", close - 2.0),
+                "close": format!("{}", close),
+                "avg": format!("{}", close - 0.5),
+            })
+        })
+        .collect();
+
+    json!({ "status": "success", "data": { "ohlc": candles } })
+}
+
+pub async fn mount_coin(upstream: &MockServer, symbol: &str, uuid: &str) {
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(search_suggestions_body(symbol, uuid)),
+        )
+        .mount(upstream)
+        .await;
+}
+
+pub async fn mount_ohlc(upstream: &MockServer, uuid: &str, count: usize) {
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", uuid)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(count)))
+        .mount(upstream)
+        .await;
+}
@@ -0,0 +1,232 @@
+mod helpers;
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use helpers::{mount_coin, mount_ohlc, ohlc_body, spawn_app, spawn_app_with, TestApp, BTC_UUID};
+use serde_json::json;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+impl TestApp {
+    async fn live(&self) -> Socket {
+        let url = format!("{}/live", self.address.replacen("http", "ws", 1));
+        connect_async(url)
+            .await
+            .expect("Failed to open the WebSocket.")
+            .0
+    }
+}
+
+async fn send(socket: &mut Socket, message: serde_json::Value) {
+    socket
+        .send(Message::Text(message.to_string()))
+        .await
+        .expect("Failed to send a message.");
+}
+
+/// The next JSON message, failing after a few seconds without one.
+async fn next_event(socket: &mut Socket) -> serde_json::Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("No message within 5 seconds.")
+            .expect("The socket closed.")
+            .expect("Failed to read a message.");
+        if let Message::Text(text) = message {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+fn subscribe_rsi() -> serde_json::Value {
+    json!({ "action": "subscribe", "coin": "btc", "indicator": "rsi", "params": { "period": 5, "interval": "hour" } })
+}
+
+async fn ohlc_requests(app: &TestApp) -> usize {
+    app.upstream
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().ends_with("/ohlc"))
+        .count()
+}
+
+#[tokio::test]
+async fn a_subscription_receives_the_value_on_the_newest_candle() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    let mut socket = app.live().await;
+
+    send(&mut socket, subscribe_rsi()).await;
+
+    let subscribed = next_event(&mut socket).await;
+    assert_eq!("subscribed", subscribed["type"]);
+    let subscription =
+        json!({ "coin": "BTC", "indicator": "rsi", "period": 5, "interval": "hour" });
+    assert_eq!(subscription, subscribed["subscription"]);
+    let update = next_event(&mut socket).await;
+    assert_eq!("update", update["type"]);
+    assert_eq!(subscription, update["subscription"]);
+    let newest = &ohlc_body(60)["data"]["ohlc"][0];
+    assert_eq!(newest["startingAt"], update["timestamp"]);
+    assert_eq!(100.0, update["close"]);
+    let value = update["value"].as_f64().unwrap();
+    assert!((0.0..=100.0).contains(&value));
+}
+
+#[tokio::test]
+async fn subscribers_of_the_same_values_share_the_upstream_fetch() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    let mut first = app.live().await;
+    let mut second = app.live().await;
+
+    send(&mut first, subscribe_rsi()).await;
+    assert_eq!("subscribed", next_event(&mut first).await["type"]);
+    let update = next_event(&mut first).await;
+    send(&mut second, subscribe_rsi()).await;
+
+    assert_eq!("subscribed", next_event(&mut second).await["type"]);
+    // The second subscriber gets the value already polled for the first one.
+    assert_eq!(update, next_event(&mut second).await);
+    assert_eq!(1, ohlc_requests(&app).await);
+}
+
+#[tokio::test]
+async fn a_price_tick_on_the_forming_candle_is_pushed() {
+    let app = spawn_app_with(|settings| settings.live.poll_interval_milliseconds = 50).await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(60)))
+        .up_to_n_times(1)
+        .mount(&app.upstream)
+        .await;
+    let mut ticked = ohlc_body(60);
+    ticked["data"]["ohlc"][0]["close"] = json!("104");
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ticked))
+        .mount(&app.upstream)
+        .await;
+    let mut socket = app.live().await;
+
+    send(&mut socket, subscribe_rsi()).await;
+    next_event(&mut socket).await;
+    let before = next_event(&mut socket).await;
+    let after = next_event(&mut socket).await;
+
+    assert_eq!(100.0, before["close"]);
+    assert_eq!(104.0, after["close"]);
+    assert_eq!(before["timestamp"], after["timestamp"]);
+    assert!(after["value"].as_f64().unwrap() > before["value"].as_f64().unwrap());
+}
+
+#[tokio::test]
+async fn invalid_messages_are_answered_with_errors() {
+    let app = spawn_app().await;
+    let mut socket = app.live().await;
+
+    for (message, parameter) in [
+        (json!({ "action": "publish" }), "message"),
+        (
+            json!({ "action": "subscribe", "coin": "btc", "indicator": "macd" }),
+            "message",
+        ),
+        (
+            json!({ "action": "subscribe", "coin": "btc", "indicator": "rsi", "params": { "period": 1 } }),
+            "period",
+        ),
+        (
+            json!({ "action": "subscribe", "coin": " ", "indicator": "rsi" }),
+            "coin",
+        ),
+    ] {
+        send(&mut socket, message.clone()).await;
+
+        let event = next_event(&mut socket).await;
+        assert_eq!("error", event["type"], "{}", message);
+        assert_eq!(
+            parameter, event["error"]["details"]["parameter"],
+            "{}",
+            message
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_failing_subscription_reports_the_upstream_error() {
+    let app = spawn_app().await;
+    let mut socket = app.live().await;
+
+    send(
+        &mut socket,
+        json!({ "action": "subscribe", "coin": "nope", "indicator": "ema" }),
+    )
+    .await;
+
+    assert_eq!("subscribed", next_event(&mut socket).await["type"]);
+    let event = next_event(&mut socket).await;
+    assert_eq!("error", event["type"]);
+    assert_eq!("NOPE", event["subscription"]["coin"]);
+    assert_eq!("error", event["error"]["status"]);
+}
+
+#[tokio::test]
+async fn unknown_coins_are_refused_rather_than_polled() {
+    let app = spawn_app_with(|settings| settings.live.poll_interval_milliseconds = 50).await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    let mut socket = app.live().await;
+
+    send(
+        &mut socket,
+        json!({ "action": "subscribe", "coin": "nope", "indicator": "ema" }),
+    )
+    .await;
+
+    let event = next_event(&mut socket).await;
+    assert_eq!("error", event["type"]);
+    assert_eq!("NOPE", event["subscription"]["coin"]);
+    assert_eq!("unknown_coin", event["error"]["code"]);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let lookups = app.upstream.received_requests().await.unwrap().len();
+    assert_eq!(1, lookups);
+}
+
+#[tokio::test]
+async fn subscriptions_past_the_limit_of_the_server_are_refused() {
+    let app = spawn_app_with(|settings| settings.live.max_subscriptions = 1).await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    let mut first = app.live().await;
+    let mut second = app.live().await;
+
+    send(&mut first, subscribe_rsi()).await;
+    assert_eq!("subscribed", next_event(&mut first).await["type"]);
+    // The same values cost nothing more, other ones would be polled too.
+    send(&mut second, subscribe_rsi()).await;
+    assert_eq!("subscribed", next_event(&mut second).await["type"]);
+    send(
+        &mut second,
+        json!({ "action": "subscribe", "coin": "btc", "indicator": "ema" }),
+    )
+    .await;
+
+    let event = loop {
+        let event = next_event(&mut second).await;
+        if event["type"] != "update" {
+            break event;
+        }
+    };
+    assert_eq!("error", event["type"]);
+    assert_eq!("subscription", event["error"]["details"]["parameter"]);
+}
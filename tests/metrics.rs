@@ -0,0 +1,146 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, TestApp, BTC_UUID};
+
+impl TestApp {
+    async fn metrics(&self) -> String {
+        let response = self.get("/metrics").await;
+        assert_eq!(200, response.status().as_u16());
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        response.text().await.unwrap()
+    }
+}
+
+/// The value of the series of `name` with every one of `labels`, e.g. `route="/rsi/{coin}"`.
+fn value(metrics: &str, name: &str, labels: &[&str]) -> Option<f64> {
+    metrics
+        .lines()
+        .filter(|line| line.starts_with(&format!("{}{{", name)))
+        .find(|line| labels.iter().all(|label| line.contains(label)))
+        .map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+}
+
+#[tokio::test]
+async fn requests_are_counted_and_timed_per_route_and_status() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    app.get("/rsi/btc?interval=hour").await;
+    app.get("/rsi/eth?interval=hour").await;
+    app.get("/rsi/btc?interval=hour").await;
+    app.get("/not/a/route").await;
+    let metrics = app.metrics().await;
+
+    let rsi = r#"route="/rsi/{coin}""#;
+    assert_eq!(
+        Some(2.0),
+        value(&metrics, "http_requests_total", &[rsi, r#"status="200""#])
+    );
+    assert_eq!(
+        Some(1.0),
+        value(&metrics, "http_requests_total", &[rsi, r#"status="404""#])
+    );
+    assert_eq!(
+        Some(1.0),
+        value(
+            &metrics,
+            "http_requests_total",
+            &[r#"route="unmatched""#, r#"status="404""#]
+        )
+    );
+    assert_eq!(
+        Some(2.0),
+        value(
+            &metrics,
+            "http_request_duration_seconds_count",
+            &[rsi, r#"status="200""#]
+        )
+    );
+    // The scrape itself.
+    assert_eq!(
+        Some(1.0),
+        value(
+            &metrics,
+            "http_requests_in_flight",
+            &[r#"route="/metrics""#]
+        )
+    );
+    assert_eq!(
+        Some(0.0),
+        value(&metrics, "http_requests_in_flight", &[rsi])
+    );
+}
+
+#[tokio::test]
+async fn upstream_calls_and_cache_lookups_are_counted() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+
+    app.get("/rsi/btc?interval=hour").await;
+    app.get("/rsi/btc?interval=hour").await;
+    app.get("/rsi/eth?interval=hour").await;
+    let metrics = app.metrics().await;
+
+    let uuid = r#"method="get_coin_uuid""#;
+    let ohlc = r#"method="get_coin_ohlc""#;
+    assert_eq!(
+        Some(2.0),
+        value(&metrics, "upstream_requests_total", &[uuid])
+    );
+    assert_eq!(
+        Some(1.0),
+        value(&metrics, "upstream_requests_total", &[ohlc])
+    );
+    assert_eq!(
+        Some(1.0),
+        value(
+            &metrics,
+            "upstream_errors_total",
+            &[uuid, r#"code="unknown_coin""#]
+        )
+    );
+    assert_eq!(
+        Some(1.0),
+        value(&metrics, "upstream_request_duration_seconds_count", &[ohlc])
+    );
+    assert_eq!(
+        Some(1.0),
+        value(&metrics, "cache_hits_total", &[r#"cache="ohlc""#])
+    );
+    assert_eq!(
+        Some(1.0),
+        value(&metrics, "cache_misses_total", &[r#"cache="ohlc""#])
+    );
+    assert_eq!(
+        Some(0.5),
+        value(&metrics, "cache_hit_ratio", &[r#"cache="ohlc""#])
+    );
+}
+
+#[tokio::test]
+async fn unknown_methods_share_a_label() {
+    let app = spawn_app().await;
+
+    for name in ["PURGE", "BREW", "X-ANYTHING"] {
+        app.api_client
+            .request(
+                reqwest::Method::from_bytes(name.as_bytes()).unwrap(),
+                format!("{}/rsi/btc", app.address),
+            )
+            .send()
+            .await
+            .unwrap();
+    }
+    let metrics = app.metrics().await;
+
+    assert!(!metrics.contains("PURGE") && !metrics.contains("BREW"));
+    assert_eq!(
+        Some(3.0),
+        value(&metrics, "http_requests_total", &[r#"method="other""#])
+    );
+}
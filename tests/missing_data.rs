@@ -0,0 +1,241 @@
+mod helpers;
+
+use helpers::{mount_coin, spawn_app, spawn_app_with, BTC_UUID};
+use serde_json::json;
+use taindicators::missing_data::{apply, parse_value, MissingDataPolicy, Row};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn rows(values: &[Option<f64>]) -> Vec<Row> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| Row {
+            timestamp: i as i64 * 60,
+            values: vec![*value],
+        })
+        .collect()
+}
+
+#[test]
+fn null_and_non_numeric_values_are_missing() {
+    assert_eq!(Some(1.5), parse_value(Some("1.5")));
+    assert_eq!(Some(2.0), parse_value(Some(" 2 ")));
+    assert_eq!(None, parse_value(None));
+    assert_eq!(None, parse_value(Some("")));
+    assert_eq!(None, parse_value(Some("abc")));
+    assert_eq!(None, parse_value(Some("NaN")));
+    assert_eq!(None, parse_value(Some("inf")));
+}
+
+#[test]
+fn skip_drops_incomplete_rows() {
+    let complete = apply(MissingDataPolicy::Skip, rows(&[Some(1.0), None, Some(3.0)])).unwrap();
+
+    assert_eq!(vec![(0, vec![1.0]), (2, vec![3.0])], complete);
+}
+
+#[test]
+fn interpolate_fills_gaps_by_timestamp() {
+    let mut input = rows(&[Some(1.0), None, None, Some(4.0)]);
+    input[2].timestamp = 150;
+    input[3].timestamp = 180;
+
+    let complete = apply(MissingDataPolicy::Interpolate, input).unwrap();
+
+    assert_eq!(
+        vec![
+            (0, vec![1.0]),
+            (1, vec![1.0 + 3.0 * 60.0 / 180.0]),
+            (2, vec![1.0 + 3.0 * 150.0 / 180.0]),
+            (3, vec![4.0]),
+        ],
+        complete
+    );
+}
+
+#[test]
+fn interpolate_drops_edges_without_a_neighbour() {
+    let complete = apply(
+        MissingDataPolicy::Interpolate,
+        rows(&[None, Some(2.0), Some(3.0), None]),
+    )
+    .unwrap();
+
+    assert_eq!(vec![(1, vec![2.0]), (2, vec![3.0])], complete);
+}
+
+#[test]
+fn interpolate_works_column_by_column() {
+    let input = vec![
+        Row {
+            timestamp: 0,
+            values: vec![Some(1.0), Some(10.0)],
+        },
+        Row {
+            timestamp: 1,
+            values: vec![None, Some(20.0)],
+        },
+        Row {
+            timestamp: 2,
+            values: vec![Some(3.0), None],
+        },
+        Row {
+            timestamp: 3,
+            values: vec![Some(4.0), Some(40.0)],
+        },
+    ];
+
+    let complete = apply(MissingDataPolicy::Interpolate, input).unwrap();
+
+    assert_eq!(
+        vec![
+            (0, vec![1.0, 10.0]),
+            (1, vec![2.0, 20.0]),
+            (2, vec![3.0, 30.0]),
+            (3, vec![4.0, 40.0]),
+        ],
+        complete
+    );
+}
+
+#[test]
+fn error_rejects_any_missing_value() {
+    let result = apply(
+        MissingDataPolicy::Error,
+        rows(&[Some(1.0), None, Some(3.0)]),
+    );
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn empty_input_is_fine_for_every_policy() {
+    for policy in [
+        MissingDataPolicy::Skip,
+        MissingDataPolicy::Interpolate,
+        MissingDataPolicy::Error,
+    ] {
+        assert_eq!(
+            Vec::<(usize, Vec<f64>)>::new(),
+            apply(policy, vec![]).unwrap()
+        );
+    }
+}
+
+async fn mount_history(upstream: &wiremock::MockServer, prices: serde_json::Value) {
+    let history: Vec<serde_json::Value> = prices
+        .as_array()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(i, price)| json!({ "price": price, "timestamp": 1_600_000_000 + i as i64 * 3600 }))
+        .collect();
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/history", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success",
+            "data": { "change": "1.0", "history": history }
+        })))
+        .mount(upstream)
+        .await;
+}
+
+async fn mount_empty_ohlc(upstream: &wiremock::MockServer) {
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success",
+            "data": { "ohlc": [] }
+        })))
+        .mount(upstream)
+        .await;
+}
+
+#[tokio::test]
+async fn null_prices_are_skipped_by_default() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, json!(["10", null, "20", "not a number"])).await;
+
+    let response = app.get("/simple_moving_average/btc/24h").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(15.0, body["data"]);
+}
+
+#[tokio::test]
+async fn null_prices_can_be_interpolated() {
+    let app = spawn_app_with(|settings| {
+        settings.crypto_client.missing_data = MissingDataPolicy::Interpolate
+    })
+    .await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, json!(["10", null, "30"])).await;
+
+    let response = app.get("/simple_moving_average/btc/24h").await;
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(20.0, body["data"]);
+}
+
+#[tokio::test]
+async fn null_prices_can_fail_the_request() {
+    let app =
+        spawn_app_with(|settings| settings.crypto_client.missing_data = MissingDataPolicy::Error)
+            .await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, json!(["10", null, "30"])).await;
+
+    let response = app.get("/simple_moving_average/btc/24h").await;
+
+    assert_eq!(502, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_history_of_only_nulls_is_insufficient_data() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, json!([null, null])).await;
+
+    let response = app.get("/simple_moving_average/btc/24h").await;
+
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn empty_ohlc_is_insufficient_data_on_every_route() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_empty_ohlc(&app.upstream).await;
+
+    for route in [
+        "/fibonacci_retracement/btc/u",
+        "/fibonacci_extension/btc/d",
+        "/stochastic_oscillator/btc",
+        "/rsi/btc",
+        "/aroon_oscillator/btc",
+        "/exponential_moving_average/btc",
+    ] {
+        let response = app.get(route).await;
+
+        assert_eq!(422, response.status().as_u16(), "{}", route);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!("insufficient_data", body["code"], "{}", route);
+    }
+}
+
+#[tokio::test]
+async fn a_short_ohlc_series_is_insufficient_data() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    helpers::mount_ohlc(&app.upstream, BTC_UUID, 5).await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(422, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(14, body["details"]["required"]);
+    assert_eq!(5, body["details"]["available"]);
+}
@@ -0,0 +1,141 @@
+mod helpers;
+
+use helpers::{mount_coin, ohlc_body, spawn_app, BTC_UUID};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// `count` candles of `interval` seconds, newest first, the close moving by
+/// `step` from one candle to the next.
+fn trending_body(interval: i64, count: i64, step: f64) -> serde_json::Value {
+    let now = chrono::Utc::now().timestamp();
+    let current_start = now - now % interval;
+    let candles: Vec<serde_json::Value> = (0..count)
+        .map(|i| {
+            let starting_at = current_start - i * interval;
+            let close = 1000.0 - step * i as f64;
+            json!({
+                "startingAt": starting_at, "endingAt": starting_at + interval,
+                "open": format!("{}", close), "high": format!("{}", close + 1.0),
+                "low": format!("{}", close - 1.0), "close": format!("{}", close),
+                "avg": format!("{}", close)
+            })
+        })
+        .collect();
+    json!({ "status": "success", "data": { "ohlc": candles } })
+}
+
+async fn mount_interval(upstream: &MockServer, interval: &str, body: serde_json::Value) {
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("interval", interval))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .mount(upstream)
+        .await;
+}
+
+#[tokio::test]
+async fn each_interval_is_computed_and_summarised() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_interval(&app.upstream, "hour", trending_body(3600, 123, 1.0)).await;
+    mount_interval(&app.upstream, "day", trending_body(86400, 30, -1.0)).await;
+
+    let response = app
+        .get("/multi_timeframe/btc/rsi?intervals=day,hour,4hours")
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("bullish", body["data"]["hour"]["bias"]);
+    assert_eq!("bullish", body["data"]["4hours"]["bias"]);
+    assert_eq!("hour", body["data"]["4hours"]["meta"]["resampledFrom"]);
+    assert_eq!("bearish", body["data"]["day"]["bias"]);
+    assert_eq!(2, body["agreement"]["bullish"]);
+    assert_eq!(1, body["agreement"]["bearish"]);
+    assert_eq!("mixed", body["agreement"]["consensus"]);
+    assert_eq!("rsi", body["meta"]["indicator"]);
+    assert_eq!(14, body["meta"]["period"]);
+}
+
+#[tokio::test]
+async fn agreeing_intervals_reach_a_consensus() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_interval(&app.upstream, "hour", trending_body(3600, 40, 1.0)).await;
+    mount_interval(&app.upstream, "day", trending_body(86400, 40, 1.0)).await;
+
+    let body: serde_json::Value = app
+        .get("/multi_timeframe/btc/ema?intervals=hour,day")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!("bullish", body["agreement"]["consensus"]);
+    assert_eq!(1.0, body["agreement"]["ratio"]);
+    assert_eq!("exponential_moving_average", body["meta"]["indicator"]);
+}
+
+#[tokio::test]
+async fn values_match_the_single_interval_route() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_interval(&app.upstream, "hour", ohlc_body(30)).await;
+
+    let single: serde_json::Value = app
+        .get("/stochastic_oscillator/btc?interval=hour")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let multi: serde_json::Value = app
+        .get("/multi_timeframe/btc/stochastic_oscillator?intervals=hour")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(single["data"], multi["data"]["hour"]["value"]);
+}
+
+#[tokio::test]
+async fn the_default_limit_covers_a_long_period() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("interval", "day"))
+        // The EMA warms up over twice its period.
+        .and(query_param("limit", "400"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(trending_body(86400, 400, 1.0)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app
+        .get("/multi_timeframe/btc/ema?intervals=day&period=200")
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn invalid_parameters_are_rejected() {
+    let app = spawn_app().await;
+
+    for (route, parameter) in [
+        (
+            "/multi_timeframe/btc/rsi?intervals=hour,2hours",
+            "intervals",
+        ),
+        ("/multi_timeframe/btc/rsi?period=1", "period"),
+        ("/multi_timeframe/btc/macd", "path"),
+    ] {
+        let response = app.get(route).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", route);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, body["details"]["parameter"], "{}", route);
+    }
+}
@@ -0,0 +1,116 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, ohlc_body, spawn_app, BTC_UUID};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn interval_and_limit_are_forwarded_upstream() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("interval", "hour"))
+        .and(query_param("limit", "40"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(40)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc?interval=hour&limit=40").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("hour", body["meta"]["interval"]);
+    assert_eq!(40, body["meta"]["candles"]);
+}
+
+#[tokio::test]
+async fn the_provider_defaults_are_echoed_when_nothing_is_asked() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("interval", "day"))
+        .and(query_param("limit", "30"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(30)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/aroon_oscillator/btc").await;
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("day", body["meta"]["interval"]);
+    assert!(body["meta"]["from"].as_i64().unwrap() < body["meta"]["to"].as_i64().unwrap());
+}
+
+#[tokio::test]
+async fn candles_outside_the_time_range_are_dropped() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    let body = ohlc_body(60);
+    let candles = body["data"]["ohlc"].as_array().unwrap();
+    // Newest first: keep the candles 5 to 44 included, as many as the EMA needs.
+    let from = candles[44]["startingAt"].as_i64().unwrap();
+    let to = candles[5]["endingAt"].as_i64().unwrap();
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("from", from.to_string()))
+        .and(query_param("to", to.to_string()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body.clone()))
+        .mount(&app.upstream)
+        .await;
+
+    let response = app
+        .get(&format!(
+            "/exponential_moving_average/btc?from={}&to={}",
+            from, to
+        ))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(40, body["meta"]["candles"]);
+    assert_eq!(from, body["meta"]["from"]);
+    assert_eq!(to, body["meta"]["to"]);
+}
+
+#[tokio::test]
+async fn different_intervals_are_cached_separately() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(ohlc_body(30)))
+        .expect(2)
+        .mount(&app.upstream)
+        .await;
+
+    app.get("/stochastic_oscillator/btc?interval=hour").await;
+    app.get("/stochastic_oscillator/btc?interval=week").await;
+    app.get("/stochastic_oscillator/btc?interval=week").await;
+}
+
+#[tokio::test]
+async fn invalid_ohlc_queries_are_rejected() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    for (query, parameter) in [
+        ("interval=fortnight", "query"),
+        ("limit=0", "limit"),
+        ("limit=5001", "limit"),
+        ("from=200&to=100", "from"),
+        ("from=yesterday", "query"),
+    ] {
+        let response = app
+            .get(&format!("/fibonacci_retracement/btc/u?{}", query))
+            .await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", query);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, body["details"]["parameter"], "{}", query);
+    }
+}
@@ -0,0 +1,166 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, spawn_app, spawn_app_with, TestApp, BTC_UUID};
+use serde_json::Value;
+use taindicators::auth::hash_key;
+use taindicators::configuration::ApiKeySettings;
+
+impl TestApp {
+    async fn specification(&self) -> Value {
+        let response = self.get("/openapi.json").await;
+        assert_eq!(200, response.status().as_u16());
+        response.json().await.unwrap()
+    }
+}
+
+/// The names of the schemas referenced anywhere in `value`.
+fn references(value: &Value, found: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => found.push(
+                        reference
+                            .trim_start_matches("#/components/schemas/")
+                            .to_owned(),
+                    ),
+                    _ => references(value, found),
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| references(value, found)),
+        _ => {}
+    }
+}
+
+/// The names of the properties of a schema.
+fn properties(specification: &Value, schema: &str) -> Vec<String> {
+    specification["components"]["schemas"][schema]["properties"]
+        .as_object()
+        .unwrap_or_else(|| panic!("{} has no properties", schema))
+        .keys()
+        .cloned()
+        .collect()
+}
+
+#[tokio::test]
+async fn every_route_is_documented() {
+    let app = spawn_app().await;
+
+    let specification = app.specification().await;
+
+    assert!(specification["openapi"].as_str().unwrap().starts_with("3."));
+    for (path, method) in [
+        ("/health_check", "get"),
+        ("/readiness", "get"),
+        ("/metrics", "get"),
+        ("/admin/cache", "get"),
+        ("/admin/cache", "delete"),
+        ("/admin/cache/{kind}", "delete"),
+        ("/simple_moving_average/{coin}/{time}", "get"),
+        ("/fibonacci_retracement/{coin}/{market}", "get"),
+        ("/fibonacci_extension/{coin}/{market}", "get"),
+        ("/rsi/{coin}", "get"),
+        ("/aroon_oscillator/{coin}", "get"),
+        ("/stochastic_oscillator/{coin}", "get"),
+        ("/exponential_moving_average/{coin}", "get"),
+        ("/multi_timeframe/{coin}/{indicator}", "get"),
+        ("/batch", "post"),
+        ("/screener", "post"),
+        ("/evaluate/{coin}", "get"),
+        ("/signals/{coin}", "get"),
+        ("/divergences/{coin}", "get"),
+        ("/backtest", "post"),
+        ("/optimize", "post"),
+        ("/live", "get"),
+        ("/events/{coin}", "get"),
+        ("/alerts", "post"),
+        ("/alerts", "get"),
+        ("/alerts/{id}", "get"),
+        ("/alerts/{id}", "put"),
+        ("/alerts/{id}", "delete"),
+        ("/alerts/{id}/deliveries", "get"),
+    ] {
+        let operation = &specification["paths"][path][method];
+        assert!(operation.is_object(), "{} {}", method, path);
+        assert_eq!(
+            "#/components/schemas/ErrorBody",
+            operation["responses"]["default"]["content"]["application/json"]["schema"]["$ref"],
+            "{} {}",
+            method,
+            path
+        );
+    }
+}
+
+#[tokio::test]
+async fn every_referenced_schema_is_defined() {
+    let app = spawn_app().await;
+
+    let specification = app.specification().await;
+
+    let mut found = vec![];
+    references(&specification, &mut found);
+    assert!(found.contains(&"RsiResponse".to_owned()));
+    for schema in found {
+        assert!(
+            specification["components"]["schemas"][&schema].is_object(),
+            "{}",
+            schema
+        );
+    }
+}
+
+#[tokio::test]
+async fn responses_have_the_documented_fields() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    let specification = app.specification().await;
+
+    let rsi: Value = app
+        .get("/rsi/btc?interval=hour")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let error: Value = app.get("/rsi/eth").await.json().await.unwrap();
+
+    for (body, schema) in [
+        (&rsi, "RsiResponse"),
+        (&rsi["meta"], "OhlcMeta"),
+        (&error, "ErrorBody"),
+    ] {
+        let documented = properties(&specification, schema);
+        for field in body.as_object().unwrap().keys() {
+            assert!(documented.contains(field), "{}.{}", schema, field);
+        }
+    }
+}
+
+#[tokio::test]
+async fn the_docs_page_renders_the_specification() {
+    let app = spawn_app_with(|settings| {
+        settings.auth.enabled = true;
+        settings.auth.keys = vec![ApiKeySettings {
+            id: "tests".to_owned(),
+            secret_hash: hash_key("a-test-api-key"),
+            requests_per_second: None,
+            burst: None,
+            daily_quota: None,
+            scopes: vec![],
+        }];
+    })
+    .await;
+
+    let response = app.get("/docs").await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(response.text().await.unwrap().contains("/openapi.json"));
+    // Both are public by default, the browser having no key to send.
+    app.specification().await;
+}
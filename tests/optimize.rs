@@ -0,0 +1,236 @@
+mod helpers;
+
+use helpers::{spawn_app, TestApp};
+use serde_json::json;
+use std::time::Duration;
+use taindicators::backtest::Costs;
+use taindicators::optimize::{Objective, Optimization, Parameter, Search, WalkForward, Workers};
+
+impl TestApp {
+    async fn optimize(&self, body: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/optimize", self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+/// Hourly candles oscillating around 100, oldest first.
+fn candles(count: usize) -> serde_json::Value {
+    (0..count)
+        .map(|i| {
+            let close = 100.0 + 10.0 * (i as f64 / 7.0).sin() + (i % 3) as f64;
+            json!({
+                "startingAt": i * 3600, "endingAt": (i + 1) * 3600,
+                "open": close, "high": close + 1.0, "low": close - 1.0, "close": close, "avg": close
+            })
+        })
+        .collect()
+}
+
+fn optimization(entry: &str, exit: &str) -> Optimization {
+    Optimization {
+        entry: entry.to_owned(),
+        exit: exit.to_owned(),
+        costs: Costs::default(),
+        initial_capital: 1000.0,
+        objective: Objective::Sharpe,
+        walk_forward: WalkForward::default(),
+    }
+}
+
+fn parameter(name: &str, min: f64, max: f64, step: f64) -> Parameter {
+    Parameter {
+        name: name.to_owned(),
+        min,
+        max,
+        step,
+    }
+}
+
+#[test]
+fn a_grid_holds_every_combination() {
+    let optimization = optimization("rsi({period}) < {oversold}", "rsi({period}) > 70");
+
+    let candidates = optimization
+        .candidates(
+            &[
+                parameter("period", 10.0, 14.0, 2.0),
+                parameter("oversold", 20.0, 30.0, 5.0),
+            ],
+            Search::Grid,
+            0,
+            0,
+        )
+        .unwrap();
+
+    assert_eq!(9, candidates.len());
+    assert_eq!(10.0, candidates[0]["period"]);
+    assert_eq!(20.0, candidates[0]["oversold"]);
+    assert_eq!(14.0, candidates[8]["period"]);
+    assert_eq!(30.0, candidates[8]["oversold"]);
+    assert_eq!(13, optimization.lookback(&candidates).unwrap());
+}
+
+#[test]
+fn a_seeded_random_search_is_repeatable() {
+    let optimization = optimization("close > sma(close, {fast})", "close < sma(close, {slow})");
+    let parameters = [
+        parameter("fast", 2.0, 50.0, 1.0),
+        parameter("slow", 20.0, 200.0, 1.0),
+    ];
+
+    let first = optimization
+        .candidates(&parameters, Search::Random, 25, 7)
+        .unwrap();
+    let second = optimization
+        .candidates(&parameters, Search::Random, 25, 7)
+        .unwrap();
+
+    assert_eq!(25, first.len());
+    assert_eq!(first, second);
+    for (i, candidate) in first.iter().enumerate() {
+        assert!(!first[i + 1..].contains(candidate));
+    }
+}
+
+#[test]
+fn unbounded_parameters_are_rejected() {
+    let optimization = optimization("close > sma(close, {period})", "close < 0");
+
+    for parameter in [
+        parameter("period", 2.0, f64::INFINITY, 1.0),
+        parameter("period", f64::NEG_INFINITY, 4.0, 1.0),
+        parameter("period", 2.0, 4.0, f64::INFINITY),
+        parameter("period", 2.0, 4.0, f64::NAN),
+        parameter("period", 2.0, 4.0, 1e-300),
+        parameter("period", -1e308, 1e308, 1.0),
+    ] {
+        let candidates =
+            optimization.candidates(std::slice::from_ref(&parameter), Search::Grid, 0, 0);
+
+        assert!(candidates.is_err(), "{:?}", parameter);
+    }
+}
+
+#[tokio::test]
+async fn candidates_are_ranked_in_sample_and_checked_out_of_sample() {
+    let app = spawn_app().await;
+
+    let response = app
+        .optimize(json!({
+            "candles": candles(300),
+            "entry": "close > sma(close, {period})",
+            "exit": "close < sma(close, {period})",
+            "parameters": [{ "name": "period", "min": 2, "max": 10, "step": 2 }],
+            "objective": "total_return",
+            "top": 3
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let best = body["data"]["best"].as_array().unwrap();
+    assert_eq!(3, best.len());
+    let in_sample: Vec<f64> = best
+        .iter()
+        .map(|candidate| candidate["inSample"].as_f64().unwrap())
+        .collect();
+    assert!(in_sample.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert!(best[0]["outOfSample"].is_number());
+    assert!(best[0]["outOfSampleStdDev"].as_f64().unwrap() >= 0.0);
+
+    let folds = body["data"]["folds"].as_array().unwrap();
+    assert_eq!(3, folds.len());
+    // Folds of 100 candles, 70 of them in sample.
+    assert_eq!(0, folds[0]["inSample"]["from"]);
+    assert_eq!(70, folds[0]["inSample"]["candles"]);
+    assert_eq!(70 * 3600, folds[0]["outOfSample"]["from"]);
+    assert_eq!(30, folds[0]["outOfSample"]["candles"]);
+    assert!(folds[0]["parameters"]["period"].is_number());
+
+    assert_eq!(5, body["meta"]["candidates"]);
+    assert_eq!("grid", body["meta"]["search"]);
+    assert_eq!("total_return", body["meta"]["objective"]);
+    assert_eq!(300, body["meta"]["bars"]);
+}
+
+#[tokio::test]
+async fn invalid_searches_are_rejected() {
+    let app = spawn_app().await;
+    let few = candles(30);
+    let request = |changes: serde_json::Value| {
+        let mut body = json!({
+            "candles": few,
+            "entry": "close > sma(close, {period})",
+            "exit": "close < 0",
+            "parameters": [{ "name": "period", "min": 2, "max": 4, "step": 1 }]
+        });
+        for (key, value) in changes.as_object().unwrap() {
+            body[key] = value.clone();
+        }
+        body
+    };
+
+    for (body, parameter) in [
+        (request(json!({ "parameters": [] })), "parameters"),
+        (
+            request(json!({ "parameters": [{ "name": "fast", "min": 2, "max": 4, "step": 1 }] })),
+            "parameters",
+        ),
+        (
+            request(json!({ "parameters": [{ "name": "period", "min": 4, "max": 2, "step": 1 }] })),
+            "parameters",
+        ),
+        (
+            request(json!({ "parameters": [{ "name": "period", "min": 0, "max": 2, "step": 1 }] })),
+            "entry",
+        ),
+        (
+            request(
+                json!({ "parameters": [{ "name": "period", "min": 2, "max": 4, "step": 1e-300 }] }),
+            ),
+            "parameters",
+        ),
+        (
+            request(json!({ "search": "random", "samples": 0 })),
+            "samples",
+        ),
+        (request(json!({ "folds": 11 })), "folds"),
+        (request(json!({ "inSample": 0.95 })), "inSample"),
+    ] {
+        let response = app.optimize(body.clone()).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", body);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", body);
+    }
+
+    let response = app.optimize(request(json!({ "folds": 10 }))).await;
+    assert_eq!(422, response.status().as_u16());
+    let response = app
+        .optimize(request(json!({ "exit": "close[5000][5000] < 0" })))
+        .await;
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_searches_share_the_workers() {
+    let workers = Workers::new(3);
+
+    let first = workers.reserve(8).await;
+    assert_eq!(3, first.num_permits());
+    // Every thread is taken: the next search waits for the first one to end.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), workers.reserve(8))
+            .await
+            .is_err()
+    );
+
+    drop(first);
+    let second = workers.reserve(2).await;
+    assert_eq!(2, second.num_permits());
+    assert_eq!(1, workers.reserve(8).await.num_permits());
+}
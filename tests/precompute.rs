@@ -0,0 +1,120 @@
+mod helpers;
+
+use std::time::Duration;
+
+use helpers::{mount_coin, mount_ohlc, ohlc_body, spawn_app_with, TestApp, BTC_UUID};
+use serde_json::json;
+use taindicators::configuration::WatchedCoin;
+use taindicators::indicators::Indicator;
+use taindicators::interval::Interval;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn spawn_watching_app() -> TestApp {
+    spawn_app_with(|settings| {
+        settings.precompute.watchlist = vec![WatchedCoin {
+            coin: "btc".to_owned(),
+            indicators: vec![Indicator::Rsi],
+            interval: Interval::Hour,
+        }];
+        settings.precompute.close_delay_milliseconds = 10;
+    })
+    .await
+}
+
+impl TestApp {
+    /// The body of `route` once `done` holds for it, failing after a few seconds.
+    async fn get_once(
+        &self,
+        route: &str,
+        done: impl Fn(&serde_json::Value) -> bool,
+    ) -> serde_json::Value {
+        for _ in 0..100 {
+            let response = self.get(route).await;
+            if response.status().is_success() {
+                let body: serde_json::Value = response.json().await.unwrap();
+                if done(&body) {
+                    return body;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("`{}` never answered as expected.", route);
+    }
+}
+
+fn precomputed(body: &serde_json::Value) -> bool {
+    body["meta"]["computedAt"].is_i64()
+}
+
+#[tokio::test]
+async fn watched_indicators_are_served_without_upstream_calls() {
+    let app = spawn_watching_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    let first = app.get_once("/rsi/btc?interval=hour", precomputed).await;
+
+    app.delete("/admin/cache").await;
+    app.upstream.reset().await;
+    let response = app.get("/rsi/btc?interval=hour").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(first, body);
+    assert_eq!("hour", body["meta"]["interval"]);
+    let now = chrono::Utc::now().timestamp();
+    assert!((now - 5..=now).contains(&body["meta"]["computedAt"].as_i64().unwrap()));
+    // Not watched, so computed on request.
+    let response = app
+        .get("/exponential_moving_average/btc?interval=hour")
+        .await;
+    assert!(!response.status().is_success());
+}
+
+#[tokio::test]
+async fn other_queries_are_computed_on_request() {
+    let app = spawn_watching_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 60).await;
+    app.get_once("/rsi/btc?interval=hour", precomputed).await;
+
+    for route in ["/rsi/btc?interval=hour&limit=50", "/rsi/btc?interval=day"] {
+        let response = app.get(route).await;
+
+        assert_eq!(200, response.status().as_u16(), "{}", route);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["meta"].get("computedAt").is_none(), "{}", route);
+    }
+}
+
+#[tokio::test]
+async fn values_are_refreshed_when_their_candle_closes() {
+    let app = spawn_watching_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    let mut closing = ohlc_body(60);
+    closing["data"]["ohlc"][0]["endingAt"] = json!(chrono::Utc::now().timestamp() + 1);
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(closing))
+        .mount(&app.upstream)
+        .await;
+    let before = app.get_once("/rsi/btc?interval=hour", precomputed).await;
+
+    app.upstream.reset().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    let mut next = ohlc_body(60);
+    next["data"]["ohlc"][0]["close"] = json!("104");
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(next))
+        .mount(&app.upstream)
+        .await;
+    let after = app
+        .get_once("/rsi/btc?interval=hour", |body| {
+            precomputed(body) && body["data"] != before["data"]
+        })
+        .await;
+
+    assert!(after["data"].as_f64().unwrap() > before["data"].as_f64().unwrap());
+    assert!(after["meta"]["computedAt"].as_i64() >= before["meta"]["computedAt"].as_i64());
+}
@@ -0,0 +1,98 @@
+mod helpers;
+
+use helpers::{spawn_app, spawn_app_with, TestApp};
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn mount_probe(app: &TestApp, status: u16) {
+    Mock::given(method("GET"))
+        .and(path("/coins"))
+        .and(query_param("limit", "1"))
+        .respond_with(
+            ResponseTemplate::new(status)
+                .set_body_json(json!({ "status": "success", "data": { "coins": [] } })),
+        )
+        .mount(&app.upstream)
+        .await;
+}
+
+#[tokio::test]
+async fn readiness_reports_every_dependency_when_ready() {
+    let app = spawn_app().await;
+    Mock::given(method("GET"))
+        .and(path("/coins"))
+        .and(header("X-RapidAPI-Key", "test-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "status": "success" })))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/readiness").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("ready", report["status"]);
+    for check in ["configuration", "upstream", "cache", "stores"] {
+        assert_eq!("up", report["checks"][check]["status"], "{}", check);
+    }
+    assert_eq!(false, report["checks"]["upstream"]["cached"]);
+    assert_eq!("closed", report["checks"]["upstream"]["circuit"]);
+}
+
+#[tokio::test]
+async fn a_rejected_token_makes_the_service_not_ready() {
+    let app = spawn_app().await;
+    mount_probe(&app, 401).await;
+
+    let response = app.get("/readiness").await;
+
+    assert_eq!(503, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("not_ready", report["status"]);
+    assert_eq!("down", report["checks"]["upstream"]["status"]);
+    assert!(report["checks"]["upstream"]["error"]
+        .as_str()
+        .unwrap()
+        .contains("rejected the API token"));
+    assert_eq!("up", report["checks"]["configuration"]["status"]);
+}
+
+#[tokio::test]
+async fn the_upstream_probe_is_reused_within_its_ttl() {
+    let app = spawn_app().await;
+    mount_probe(&app, 200).await;
+
+    app.get("/readiness").await;
+    let response = app.get("/readiness").await;
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(true, report["checks"]["upstream"]["cached"]);
+    assert_eq!(1, app.upstream.received_requests().await.unwrap().len());
+}
+
+#[tokio::test]
+async fn configuration_problems_are_reported() {
+    let app = spawn_app_with(|settings| {
+        settings.crypto_client.auth_token = Secret::new(" ".to_owned());
+        settings.alerts.evaluation_interval_milliseconds = 0;
+    })
+    .await;
+    mount_probe(&app, 200).await;
+
+    let response = app.get("/readiness").await;
+
+    assert_eq!(503, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("down", report["checks"]["configuration"]["status"]);
+    assert_eq!(
+        json!([
+            "crypto_client.auth_token is empty",
+            "alerts.evaluation_interval_milliseconds is 0"
+        ]),
+        report["checks"]["configuration"]["problems"]
+    );
+    // Liveness doesn't care.
+    assert_eq!(200, app.get("/health_check").await.status().as_u16());
+}
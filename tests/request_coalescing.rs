@@ -0,0 +1,81 @@
+mod helpers;
+
+use futures::future::join_all;
+use helpers::{ohlc_body, search_suggestions_body, spawn_app_with, BTC_UUID};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use taindicators::single_flight::SingleFlight;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn concurrent_requests_share_one_upstream_call() {
+    // Without a cache every request would have to go upstream on its own.
+    let app = spawn_app_with(|settings| settings.crypto_client.cache.max_entries = 0).await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(search_suggestions_body("BTC", BTC_UUID))
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(ohlc_body(30))
+                .set_delay(Duration::from_millis(200)),
+        )
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let responses = join_all((0..10).map(|_| app.get("/rsi/btc"))).await;
+
+    for response in responses {
+        assert_eq!(200, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn waiting_callers_receive_the_leaders_result() {
+    let flights = SingleFlight::new();
+    let calls = AtomicUsize::new(0);
+
+    let results = join_all((0..5).map(|_| {
+        flights.run("btc", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            42
+        })
+    }))
+    .await;
+
+    assert_eq!(vec![42; 5], results);
+    assert_eq!(1, calls.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn a_waiting_caller_takes_over_when_the_leader_is_cancelled() {
+    let flights = SingleFlight::new();
+
+    let leader = flights.run("btc", || async {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        1
+    });
+    let follower = async {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        flights.run("btc", || async { 2 }).await
+    };
+
+    let (leader, follower) = tokio::join!(
+        tokio::time::timeout(Duration::from_millis(50), leader),
+        follower
+    );
+
+    assert!(leader.is_err());
+    assert_eq!(2, follower);
+}
@@ -0,0 +1,71 @@
+mod helpers;
+
+use helpers::{spawn_app, TestApp};
+
+impl TestApp {
+    async fn get_with_request_id(&self, route: &str, request_id: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", self.address, route))
+            .header("X-Request-Id", request_id)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+fn request_id(response: &reqwest::Response) -> String {
+    response.headers()["X-Request-Id"]
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn every_response_gets_its_own_request_id() {
+    let app = spawn_app().await;
+
+    let first = request_id(&app.get("/health_check").await);
+    let second = request_id(&app.get("/health_check").await);
+
+    assert_eq!(32, first.len());
+    assert!(first.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(first, second);
+}
+
+#[tokio::test]
+async fn an_incoming_request_id_is_propagated() {
+    let app = spawn_app().await;
+
+    let response = app
+        .get_with_request_id("/health_check", "edge-7f3a.42")
+        .await;
+
+    assert_eq!("edge-7f3a.42", request_id(&response));
+}
+
+#[tokio::test]
+async fn an_unreasonable_incoming_request_id_is_replaced() {
+    let app = spawn_app().await;
+
+    for invalid in ["", "with spaces", &"x".repeat(129)] {
+        let response = app.get_with_request_id("/health_check", invalid).await;
+
+        let replaced = request_id(&response);
+        assert_ne!(invalid, replaced);
+        assert_eq!(32, replaced.len());
+    }
+}
+
+#[tokio::test]
+async fn error_responses_include_the_request_id() {
+    let app = spawn_app().await;
+
+    for route in ["/not/a/route", "/rsi/btc?limit=0", "/alerts/1"] {
+        let response = app.get_with_request_id(route, "client-chosen-id").await;
+
+        assert!(response.status().is_client_error(), "{}", route);
+        assert_eq!("client-chosen-id", request_id(&response), "{}", route);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!("client-chosen-id", error["requestId"], "{}", route);
+    }
+}
@@ -0,0 +1,239 @@
+mod helpers;
+
+use helpers::{mount_coin, spawn_app, BTC_UUID};
+use serde_json::json;
+use taindicators::crypto_client::ParsedOhlc;
+use taindicators::interval::Interval;
+use taindicators::resample::{fill_gaps, find_gaps, resample, Gap, GapPolicy};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+const HOUR: i64 = 3600;
+
+fn candle(starting_at: i64, open: f64, high: f64, low: f64, close: f64) -> ParsedOhlc {
+    ParsedOhlc {
+        starting_at,
+        ending_at: starting_at + HOUR,
+        open,
+        high,
+        low,
+        close,
+        avg: (open + close) / 2.0,
+        volume: Some(10.0),
+        filled: false,
+    }
+}
+
+/// `count` hourly candles starting at `start`, newest first.
+fn hourly(start: i64, count: i64) -> Vec<ParsedOhlc> {
+    (0..count)
+        .rev()
+        .map(|i| {
+            let price = 100.0 + i as f64;
+            candle(
+                start + i * HOUR,
+                price,
+                price + 5.0,
+                price - 5.0,
+                price + 1.0,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn non_native_intervals_are_built_from_the_largest_native_divisor() {
+    assert_eq!(Interval::Hour, Interval::FourHours.native_source());
+    assert_eq!(Interval::Hour, Interval::TwelveHours.native_source());
+    assert_eq!(Interval::Day, Interval::ThreeDays.native_source());
+    assert_eq!(Interval::Minute, Interval::FifteenMinutes.native_source());
+    assert_eq!(Interval::EightHours, Interval::EightHours.native_source());
+}
+
+#[test]
+fn hourly_candles_aggregate_into_four_hour_candles() {
+    let resampled = resample(&hourly(0, 8), Interval::FourHours);
+
+    assert_eq!(2, resampled.len());
+    // Newest first.
+    let newest = &resampled[0];
+    assert_eq!(4 * HOUR, newest.starting_at);
+    assert_eq!(8 * HOUR, newest.ending_at);
+    assert_eq!(104.0, newest.open);
+    assert_eq!(108.0, newest.close);
+    assert_eq!(112.0, newest.high);
+    assert_eq!(99.0, newest.low);
+    assert_eq!(Some(40.0), newest.volume);
+    assert_eq!(
+        [100.5, 101.5, 102.5, 103.5].iter().sum::<f64>() / 4.0,
+        resampled[1].avg
+    );
+}
+
+#[test]
+fn resampling_can_be_chained() {
+    let four_hours = resample(&hourly(0, 48), Interval::FourHours);
+    let daily_from_four_hours = resample(&four_hours, Interval::Day);
+    let daily_from_hours = resample(&hourly(0, 48), Interval::Day);
+
+    assert_eq!(daily_from_hours, daily_from_four_hours);
+    assert_eq!(2, daily_from_hours.len());
+}
+
+#[test]
+fn an_incomplete_oldest_bucket_is_dropped_but_the_current_one_is_kept() {
+    // From 02:00 to 09:00: [02:00, 04:00) is partial, [08:00, 12:00) still open.
+    let resampled = resample(&hourly(2 * HOUR, 7), Interval::FourHours);
+
+    let starts: Vec<i64> = resampled.iter().map(|candle| candle.starting_at).collect();
+    assert_eq!(vec![8 * HOUR, 4 * HOUR], starts);
+}
+
+#[test]
+fn missing_candles_are_detected() {
+    let mut candles = hourly(0, 10);
+    candles.retain(|candle| ![3, 4, 7].contains(&(candle.starting_at / HOUR)));
+
+    let gaps = find_gaps(&candles, Interval::Hour);
+
+    assert_eq!(
+        vec![
+            Gap {
+                from: 3 * HOUR,
+                to: 5 * HOUR,
+                missing: 2
+            },
+            Gap {
+                from: 7 * HOUR,
+                to: 8 * HOUR,
+                missing: 1
+            },
+        ],
+        gaps
+    );
+}
+
+#[test]
+fn months_of_different_lengths_are_not_gaps() {
+    let day = 24 * HOUR;
+    let months: Vec<ParsedOhlc> = [0, 31, 59, 90]
+        .iter()
+        .map(|days| candle(days * day, 1.0, 1.0, 1.0, 1.0))
+        .collect();
+
+    assert!(find_gaps(&months, Interval::Month).is_empty());
+}
+
+#[test]
+fn gaps_are_handled_per_policy() {
+    let mut candles = hourly(0, 4);
+    candles.remove(1); // 02:00
+
+    let (skipped, skipped_gaps) = fill_gaps(candles.clone(), Interval::Hour, GapPolicy::Skip);
+    let (marked, marked_gaps) = fill_gaps(candles.clone(), Interval::Hour, GapPolicy::Mark);
+    let (filled, filled_gaps) = fill_gaps(candles.clone(), Interval::Hour, GapPolicy::ForwardFill);
+
+    assert_eq!(candles, skipped);
+    assert!(skipped_gaps.is_empty());
+    assert_eq!(candles, marked);
+    assert_eq!(1, marked_gaps.len());
+    assert_eq!(marked_gaps, filled_gaps);
+
+    assert_eq!(4, filled.len());
+    let synthetic = &filled[1];
+    assert_eq!(2 * HOUR, synthetic.starting_at);
+    assert!(synthetic.filled);
+    assert_eq!(candles[1].close, synthetic.open);
+    assert_eq!(candles[1].close, synthetic.close);
+    assert_eq!(Some(0.0), synthetic.volume);
+}
+
+#[tokio::test]
+async fn a_non_native_interval_is_resampled_from_the_native_one() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    let now = chrono::Utc::now().timestamp();
+    let current_hour = now - now % HOUR;
+    let candles: Vec<serde_json::Value> = (0..123)
+        .map(|i| {
+            let starting_at = current_hour - i * HOUR;
+            json!({
+                "startingAt": starting_at, "endingAt": starting_at + HOUR,
+                "open": "1", "high": "2", "low": "0.5", "close": "1.5", "avg": "1.2"
+            })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("interval", "hour"))
+        .and(query_param("limit", "123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success",
+            "data": { "ohlc": candles }
+        })))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc?interval=4hours").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("4hours", body["meta"]["interval"]);
+    assert_eq!("hour", body["meta"]["resampledFrom"]);
+    assert_eq!(30, body["meta"]["candles"]);
+}
+
+#[tokio::test]
+async fn gaps_are_reported_when_marked() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    let mut body = helpers::ohlc_body(30);
+    body["data"]["ohlc"].as_array_mut().unwrap().remove(10);
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body))
+        .mount(&app.upstream)
+        .await;
+
+    let marked: serde_json::Value = app
+        .get("/rsi/btc?interval=hour&gaps=mark")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let filled: serde_json::Value = app
+        .get("/rsi/btc?interval=hour&gaps=forward_fill")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(1, marked["meta"]["gaps"].as_array().unwrap().len());
+    assert_eq!(1, marked["meta"]["gaps"][0]["missing"]);
+    assert_eq!(29, marked["meta"]["candles"]);
+    assert_eq!(30, filled["meta"]["candles"]);
+}
+
+#[tokio::test]
+async fn a_limit_needing_more_native_candles_than_the_upstream_serves_is_rejected() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("interval", "hour"))
+        .and(query_param("limit", "4999"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::ohlc_body(4999)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc?interval=4hours&limit=1250").await;
+
+    assert_eq!(400, response.status().as_u16());
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("limit", error["details"]["parameter"]);
+    assert!(error["message"].as_str().unwrap().contains("1249"));
+    let response = app.get("/rsi/btc?interval=4hours&limit=1249").await;
+    assert_eq!(200, response.status().as_u16());
+}
@@ -0,0 +1,189 @@
+mod helpers;
+
+use helpers::{search_suggestions_body, spawn_app, TestApp};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+impl TestApp {
+    async fn screen(&self, body: serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/screener", self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+/// 30 daily candles of a coin whose close moves by `step` every day, ending at `last`.
+async fn mount_trending_coin(app: &TestApp, symbol: &str, last: f64, step: f64) {
+    let uuid = format!("{}-uuid", symbol);
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .and(query_param("query", symbol))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(search_suggestions_body(symbol, &uuid)),
+        )
+        .mount(&app.upstream)
+        .await;
+    let now = chrono::Utc::now().timestamp();
+    let current_start = now - now % 86400;
+    let candles: Vec<serde_json::Value> = (0..30)
+        .map(|i| {
+            let starting_at = current_start - i * 86400;
+            let close = last - step * i as f64;
+            json!({
+                "startingAt": starting_at, "endingAt": starting_at + 86400,
+                "open": format!("{}", close), "high": format!("{}", close + 1.0),
+                "low": format!("{}", close - 1.0), "close": format!("{}", close),
+                "avg": format!("{}", close)
+            })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", uuid)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success", "data": { "ohlc": candles }
+        })))
+        .mount(&app.upstream)
+        .await;
+}
+
+#[tokio::test]
+async fn coins_matching_the_filter_are_returned_sorted() {
+    let app = spawn_app().await;
+    // Falling coins have an RSI of 0, rising ones of 100.
+    mount_trending_coin(&app, "AAA", 100.0, -1.0).await;
+    mount_trending_coin(&app, "BBB", 500.0, -2.0).await;
+    mount_trending_coin(&app, "CCC", 100.0, 1.0).await;
+
+    let response = app
+        .screen(json!({
+            "coins": ["aaa", "bbb", "ccc"],
+            "filter": "rsi < 30 and price > 50",
+            "sort": [{ "key": "price", "order": "desc" }]
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let coins: Vec<&str> = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|coin| coin["coin"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["BBB", "AAA"], coins);
+    assert_eq!(0.0, body["data"][0]["values"]["rsi"]);
+    assert_eq!(500.0, body["data"][0]["values"]["price"]);
+    assert_eq!(3, body["meta"]["universe"]);
+    assert_eq!(2, body["meta"]["matched"]);
+}
+
+#[tokio::test]
+async fn every_field_of_the_expressions_can_be_screened_on() {
+    let app = spawn_app().await;
+    mount_trending_coin(&app, "AAA", 100.0, -1.0).await;
+    mount_trending_coin(&app, "BBB", 500.0, -2.0).await;
+
+    let response = app
+        .screen(json!({
+            "coins": ["aaa", "bbb"],
+            "filter": "avg > 200 and open > 0",
+            "sort": [{ "key": "volume", "order": "desc" }]
+        }))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, body["data"].as_array().unwrap().len());
+    assert_eq!("BBB", body["data"][0]["coin"]);
+    assert_eq!(500.0, body["data"][0]["values"]["avg"]);
+    // The upstream gives no volume for these candles.
+    assert!(body["data"][0]["values"]["volume"].is_null());
+}
+
+#[tokio::test]
+async fn the_universe_can_be_the_top_coins_by_market_cap() {
+    let app = spawn_app().await;
+    mount_trending_coin(&app, "AAA", 100.0, 1.0).await;
+    mount_trending_coin(&app, "BBB", 100.0, -1.0).await;
+    Mock::given(method("GET"))
+        .and(path("/coins"))
+        .and(query_param("orderBy", "marketCap"))
+        .and(query_param("limit", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success",
+            "data": { "coins": [
+                { "uuid": "AAA-uuid", "symbol": "AAA", "name": "Aaa" },
+                { "uuid": "BBB-uuid", "symbol": "BBB", "name": "Bbb" }
+            ]}
+        })))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let body: serde_json::Value = app
+        .screen(json!({ "top": 2, "filter": "price > ema_10", "limit": 5 }))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(1, body["data"].as_array().unwrap().len());
+    assert_eq!("AAA", body["data"][0]["coin"]);
+    assert!(body["data"][0]["values"]["ema_10"].is_number());
+}
+
+#[tokio::test]
+async fn coins_that_fail_are_reported_apart() {
+    let app = spawn_app().await;
+    mount_trending_coin(&app, "AAA", 100.0, -1.0).await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .and(query_param("query", "NOPE"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success", "data": { "coins": [] }
+        })))
+        .mount(&app.upstream)
+        .await;
+
+    let body: serde_json::Value = app
+        .screen(json!({ "coins": ["nope", "aaa"], "filter": "rsi < 30" }))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(1, body["data"].as_array().unwrap().len());
+    assert_eq!("nope", body["errors"][0]["coin"]);
+    assert_eq!("unknown_coin", body["errors"][0]["error"]["code"]);
+}
+
+#[tokio::test]
+async fn invalid_screens_are_rejected() {
+    let app = spawn_app().await;
+
+    for (body, parameter) in [
+        (json!({ "coins": ["btc"], "filter": "rsi <" }), "filter"),
+        (json!({ "coins": ["btc"], "filter": "rsi + 1" }), "filter"),
+        (json!({ "coins": ["btc"], "filter": "macd > 0" }), "filter"),
+        (
+            json!({ "coins": ["btc"], "filter": "rsi < 30", "sort": [{ "key": "momentum" }] }),
+            "sort",
+        ),
+        (json!({ "filter": "rsi < 30" }), "coins"),
+        (
+            json!({ "coins": ["btc"], "top": 10, "filter": "rsi < 30" }),
+            "coins",
+        ),
+        (json!({ "top": 1000, "filter": "rsi < 30" }), "coins"),
+    ] {
+        let response = app.screen(body.clone()).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", body);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", body);
+    }
+}
@@ -0,0 +1,169 @@
+mod helpers;
+
+use helpers::{mount_coin, spawn_app, TestApp, BTC_UUID};
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Hourly candles with the given closes, oldest first.
+async fn mount_closes(app: &TestApp, closes: &[f64]) {
+    let now = chrono::Utc::now().timestamp();
+    let current_start = now - now % 3600;
+    let candles: Vec<serde_json::Value> = closes
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, close)| {
+            let starting_at = current_start - i as i64 * 3600;
+            json!({
+                "startingAt": starting_at, "endingAt": starting_at + 3600,
+                "open": format!("{}", close), "high": format!("{}", close + 1.0),
+                "low": format!("{}", close - 1.0), "close": format!("{}", close),
+                "avg": format!("{}", close)
+            })
+        })
+        .collect();
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success", "data": { "ohlc": candles }
+        })))
+        .mount(&app.upstream)
+        .await;
+}
+
+/// Falling from 20 to 10, then back up to 20.
+fn v_shape() -> Vec<f64> {
+    (0..=20).map(|i| 10.0 + (i as f64 - 10.0).abs()).collect()
+}
+
+fn triggers(body: &serde_json::Value) -> Vec<(String, String)> {
+    body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|signal| {
+            (
+                signal["trigger"].as_str().unwrap().to_owned(),
+                signal["side"].as_str().unwrap().to_owned(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn a_reversal_triggers_a_golden_cross() {
+    let app = spawn_app().await;
+    mount_closes(&app, &v_shape()).await;
+
+    let response = app
+        .get("/signals/btc?signals=ma_crossover&ma_fast=3&ma_slow=5&limit=21")
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        vec![("golden_cross".to_owned(), "buy".to_owned())],
+        triggers(&body)
+    );
+    let signal = &body["data"][0];
+    assert_eq!("ma_crossover", signal["kind"]);
+    assert!(signal["values"]["fast"].as_f64() > signal["values"]["slow"].as_f64());
+    assert_eq!(json!(["ma_crossover"]), body["meta"]["signals"]);
+}
+
+#[tokio::test]
+async fn oscillators_report_entering_and_leaving_their_zones() {
+    let app = spawn_app().await;
+    mount_closes(&app, &v_shape()).await;
+
+    let body: serde_json::Value = app
+        .get("/signals/btc?signals=rsi&rsi_period=5&limit=21")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            ("exit_oversold".to_owned(), "buy".to_owned()),
+            ("enter_overbought".to_owned(), "neutral".to_owned()),
+        ],
+        triggers(&body)
+    );
+    assert_eq!(50.0, body["data"][0]["values"]["rsi"]);
+    assert_eq!(30.0, body["data"][0]["values"]["threshold"]);
+}
+
+#[tokio::test]
+async fn a_jump_breaks_out_of_the_bollinger_bands() {
+    let app = spawn_app().await;
+    let mut closes = vec![10.0; 24];
+    closes.push(20.0);
+    mount_closes(&app, &closes).await;
+
+    let body: serde_json::Value = app
+        .get("/signals/btc?signals=bollinger_breakout&limit=25")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![("upper_breakout".to_owned(), "buy".to_owned())],
+        triggers(&body)
+    );
+    assert_eq!(20.0, body["data"][0]["values"]["close"]);
+}
+
+#[tokio::test]
+async fn too_few_candles_for_a_detector_are_insufficient_data() {
+    let app = spawn_app().await;
+    mount_closes(&app, &[10.0; 25]).await;
+
+    let response = app
+        .get("/signals/btc?signals=bollinger_breakout,macd_cross&limit=25")
+        .await;
+
+    assert_eq!(422, response.status().as_u16());
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(35, error["details"]["required"]);
+    assert_eq!(25, error["details"]["available"]);
+}
+
+#[tokio::test]
+async fn the_default_limit_covers_the_longest_detector() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/ohlc", BTC_UUID)))
+        .and(query_param("limit", "230"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::ohlc_body(230)))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/signals/btc").await;
+
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn invalid_parameters_are_rejected() {
+    let app = spawn_app().await;
+
+    for (query, parameter) in [
+        ("signals=rsi,golden", "signals"),
+        ("ma_fast=50&ma_slow=20", "ma_fast"),
+        ("rsi_period=1", "rsi_period"),
+        ("rsi_oversold=80", "rsi_oversold"),
+        ("bollinger_width=0", "bollinger_width"),
+    ] {
+        let response = app.get(&format!("/signals/btc?{}", query)).await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", query);
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(parameter, error["details"]["parameter"], "{}", query);
+    }
+}
@@ -0,0 +1,126 @@
+mod helpers;
+
+use helpers::{mount_coin, spawn_app, BTC_UUID};
+use serde_json::json;
+use taindicators::indicators::simple_moving_average;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Prices newest first, one per hour, as Coinranking returns them.
+async fn mount_history(upstream: &MockServer, period: &str, prices: &[f64]) {
+    let history: Vec<serde_json::Value> = prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| {
+            json!({ "price": price.to_string(), "timestamp": 1_600_000_000 - i as i64 * 3600 })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path(format!("/coin/{}/history", BTC_UUID)))
+        .and(query_param("timePeriod", period))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "success",
+            "data": { "change": "1.0", "history": history }
+        })))
+        .mount(upstream)
+        .await;
+}
+
+#[test]
+fn the_moving_average_slides_over_the_values() {
+    assert_eq!(
+        vec![2.0, 3.0, 4.0],
+        simple_moving_average(&[1.0, 2.0, 3.0, 4.0, 5.0], 3)
+    );
+    assert_eq!(
+        vec![3.0],
+        simple_moving_average(&[1.0, 2.0, 3.0, 4.0, 5.0], 5)
+    );
+    assert!(simple_moving_average(&[1.0, 2.0], 3).is_empty());
+    assert!(simple_moving_average(&[1.0, 2.0], 0).is_empty());
+}
+
+#[tokio::test]
+async fn without_a_window_the_whole_history_is_averaged() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, "7d", &[4.0, 3.0, 2.0, 1.0]).await;
+
+    let response = app.get("/simple_moving_average/btc/7d").await;
+
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(2.5, body["data"]);
+    assert_eq!(1, body["series"].as_array().unwrap().len());
+    assert_eq!("7d", body["meta"]["period"]);
+    assert_eq!(4, body["meta"]["window"]);
+}
+
+#[tokio::test]
+async fn a_window_returns_the_moving_average_series_oldest_first() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, "24h", &[5.0, 4.0, 3.0, 2.0, 1.0]).await;
+
+    let response = app.get("/simple_moving_average/btc/24h?window=2").await;
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    let values: Vec<f64> = body["series"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|point| point["value"].as_f64().unwrap())
+        .collect();
+    assert_eq!(vec![1.5, 2.5, 3.5, 4.5], values);
+    assert_eq!(4.5, body["data"]);
+    assert_eq!(1_600_000_000, body["series"][3]["timestamp"]);
+}
+
+#[tokio::test]
+async fn an_unknown_period_is_rejected_before_calling_the_upstream() {
+    let app = spawn_app().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.upstream)
+        .await;
+
+    for period in ["foo", "2h", "24H"] {
+        let response = app
+            .get(&format!("/simple_moving_average/btc/{}", period))
+            .await;
+
+        assert_eq!(400, response.status().as_u16(), "{}", period);
+    }
+}
+
+#[tokio::test]
+async fn every_documented_period_is_accepted() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+
+    for period in [
+        "1h", "3h", "12h", "24h", "7d", "30d", "3m", "1y", "3y", "5y",
+    ] {
+        mount_history(&app.upstream, period, &[1.0, 2.0]).await;
+
+        let response = app
+            .get(&format!("/simple_moving_average/btc/{}", period))
+            .await;
+
+        assert_eq!(200, response.status().as_u16(), "{}", period);
+    }
+}
+
+#[tokio::test]
+async fn invalid_windows_are_rejected() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_history(&app.upstream, "24h", &[1.0, 2.0, 3.0]).await;
+
+    let zero = app.get("/simple_moving_average/btc/24h?window=0").await;
+    let too_wide = app.get("/simple_moving_average/btc/24h?window=4").await;
+
+    assert_eq!(400, zero.status().as_u16());
+    assert_eq!(422, too_wide.status().as_u16());
+}
@@ -0,0 +1,134 @@
+mod helpers;
+
+use helpers::{mount_coin, mount_ohlc, ohlc_body, spawn_app, spawn_app_with, BTC_UUID};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn ohlc_path() -> String {
+    format!("/coin/{}/ohlc", BTC_UUID)
+}
+
+#[tokio::test]
+async fn transient_server_errors_are_retried() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(ohlc_path()))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&app.upstream)
+        .await;
+    mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_short_retry_after_is_honoured() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(ohlc_path()))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+    mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_long_retry_after_is_passed_on_as_a_429() {
+    let app = spawn_app().await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(ohlc_path()))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+        .expect(1)
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(429, response.status().as_u16());
+    assert_eq!("120", response.headers()["Retry-After"]);
+}
+
+#[tokio::test]
+async fn a_slow_upstream_times_out_with_a_504() {
+    let app = spawn_app_with(|settings| {
+        settings.crypto_client.timeout_milliseconds = 100;
+        settings.crypto_client.retry.max_retries = 0;
+    })
+    .await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    Mock::given(method("GET"))
+        .and(path(ohlc_path()))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(ohlc_body(30))
+                .set_delay(Duration::from_millis(500)),
+        )
+        .mount(&app.upstream)
+        .await;
+
+    let response = app.get("/rsi/btc").await;
+
+    assert_eq!(504, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_circuit_opens_after_consecutive_failures() {
+    let app = spawn_app_with(|settings| {
+        settings.crypto_client.retry.max_retries = 0;
+        settings.crypto_client.circuit_breaker.failure_threshold = 2;
+        settings.crypto_client.cache.max_entries = 0;
+    })
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&app.upstream)
+        .await;
+
+    let first = app.get("/rsi/btc").await;
+    let second = app.get("/rsi/btc").await;
+    let third = app.get("/rsi/btc").await;
+
+    assert_eq!(502, first.status().as_u16());
+    assert_eq!(502, second.status().as_u16());
+    assert_eq!(503, third.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_circuit_closes_again_after_a_successful_trial() {
+    let app = spawn_app_with(|settings| {
+        settings.crypto_client.retry.max_retries = 0;
+        settings.crypto_client.circuit_breaker.failure_threshold = 1;
+        settings.crypto_client.circuit_breaker.open_seconds = 0;
+    })
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/search-suggestions"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&app.upstream)
+        .await;
+    mount_coin(&app.upstream, "BTC", BTC_UUID).await;
+    mount_ohlc(&app.upstream, BTC_UUID, 30).await;
+
+    let failed = app.get("/rsi/btc").await;
+    let recovered = app.get("/rsi/btc").await;
+
+    assert_eq!(502, failed.status().as_u16());
+    assert_eq!(200, recovered.status().as_u16());
+}